@@ -0,0 +1,42 @@
+#![feature(test)]
+
+extern crate test;
+extern crate infotree;
+
+use infotree::node::{Node, Rc16};
+use infotree::traits::Leaf;
+
+use test::Bencher;
+
+const TOTAL: usize = 1 << 20;
+
+#[derive(Clone)]
+struct TestLeaf(usize);
+
+impl Leaf for TestLeaf {
+    type Info = usize;
+    fn compute_info(&self) -> usize { 1 }
+}
+
+type NodeRc = Node<TestLeaf, Rc16<TestLeaf>>;
+
+#[bench]
+fn concat_leaf_huge(b: &mut Bencher) {
+    let huge: NodeRc = (0..TOTAL).map(|e| TestLeaf(e)).collect();
+    let leaf = NodeRc::from_leaf(TestLeaf(0));
+    b.iter(|| NodeRc::concat(leaf.clone(), huge.clone()))
+}
+
+#[bench]
+fn concat_huge_leaf(b: &mut Bencher) {
+    let huge: NodeRc = (0..TOTAL).map(|e| TestLeaf(e)).collect();
+    let leaf = NodeRc::from_leaf(TestLeaf(0));
+    b.iter(|| NodeRc::concat(huge.clone(), leaf.clone()))
+}
+
+#[bench]
+fn concat_halves(b: &mut Bencher) {
+    let left: NodeRc = (0..TOTAL/2).map(|e| TestLeaf(e)).collect();
+    let right: NodeRc = (TOTAL/2..TOTAL).map(|e| TestLeaf(e)).collect();
+    b.iter(|| NodeRc::concat(left.clone(), right.clone()))
+}
@@ -0,0 +1,84 @@
+#![feature(test)]
+
+extern crate test;
+extern crate infotree;
+
+use infotree::cursor::Cursor;
+use infotree::node::{Node, Rc16};
+use infotree::traits::{Leaf, SubOrd};
+
+use test::Bencher;
+
+use std::cmp::Ordering;
+
+const TOTAL: usize = 1 << 16;
+
+#[derive(Clone)]
+struct TestLeaf(usize);
+
+impl Leaf for TestLeaf {
+    type Info = usize;
+    fn compute_info(&self) -> usize { 1 }
+}
+
+type NodeRc = Node<TestLeaf, Rc16<TestLeaf>>;
+
+// A position whose comparisons are costly, such as those of long string keys.
+struct CostlyPos(usize);
+
+impl SubOrd<usize> for CostlyPos {
+    fn sub_cmp(&self, rhs: &usize) -> Ordering {
+        let mut ord = Ordering::Equal;
+        for _ in 0..256 {
+            ord = test::black_box(self.0).cmp(rhs);
+        }
+        ord
+    }
+}
+
+// Positions spread pseudo-randomly over the tree.
+fn position(i: usize) -> usize {
+    i.wrapping_mul(40503) % TOTAL
+}
+
+// Descend to the leaf at `pos` by scanning the children of each node from the first one, as seeks
+// did before they binary searched among children.
+fn linear_seek<PS: SubOrd<usize>>(cursor: &mut Cursor<TestLeaf, usize>, pos: PS) {
+    cursor.reset();
+    while cursor.descend_first().is_some() {
+        while pos.sub_cmp(&(cursor.path_info() + cursor.current().info())) != Ordering::Less &&
+              cursor.right_sibling().is_some() {}
+    }
+}
+
+fn bench_seek<F: FnMut(&mut Cursor<TestLeaf, usize>, usize)>(b: &mut Bencher, mut seek: F) {
+    let tree: NodeRc = (0..TOTAL).map(|e| TestLeaf(e)).collect();
+    let mut cursor = Cursor::<_, usize>::new(&tree);
+    let mut i = 0;
+    b.iter(|| {
+        i += 1;
+        let pos = position(i);
+        seek(&mut cursor, pos);
+        assert_eq!(cursor.leaf().unwrap().0, pos);
+    })
+}
+
+#[bench]
+fn seek_binary(b: &mut Bencher) {
+    bench_seek(b, |cursor, pos| { cursor.seek(pos); })
+}
+
+#[bench]
+fn seek_linear(b: &mut Bencher) {
+    bench_seek(b, linear_seek)
+}
+
+#[bench]
+fn seek_binary_costly(b: &mut Bencher) {
+    bench_seek(b, |cursor, pos| { cursor.seek(CostlyPos(pos)); })
+}
+
+#[bench]
+fn seek_linear_costly(b: &mut Bencher) {
+    bench_seek(b, |cursor, pos| linear_seek(cursor, CostlyPos(pos)))
+}
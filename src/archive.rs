@@ -0,0 +1,238 @@
+//! Zero-copy archiving of trees using rkyv (requires the `rkyv` feature).
+//!
+//! A tree is archived as nested nodes, each with its info and either its leaf or its children.
+//! An archive (possibly memory-mapped) can be traversed in-place by an `ArchivedCursor`, without
+//! deserializing any leaves. This makes loading large documents practically instantaneous, as
+//! only the parts of the tree that are visited are ever read.
+//!
+//! Children and leaves are addressed by offsets relative to their parent in the archive, so an
+//! archive can be memory-mapped (with the alignment required by rkyv) and opened using
+//! `archived_root` in O(1) time, regardless of its size.
+
+use node::{Node, NodesPtr};
+use traits::{Leaf, PathInfo, SubOrd};
+
+use rkyv::ser::serializers::AllocSerializer;
+use rkyv::ser::{ScratchSpace, Serializer};
+use rkyv::{AlignedVec, Archive, Archived, Deserialize, Infallible, Serialize};
+
+/// The serializable form of a node, with `I` being the info of the node. See `to_bytes`.
+#[derive(Archive, Serialize)]
+#[archive(archived = "ArchivedNode")]
+#[archive(bound(serialize = "__S: ScratchSpace + Serializer"))]
+pub enum NodeRepr<L, I> {
+    Leaf(I, L),
+    Internal(I, #[omit_bounds] Vec<NodeRepr<L, I>>),
+}
+
+type ArchiveSerializer = AllocSerializer<1024>;
+
+impl<L: Leaf> NodeRepr<L, L::Info> {
+    fn from_node<NP: NodesPtr<L>>(node: &Node<L, NP>) -> Self {
+        match node.leaf() {
+            Some(leaf) => NodeRepr::Leaf(node.info(), leaf.clone()),
+            None => NodeRepr::Internal(node.info(),
+                                       node.children().iter().map(NodeRepr::from_node).collect()),
+        }
+    }
+}
+
+/// Archive the tree at `root` into bytes, which can be accessed using `archived_root`.
+pub fn to_bytes<L, NP>(root: &Node<L, NP>) -> AlignedVec
+    where L: Leaf + Serialize<ArchiveSerializer>,
+          L::Info: Serialize<ArchiveSerializer>,
+          NP: NodesPtr<L>,
+{
+    let repr = NodeRepr::from_node(root);
+    ::rkyv::to_bytes::<_, 1024>(&repr).expect("failed to archive tree")
+}
+
+/// Access the root of a tree archived using `to_bytes`, without copying or deserializing.
+///
+/// # Safety
+///
+/// `bytes` must be the bytes returned by `to_bytes` for a tree with the same `L` (see
+/// `rkyv::archived_root`).
+pub unsafe fn archived_root<L, I>(bytes: &[u8]) -> &ArchivedNode<L, I>
+    where L: Archive, I: Archive,
+{
+    ::rkyv::archived_root::<NodeRepr<L, I>>(bytes)
+}
+
+impl<L: Archive, I: Archive> ArchivedNode<L, I>
+    where Archived<I>: Deserialize<I, Infallible>,
+{
+    /// The info of this node, which is deserialized (usually a cheap copy).
+    pub fn info(&self) -> I {
+        match *self {
+            ArchivedNode::Leaf(ref info, _) | ArchivedNode::Internal(ref info, _) => {
+                info.deserialize(&mut Infallible).unwrap()
+            }
+        }
+    }
+
+    /// The archived leaf, if this is a leaf node.
+    pub fn leaf(&self) -> Option<&Archived<L>> {
+        match *self {
+            ArchivedNode::Leaf(_, ref leaf) => Some(leaf),
+            ArchivedNode::Internal(..) => None,
+        }
+    }
+
+    /// The children of this node. Empty if this is a leaf node.
+    pub fn children(&self) -> &[ArchivedNode<L, I>] {
+        match *self {
+            ArchivedNode::Leaf(..) => &[],
+            ArchivedNode::Internal(_, ref children) => children.as_slice(),
+        }
+    }
+}
+
+// (siblings, index, path_info) of each node along the path to the current node.
+type ArchivedStep<'a, L, I, PI> = (&'a [ArchivedNode<L, I>], usize, PI);
+
+/// A read-only cursor over an archived tree, similar to `Cursor`.
+pub struct ArchivedCursor<'a, L: Archive + 'a, I: Archive + 'a, PI> {
+    root: &'a ArchivedNode<L, I>,
+    steps: Vec<ArchivedStep<'a, L, I, PI>>,
+}
+
+impl<'a, L, I, PI> ArchivedCursor<'a, L, I, PI>
+    where L: Archive, I: ::traits::Info + Archive, PI: PathInfo<I>,
+          Archived<I>: Deserialize<I, Infallible>,
+{
+    pub fn new(root: &'a ArchivedNode<L, I>) -> Self {
+        ArchivedCursor { root, steps: Vec::new() }
+    }
+
+    /// Returns a reference to the current node, where the cursor is at.
+    pub fn current(&self) -> &'a ArchivedNode<L, I> {
+        match self.steps.last() {
+            Some(&(nodes, idx, _)) => &nodes[idx],
+            None => self.root,
+        }
+    }
+
+    /// Returns a reference to the archived leaf if the current node is a leaf.
+    pub fn leaf(&self) -> Option<&'a Archived<L>> {
+        self.current().leaf()
+    }
+
+    /// The cumulative info along the path from root to this node.
+    pub fn path_info(&self) -> PI {
+        self.steps.last().map_or(PI::identity(), |&(_, _, path_info)| path_info)
+    }
+
+    pub fn reset(&mut self) {
+        self.steps.clear();
+    }
+
+    pub fn first_leaf(&mut self) -> Option<&'a Archived<L>> {
+        loop {
+            let nodes = self.current().children();
+            if nodes.is_empty() {
+                return self.leaf();
+            }
+            let path_info = self.path_info();
+            self.steps.push((nodes, 0, path_info));
+        }
+    }
+
+    pub fn last_leaf(&mut self) -> Option<&'a Archived<L>> {
+        loop {
+            let nodes = self.current().children();
+            if nodes.is_empty() {
+                return self.leaf();
+            }
+            let last = nodes.len() - 1;
+            let path_info = nodes[..last].iter()
+                                         .fold(self.path_info(), |pi, node| pi.extend(node.info()));
+            self.steps.push((nodes, last, path_info));
+        }
+    }
+
+    /// Moves to the previous leaf. Returns `None` (and resets the cursor) if there are no more
+    /// leaves.
+    pub fn prev_leaf(&mut self) -> Option<&'a Archived<L>> {
+        while let Some((nodes, idx, path_info)) = self.steps.pop() {
+            if idx > 0 {
+                self.steps.push((nodes, idx - 1, path_info.extend_inv(nodes[idx - 1].info())));
+                return self.last_leaf();
+            }
+        }
+        None
+    }
+
+    /// Moves to the next leaf. Returns `None` (and resets the cursor) if there are no more leaves.
+    pub fn next_leaf(&mut self) -> Option<&'a Archived<L>> {
+        while let Some((nodes, idx, path_info)) = self.steps.pop() {
+            if idx + 1 < nodes.len() {
+                self.steps.push((nodes, idx + 1, path_info.extend(nodes[idx].info())));
+                return self.first_leaf();
+            }
+        }
+        None
+    }
+
+    /// Moves the cursor to the first leaf which satisfies `path_info_sub <= path_info`, starting
+    /// from the root. See `CursorMut::goto_min` for details.
+    pub fn goto_min<PS: SubOrd<PI>>(&mut self, path_info_sub: PS) -> Option<&'a Archived<L>> {
+        use std::cmp::Ordering;
+
+        let satisfies = |path_info: &PI| path_info_sub.sub_cmp(path_info) != Ordering::Greater;
+
+        self.reset();
+        if satisfies(&PI::identity()) {
+            return self.first_leaf();
+        }
+        // descend to the last leaf that does not satisfy the condition
+        loop {
+            let nodes = self.current().children();
+            if nodes.is_empty() {
+                break;
+            }
+            let (mut idx, mut path_info) = (0, self.path_info());
+            while idx + 1 < nodes.len() {
+                let next_path_info = path_info.extend(nodes[idx].info());
+                if satisfies(&next_path_info) {
+                    break;
+                }
+                idx += 1;
+                path_info = next_path_info;
+            }
+            self.steps.push((nodes, idx, path_info));
+        }
+        self.next_leaf()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{archived_root, to_bytes, ArchivedCursor};
+    use test_help::*;
+
+    #[test]
+    fn cursor() {
+        let node: NodeRc<_> = (0..1000).map(ListLeaf).collect();
+        let bytes = to_bytes(&node);
+        let root = unsafe { archived_root::<ListLeaf, ListInfo>(&bytes) };
+        assert_eq!(root.info(), node.info());
+
+        let mut cursor = ArchivedCursor::<_, _, ListPath>::new(root);
+        let leaves: Vec<_> = ::std::iter::successors(cursor.first_leaf(), |_| cursor.next_leaf())
+                                 .map(|leaf| leaf.0)
+                                 .collect();
+        assert_eq!(leaves, (0..1000).collect::<Vec<_>>());
+        assert_eq!(cursor.goto_min(ListIndex(500)).map(|leaf| leaf.0), Some(500));
+        assert_eq!(cursor.path_info(), ListPath { index: 500, run: 499 * 500 / 2 });
+        assert_eq!(cursor.goto_min(ListIndex(0)).map(|leaf| leaf.0), Some(0));
+        assert!(cursor.goto_min(ListIndex(1000)).is_none());
+
+        assert_eq!(cursor.last_leaf().map(|leaf| leaf.0), Some(999));
+        assert_eq!(cursor.path_info(), ListPath { index: 999, run: 998 * 999 / 2 });
+        let leaves: Vec<_> = ::std::iter::successors(cursor.prev_leaf(), |_| cursor.prev_leaf())
+                                 .map(|leaf| leaf.0)
+                                 .collect();
+        assert_eq!(leaves, (0..999).rev().collect::<Vec<_>>());
+    }
+}
@@ -0,0 +1,480 @@
+//! A compact binary format for trees, preserving the subtrees shared among them.
+//!
+//! Versions of a tree derived from each other through copy-on-write editing share most of their
+//! internal nodes. A `Writer` writes each internal node only once (identified by pointer), and
+//! refers to it by id thereafter. So writing a history of many versions takes space proportional
+//! to the total size of the edits rather than the number of versions. Reading it back restores
+//! the sharing among the trees.
+//!
+//! The format is a header followed by a sequence of records:
+//!
+//! - leaf: `0`, followed by the leaf as encoded by `LeafCodec`.
+//! - internal node: `1`, the number of children, and the id of each child.
+//! - root: `2`, and the id of the root node.
+//!
+//! Nodes are assigned ids in the order they are written, starting from 0. Integers (other than
+//! the record tags) are written as LEB128 varints. Each record is preceded by its length in bytes,
+//! and followed by a checksum of it (32-bit FNV-1a, little-endian). A record is decoded only after
+//! its checksum is verified, so that corruption is detected while reading (before decoding any
+//! leaf out of corrupt bytes), and reported as a `CorruptData` error with the offset of the
+//! corrupt record.
+//!
+//! Trees can also be streamed as just their leaves (see `write_leaves`), using a header of its
+//! own followed by only leaf records. The internal nodes are rebuilt when reading them back.
+
+use builder::TreeBuilder;
+use node::{Node, NodesPtr};
+use traits::Leaf;
+
+use arrayvec::ArrayVec;
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::io::{self, Read, Write};
+
+const MAGIC: &[u8; 4] = b"ITR\x03";
+const LEAVES_MAGIC: &[u8; 4] = b"ITL\x02";
+
+const TAG_LEAF: u8 = 0;
+const TAG_INTERNAL: u8 = 1;
+const TAG_ROOT: u8 = 2;
+
+/// A leaf that can be written in a binary format.
+pub trait LeafCodec: Leaf {
+    fn encode<W: Write>(&self, out: &mut W) -> io::Result<()>;
+
+    fn decode<R: Read>(input: &mut R) -> io::Result<Self>;
+}
+
+/// The error (wrapped in an `io::Error` of kind `InvalidData`) returned by `read` for a file that
+/// is corrupt, describing where the corruption was found.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CorruptData {
+    /// The byte offset of the start of the corrupt record in the file.
+    pub offset: u64,
+    /// The id of the node that the record would have defined (which is the number of nodes read
+    /// before it). For `read_leaves`, this is the index of the leaf.
+    pub node_id: usize,
+    pub reason: &'static str,
+}
+
+impl fmt::Display for CorruptData {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "corrupt record at offset {} (node {}): {}", self.offset, self.node_id,
+               self.reason)
+    }
+}
+
+impl Error for CorruptData {}
+
+/// Writes trees to `W` in the binary format described in the module documentation.
+pub struct Writer<W: Write, L: Leaf, NP: NodesPtr<L>> {
+    out: W,
+    ids: HashMap<*const Node<L, NP>, usize>,
+    // keeps written nodes alive, so that their pointers are not reused by other nodes
+    written: Vec<Node<L, NP>>,
+    next_id: usize,
+}
+
+impl<W, L, NP> Writer<W, L, NP>
+    where W: Write, L: LeafCodec, NP: NodesPtr<L>,
+{
+    /// Create a writer, writing the header to `out`.
+    pub fn new(mut out: W) -> io::Result<Self> {
+        out.write_all(MAGIC)?;
+        Ok(Writer { out, ids: HashMap::new(), written: Vec::new(), next_id: 0 })
+    }
+
+    /// Read all trees written to a file (as `read` does), and create a writer that appends to it
+    /// through `out`. The subtrees of the trees read are not written again by this writer, so
+    /// persisting an edited version of them writes only the nodes changed since.
+    ///
+    /// Returns the trees read along with the writer.
+    pub fn resume<R: Read>(input: R, out: W) -> io::Result<(Vec<Node<L, NP>>, Self)> {
+        let (nodes, roots) = read_nodes(input)?;
+        let ids = nodes.iter().enumerate()
+                       .filter(|&(_, node)| !node.is_leaf())
+                       .map(|(id, node)| (node.children().as_ptr(), id))
+                       .collect();
+        let next_id = nodes.len();
+        Ok((roots, Writer { out, ids, written: nodes, next_id }))
+    }
+
+    /// Write `root`, skipping all subtrees already written by this writer.
+    pub fn write(&mut self, root: &Node<L, NP>) -> io::Result<()> {
+        let id = self.write_node(root)?;
+        let mut record = vec![TAG_ROOT];
+        write_varint(&mut record, id as u64)?;
+        self.write_record(&record)
+    }
+
+    // Write `node` (post-order) unless it was already written, and returns its id.
+    fn write_node(&mut self, node: &Node<L, NP>) -> io::Result<usize> {
+        match node.leaf() {
+            Some(leaf) => {
+                let mut record = vec![TAG_LEAF];
+                leaf.encode(&mut record)?;
+                self.write_record(&record)?;
+            }
+            None => {
+                let key = node.children().as_ptr();
+                if let Some(&id) = self.ids.get(&key) {
+                    return Ok(id);
+                }
+                let mut child_ids = Vec::with_capacity(node.children().len());
+                for child in node.children() {
+                    child_ids.push(self.write_node(child)?);
+                }
+                let mut record = vec![TAG_INTERNAL];
+                write_varint(&mut record, child_ids.len() as u64)?;
+                for &child_id in &child_ids {
+                    write_varint(&mut record, child_id as u64)?;
+                }
+                self.write_record(&record)?;
+                self.ids.insert(key, self.next_id);
+                self.written.push(node.clone());
+            }
+        }
+        self.next_id += 1;
+        Ok(self.next_id - 1)
+    }
+
+    fn write_record(&mut self, record: &[u8]) -> io::Result<()> {
+        write_record(&mut self.out, record)
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+
+    pub fn into_inner(self) -> W {
+        self.out
+    }
+}
+
+/// Read all trees written by a `Writer`, in order. Subtrees shared among the trees when written
+/// are shared among the returned trees as well.
+///
+/// A corrupt file results in an `InvalidData` error wrapping a `CorruptData`, which can be found
+/// using `io::Error::get_ref`.
+pub fn read<R, L, NP>(input: R) -> io::Result<Vec<Node<L, NP>>>
+    where R: Read, L: LeafCodec, NP: NodesPtr<L>,
+{
+    read_nodes(input).map(|(_, roots)| roots)
+}
+
+// All nodes in a file (indexed by id), and the roots.
+type NodesRoots<L, NP> = (Vec<Node<L, NP>>, Vec<Node<L, NP>>);
+
+// Read all nodes and roots from `input`.
+fn read_nodes<R, L, NP>(input: R) -> io::Result<NodesRoots<L, NP>>
+    where R: Read, L: LeafCodec, NP: NodesPtr<L>,
+{
+    let mut input = RecordReader { inner: input, offset: 0 };
+    let mut magic = [0; 4];
+    input.read_exact(&mut magic)?;
+    if &magic != MAGIC {
+        return Err(invalid_data("not a tree file"));
+    }
+    let mut nodes: Vec<Node<L, NP>> = Vec::new();
+    let mut roots = Vec::new();
+    while let Some((start, record)) = input.next_record(nodes.len())? {
+        let id = nodes.len();
+        let corrupt = |reason| corrupt_data(start, id, reason);
+        let (tag, mut body) = match record.split_first() {
+            Some((&tag, body)) => (tag, body),
+            None => return Err(corrupt("invalid record")),
+        };
+        let node = match tag {
+            TAG_LEAF => {
+                let leaf = L::decode(&mut body).map_err(|_| corrupt("invalid leaf"))?;
+                Some(Node::from_leaf(leaf))
+            }
+            TAG_INTERNAL => {
+                let len = read_varint(&mut body).map_err(|_| corrupt("invalid record"))? as usize;
+                if len == 0 || len > NP::max_size() {
+                    return Err(corrupt("invalid number of children"));
+                }
+                let mut children = ArrayVec::new();
+                for _ in 0..len {
+                    let child = read_id(&mut body, &nodes).map_err(corrupt)?.clone();
+                    if child.height() != children.first().map_or(child.height(), Node::height) {
+                        return Err(corrupt("children of different heights"));
+                    }
+                    children.push(child);
+                }
+                Some(Node::from_children(NP::new(children)))
+            }
+            TAG_ROOT => {
+                roots.push(read_id(&mut body, &nodes).map_err(corrupt)?.clone());
+                None
+            }
+            _ => return Err(corrupt("invalid record")),
+        };
+        if !body.is_empty() {
+            return Err(corrupt("trailing bytes in record"));
+        }
+        nodes.extend(node);
+    }
+    Ok((nodes, roots))
+}
+
+/// Write the leaves of `root` to `out` in order, in a single pass. Only the leaves are written,
+/// without any sharing of subtrees, and no state is kept other than the traversal. So this is
+/// suitable for persisting huge trees. Use `read_leaves` to read them back.
+pub fn write_leaves<W, L, NP>(mut out: W, root: &Node<L, NP>) -> io::Result<()>
+    where W: Write, L: LeafCodec, NP: NodesPtr<L>,
+{
+    out.write_all(LEAVES_MAGIC)?;
+    let mut record = Vec::new();
+    for leaf in root.leaves() {
+        record.clear();
+        record.push(TAG_LEAF);
+        leaf.encode(&mut record)?;
+        write_record(&mut out, &record)?;
+    }
+    Ok(())
+}
+
+/// Read the leaves written by `write_leaves` into a balanced tree, as they are read (using a
+/// `TreeBuilder`). Returns `None` if there were no leaves.
+pub fn read_leaves<R, L, NP>(input: R) -> io::Result<Option<Node<L, NP>>>
+    where R: Read, L: LeafCodec, NP: NodesPtr<L>,
+{
+    let mut input = RecordReader { inner: input, offset: 0 };
+    let mut magic = [0; 4];
+    input.read_exact(&mut magic)?;
+    if &magic != LEAVES_MAGIC {
+        return Err(invalid_data("not a leaves file"));
+    }
+    let mut builder = TreeBuilder::new();
+    for index in 0.. {
+        let (start, record) = match input.next_record(index)? {
+            Some(record) => record,
+            None => break,
+        };
+        let corrupt = |reason| corrupt_data(start, index, reason);
+        let mut body = match record.split_first() {
+            Some((&TAG_LEAF, body)) => body,
+            _ => return Err(corrupt("invalid record")),
+        };
+        let leaf = L::decode(&mut body).map_err(|_| corrupt("invalid leaf"))?;
+        if !body.is_empty() {
+            return Err(corrupt("trailing bytes in record"));
+        }
+        builder.push(leaf);
+    }
+    Ok(builder.build())
+}
+
+fn write_record<W: Write>(out: &mut W, record: &[u8]) -> io::Result<()> {
+    write_varint(out, record.len() as u64)?;
+    out.write_all(record)?;
+    out.write_all(&checksum(record).to_le_bytes())
+}
+
+// Reads from `inner`, keeping track of the offset for reporting corrupt records.
+struct RecordReader<R> {
+    inner: R,
+    offset: u64,
+}
+
+impl<R: Read> Read for RecordReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = self.inner.read(buf)?;
+        self.offset += len as u64;
+        Ok(len)
+    }
+}
+
+impl<R: Read> RecordReader<R> {
+    // Read the next record, and return its offset along with its bytes once its checksum is
+    // verified, or `None` at the end of input. `id` is the node id reported if it is corrupt.
+    fn next_record(&mut self, id: usize) -> io::Result<Option<(u64, Vec<u8>)>> {
+        let start = self.offset;
+        let corrupt = |reason| corrupt_data(start, id, reason);
+        let mut first = [0];
+        if self.read(&mut first)? == 0 {
+            return Ok(None);
+        }
+        let len = read_varint(&mut (&first[..]).chain(&mut *self))
+            .map_err(|_| corrupt("invalid record length"))?;
+        // not allocated upfront, since the length may be corrupt
+        let mut record = Vec::new();
+        (&mut *self).take(len).read_to_end(&mut record)?;
+        let mut found = [0; 4];
+        if record.len() as u64 != len || self.read_exact(&mut found).is_err() {
+            return Err(corrupt("truncated record"));
+        }
+        if u32::from_le_bytes(found) != checksum(&record) {
+            return Err(corrupt("checksum mismatch"));
+        }
+        Ok(Some((start, record)))
+    }
+}
+
+// The 32-bit FNV-1a hash of `bytes`.
+fn checksum(bytes: &[u8]) -> u32 {
+    bytes.iter().fold(0x811c_9dc5, |hash, &byte| (hash ^ u32::from(byte)).wrapping_mul(0x0100_0193))
+}
+
+fn read_id<'a, R: Read, L: Leaf, NP: NodesPtr<L>>(input: &mut R, nodes: &'a [Node<L, NP>])
+    -> Result<&'a Node<L, NP>, &'static str>
+{
+    let id = read_varint(input).map_err(|_| "invalid node id")? as usize;
+    nodes.get(id).ok_or("invalid node id")
+}
+
+fn corrupt_data(offset: u64, node_id: usize, reason: &'static str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, CorruptData { offset, node_id, reason })
+}
+
+pub(crate) fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+pub(crate) fn write_varint<W: Write>(out: &mut W, mut value: u64) -> io::Result<()> {
+    let mut buf = ArrayVec::<[u8; 10]>::new();
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            buf.push(byte);
+            break;
+        }
+        buf.push(byte | 0x80);
+    }
+    out.write_all(&buf)
+}
+
+pub(crate) fn read_varint<R: Read>(input: &mut R) -> io::Result<u64> {
+    let mut value = 0;
+    for shift in (0..64).step_by(7) {
+        let mut byte = [0];
+        input.read_exact(&mut byte)?;
+        value |= u64::from(byte[0] & 0x7f) << shift;
+        if byte[0] & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err(invalid_data("varint too long"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{read, read_leaves, write_leaves, write_record, CorruptData, Writer, LEAVES_MAGIC,
+                TAG_LEAF};
+    use leaf::ArcStr;
+    use test_help::*;
+
+    fn corrupt_data(err: &::std::io::Error) -> &CorruptData {
+        err.get_ref().and_then(|err| err.downcast_ref::<CorruptData>()).unwrap()
+    }
+
+    #[test]
+    fn shared_history() {
+        let mut cursor_mut: CursorMutT<_> = (0..1000).map(ListLeaf).collect();
+        let mut versions = Vec::new();
+        for i in 0..10 {
+            cursor_mut.reset();
+            cursor_mut.first_leaf();
+            cursor_mut.leaf_update(|leaf| leaf.0 = i);
+            versions.push(cursor_mut.clone().into_root().unwrap());
+        }
+        let mut writer = Writer::new(Vec::new()).unwrap();
+        writer.write(&versions[0]).unwrap();
+        let one = writer.into_inner().len();
+        let mut writer = Writer::new(Vec::new()).unwrap();
+        for version in &versions {
+            writer.write(version).unwrap();
+        }
+        let bytes = writer.into_inner();
+        assert!(bytes.len() < one + one / 2);
+
+        let trees: Vec<NodeRc<ListLeaf>> = read(&bytes[..]).unwrap();
+        assert_eq!(trees.len(), 10);
+        for (i, (node, version)) in trees.iter().zip(&versions).enumerate() {
+            assert_eq!(node.info(), version.info());
+            assert_eq!(CursorT::new(node).first_leaf(), Some(&ListLeaf(i)));
+        }
+        assert!(trees[0].children()[1].ptr_eq(&trees[9].children()[1]));
+        assert!(read::<_, ListLeaf, ::node::Rc16<_>>(&bytes[1..]).is_err());
+    }
+
+    #[test]
+    fn resume() {
+        let node: NodeRc<_> = (0..1000).map(ListLeaf).collect();
+        let mut writer = Writer::new(Vec::new()).unwrap();
+        writer.write(&node).unwrap();
+        let mut bytes = writer.into_inner();
+
+        let (roots, mut writer) = Writer::resume(&bytes[..], Vec::new()).unwrap();
+        let root = roots.into_iter().next().unwrap();
+        let mut cursor_mut = ::cursor::CursorMut::<_, ListPath>::from_node(root);
+        cursor_mut.goto_min(ListIndex(500));
+        cursor_mut.leaf_update(|leaf: &mut ListLeaf| leaf.0 = 0);
+        writer.write(&cursor_mut.into_root().unwrap()).unwrap();
+        let appended = writer.into_inner();
+        assert!(appended.len() < bytes.len() / 10);
+
+        bytes.extend(appended);
+        let trees: Vec<NodeRc<ListLeaf>> = read(&bytes[..]).unwrap();
+        assert_eq!(trees.len(), 2);
+        assert_eq!(trees[1].info().sum, trees[0].info().sum - 500);
+        assert!(trees[0].children()[0].ptr_eq(&trees[1].children()[0]));
+    }
+
+    #[test]
+    fn leaves() {
+        let node: NodeRc<_> = (0..1000).map(ListLeaf).collect();
+        let mut bytes = Vec::new();
+        write_leaves(&mut bytes, &node).unwrap();
+        let read: NodeRc<ListLeaf> = read_leaves(&bytes[..]).unwrap().unwrap();
+        assert_balanced(&read);
+        assert!(read.leaves().eq(node.leaves()));
+
+        let last = bytes.len() - 1;
+        bytes[last] ^= 1;
+        let err = read_leaves::<_, ListLeaf, ::node::Rc16<_>>(&bytes[..]).unwrap_err();
+        let corrupt = err.get_ref().and_then(|err| err.downcast_ref::<CorruptData>()).unwrap();
+        assert_eq!(corrupt.node_id, 999);
+    }
+
+    #[test]
+    fn corrupt_leaf() {
+        let node: NodeRc<_> = (0..10).map(|i| ArcStr::new(format!("leaf {}", i))).collect();
+        let mut bytes = Vec::new();
+        write_leaves(&mut bytes, &node).unwrap();
+        // the length of the first leaf, after the header, the record length and the tag
+        for byte in &mut bytes[6..12] {
+            *byte = 0xff;
+        }
+        let err = read_leaves::<_, ArcStr, ::node::Rc16<_>>(&bytes[..]).unwrap_err();
+        assert_eq!(corrupt_data(&err), &CorruptData {
+            offset: 4,
+            node_id: 0,
+            reason: "checksum mismatch",
+        });
+
+        // a record with a valid checksum, but invalid UTF-8
+        let mut bytes = LEAVES_MAGIC.to_vec();
+        write_record(&mut bytes, &[TAG_LEAF, 2, 0xff, 0xfe]).unwrap();
+        let err = read_leaves::<_, ArcStr, ::node::Rc16<_>>(&bytes[..]).unwrap_err();
+        assert_eq!(corrupt_data(&err).reason, "invalid leaf");
+    }
+
+    #[test]
+    fn corruption() {
+        let node: NodeRc<_> = (0..100).map(ListLeaf).collect();
+        let mut writer = Writer::new(Vec::new()).unwrap();
+        writer.write(&node).unwrap();
+        let mut bytes = writer.into_inner();
+        let at = bytes.len() / 2;
+        bytes[at] ^= 0x40;
+        let err = read::<_, ListLeaf, ::node::Rc16<_>>(&bytes[..]).unwrap_err();
+        let corrupt = err.get_ref().and_then(|err| err.downcast_ref::<CorruptData>()).unwrap();
+        assert!(corrupt.offset <= at as u64 && at as u64 - corrupt.offset < 16);
+        assert!(corrupt.node_id > 0);
+    }
+}
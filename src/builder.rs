@@ -0,0 +1,403 @@
+//! Bulk construction of trees from leaves in order.
+
+use iter::IntoLeaves;
+use node::{Node, NodesPtr};
+use shared::SharedTree;
+use traits::{Leaf, LeafMerge};
+
+use arrayvec::ArrayVec;
+
+use std::iter::FromIterator;
+
+/// Builds a balanced tree out of leaves appended or prepended in order, in O(n) time.
+///
+/// Only the edges of the tree being built are kept open, as lists of partially filled nodes per
+/// height (similar to the spines of a finger tree). Whenever one of them gets full, it is closed
+/// and pushed to the next height. Thus, `push_back` and `push_front` take amortized O(1) time.
+pub struct TreeBuilder<L: Leaf, NP: NodesPtr<L>> {
+    front: Vec<ArrayVec<NP::Array>>, // front[h] contains open nodes of height h in reverse order
+    back: Vec<ArrayVec<NP::Array>>, // back[h] contains open nodes of height h
+}
+
+impl<L: Leaf, NP: NodesPtr<L>> Default for TreeBuilder<L, NP> {
+    fn default() -> Self {
+        TreeBuilder::new()
+    }
+}
+
+impl<L: Leaf, NP: NodesPtr<L>> TreeBuilder<L, NP> {
+    pub fn new() -> Self {
+        TreeBuilder { front: Vec::new(), back: Vec::new() }
+    }
+
+    /// Create a builder that appends to (and prepends to) the leaves of `root`.
+    ///
+    /// Time: O(log n)
+    pub fn from_node(root: Node<L, NP>) -> Self {
+        let mut builder = TreeBuilder::new();
+        // open the right edge of root
+        let mut node = root;
+        for _ in 0..node.height() + 1 {
+            builder.back.push(ArrayVec::new());
+        }
+        loop {
+            let height = node.height();
+            match node.into_children() {
+                Ok(mut nodes) => {
+                    let last = {
+                        let nodes = NP::make_mut(&mut nodes);
+                        let last = nodes.pop().unwrap();
+                        builder.back[height - 1].extend(nodes.drain(..));
+                        last
+                    };
+                    node = last;
+                }
+                Err(leaf) => {
+                    builder.back[0].push(leaf);
+                    break;
+                }
+            }
+        }
+        builder
+    }
+
+    /// Append `leaf` to the tree.
+    pub fn push(&mut self, leaf: L) {
+        self.push_back(leaf);
+    }
+
+    /// Append `leaf` to the tree.
+    pub fn push_back(&mut self, leaf: L) {
+        Self::push_node(&mut self.back, Node::from_leaf(leaf), 0, false);
+    }
+
+    /// Prepend `leaf` to the tree.
+    pub fn push_front(&mut self, leaf: L) {
+        Self::push_node(&mut self.front, Node::from_leaf(leaf), 0, true);
+    }
+
+    // Add `node` of height `height` to the open nodes of an edge.
+    fn push_node(levels: &mut Vec<ArrayVec<NP::Array>>, node: Node<L, NP>, height: usize,
+                 reversed: bool) {
+        if levels.len() == height {
+            levels.push(ArrayVec::new());
+        }
+        if let Some(node) = levels[height].push(node) {
+            // the open node at this height is full
+            let full = Self::close(&mut levels[height], reversed);
+            Self::push_node(levels, full, height + 1, reversed);
+            levels[height].push(node);
+        }
+    }
+
+    // Create a node out of `nodes`, leaving it empty. Returns `nodes[0]` if it is the only one.
+    fn close(nodes: &mut ArrayVec<NP::Array>, reversed: bool) -> Node<L, NP> {
+        if nodes.len() == 1 {
+            nodes.pop().unwrap()
+        } else if reversed {
+            Node::from_children(NP::new(nodes.drain(..).rev().collect()))
+        } else {
+            Node::from_children(NP::new(nodes.drain(..).collect()))
+        }
+    }
+
+    /// Returns the root of the tree built, or `None` if the tree is empty.
+    ///
+    /// Time: O(log^2 n)
+    pub fn build(self) -> Option<Node<L, NP>> {
+        let front = self.front.into_iter().map(|nodes| (nodes, true));
+        let back = self.back.into_iter().rev().map(|nodes| (nodes, false));
+        let mut root: Option<Node<L, NP>> = None;
+        for (mut nodes, reversed) in front.chain(back) {
+            if nodes.is_empty() {
+                continue;
+            }
+            let node = Self::close(&mut nodes, reversed);
+            root = Some(match root {
+                Some(root) => Node::concat(root, node),
+                None => node,
+            });
+        }
+        root
+    }
+}
+
+impl<L: Leaf, NP: NodesPtr<L>> Extend<L> for TreeBuilder<L, NP> {
+    fn extend<I: IntoIterator<Item=L>>(&mut self, iter: I) {
+        for leaf in iter {
+            self.push(leaf);
+        }
+    }
+}
+
+impl<L: Leaf, NP: NodesPtr<L>> FromIterator<L> for TreeBuilder<L, NP> {
+    fn from_iter<I: IntoIterator<Item=L>>(iter: I) -> Self {
+        let mut builder = TreeBuilder::new();
+        builder.extend(iter);
+        builder
+    }
+}
+
+impl<L: Leaf, NP: NodesPtr<L>> Node<L, NP> {
+    /// Rebuild this tree so that all internal nodes are well-filled and the tree is of minimal
+    /// height, preserving the order of leaves. Leaves are moved (not cloned) out of nodes that are
+    /// not shared with other trees.
+    ///
+    /// A tree that went through many edits may have a lot of half-empty nodes, making it deeper
+    /// than necessary. See `stats::Census::fill_factor` for detecting this.
+    ///
+    /// Time: O(n)
+    pub fn compact(&mut self) {
+        self.compact_with(|_, _| false);
+    }
+
+    /// Same as `compact`, but also merges adjacent leaves using `LeafMerge::try_merge`.
+    pub fn compact_leaves(&mut self) where L: LeafMerge {
+        self.compact_with(|leaf, next| leaf.try_merge(next));
+    }
+
+    // Rebuild the tree with `merge(leaf, next)` called on adjacent leaves, where `next` is
+    // dropped if it returns true. If `merge` panics, the tree is rebuilt out of the leaves
+    // processed so far and the remaining ones, so that it is not left as `Never`.
+    fn compact_with<F>(&mut self, mut merge: F) where F: FnMut(&mut L, &L) -> bool {
+        let leaves = self.never_take().into_iter();
+        let mut guard = CompactGuard {
+            root: self,
+            leaves,
+            pending: None,
+            next: None,
+            builder: TreeBuilder::new(),
+        };
+        for leaf in guard.leaves.by_ref() {
+            guard.next = Some(leaf);
+            push_merged(&mut guard.builder, &mut guard.pending, &mut guard.next, &mut merge);
+        }
+    } // guard assigns the compacted tree to `self`
+}
+
+// The state of `Node::compact_with`, which puts the (possibly partially) compacted tree back into
+// `root` when dropped, including during unwinding.
+struct CompactGuard<'a, L: Leaf + 'a, NP: NodesPtr<L> + 'a> {
+    root: &'a mut Node<L, NP>,
+    leaves: IntoLeaves<L, NP>,
+    pending: Option<L>, // the last leaf processed, yet to be pushed to builder
+    next: Option<L>, // the leaf being merged into pending
+    builder: TreeBuilder<L, NP>,
+}
+
+impl<'a, L: Leaf, NP: NodesPtr<L>> Drop for CompactGuard<'a, L, NP> {
+    fn drop(&mut self) {
+        let mut builder = ::std::mem::take(&mut self.builder);
+        builder.extend(self.pending.take());
+        builder.extend(self.next.take());
+        builder.extend(&mut self.leaves);
+        if let Some(root) = builder.build() {
+            *self.root = root;
+        }
+    }
+}
+
+// Push `pending` (if any) to `builder` and replace it with the leaf in `next`, unless that leaf
+// can be merged into `pending` (then it is dropped). Both leaves are left in place while `merge`
+// runs, so that they are not lost if it panics (see `CompactGuard`).
+fn push_merged<L, NP, F>(builder: &mut TreeBuilder<L, NP>, pending: &mut Option<L>,
+                         next: &mut Option<L>, merge: &mut F)
+    where L: Leaf, NP: NodesPtr<L>, F: FnMut(&mut L, &L) -> bool,
+{
+    let merged = match (pending.as_mut(), next.as_ref()) {
+        (Some(prev), Some(next)) => merge(prev, next),
+        _ => false,
+    };
+    if merged {
+        *next = None;
+    } else {
+        builder.extend(pending.take());
+        *pending = next.take();
+    }
+}
+
+/// The state of compacting a tree (as `Node::compact` does) incrementally, a bounded number of
+/// leaves at a time, so that an application can compact a large tree in idle time slices.
+///
+/// The compacted tree is built from a snapshot of the tree, which remains unaffected by edits to
+/// the tree meanwhile. The result should thus be used only if the tree has not changed since.
+pub struct CompactionState<L: Leaf, NP: NodesPtr<L>> {
+    source: Node<L, NP>,
+    leaves: IntoLeaves<L, NP>,
+    pending: Option<L>,
+    builder: TreeBuilder<L, NP>,
+    merge: fn(&mut L, &L) -> bool,
+}
+
+impl<L: Leaf, NP: NodesPtr<L>> CompactionState<L, NP> {
+    /// Start compacting (a snapshot of) `root`.
+    pub fn new(root: &Node<L, NP>) -> Self {
+        Self::with_merge(root, |_, _| false)
+    }
+
+    /// Same as `new`, but also merges adjacent leaves as `Node::compact_leaves` does.
+    pub fn merging(root: &Node<L, NP>) -> Self where L: LeafMerge {
+        Self::with_merge(root, L::try_merge)
+    }
+
+    fn with_merge(root: &Node<L, NP>, merge: fn(&mut L, &L) -> bool) -> Self {
+        CompactionState {
+            source: root.clone(),
+            leaves: root.clone().into_iter(),
+            pending: None,
+            builder: TreeBuilder::new(),
+            merge,
+        }
+    }
+
+    /// The tree being compacted.
+    pub fn source(&self) -> &Node<L, NP> {
+        &self.source
+    }
+
+    /// Process up to `budget` leaves. Returns whether all leaves have been processed.
+    pub fn step(&mut self, budget: usize) -> bool {
+        for _ in 0..budget {
+            match self.leaves.next() {
+                Some(leaf) => push_merged(&mut self.builder, &mut self.pending, &mut Some(leaf),
+                                          &mut self.merge),
+                None => return true,
+            }
+        }
+        false
+    }
+
+    /// Process the remaining leaves, and return the compacted tree.
+    pub fn finish(mut self) -> Node<L, NP> {
+        while !self.step(usize::MAX) {}
+        self.builder.extend(self.pending);
+        self.builder.build().unwrap()
+    }
+
+    /// Finish the compaction, and publish the compacted tree to `shared` if its root is still the
+    /// tree being compacted. Returns whether it was published.
+    ///
+    /// A tree of a single leaf is never published, since it is always compact.
+    pub fn publish(self, shared: &SharedTree<L, NP>) -> bool {
+        let source = self.source.clone();
+        let compacted = self.finish();
+        let mut published = false;
+        shared.update(|root| match root {
+            Some(ref root) if root.ptr_eq(&source) => {
+                published = true;
+                Some(compacted)
+            }
+            root => root,
+        });
+        published
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TreeBuilder;
+    use test_help::*;
+
+    #[test]
+    fn build() {
+        assert!(TreeBuilder::<ListLeaf, ::node::Rc16<_>>::new().build().is_none());
+        for &len in &[1, 2, 15, 16, 17, 100, 256, 257, 1000, 4097] {
+            let builder: TreeBuilder<_, _> = (0..len).map(ListLeaf).collect();
+            let node: NodeRc<_> = builder.build().unwrap();
+            assert_balanced(&node);
+            let leaves: Vec<_> = CursorT::new(&node).into_iter().map(|leaf| leaf.0).collect();
+            assert_eq!(leaves, (0..len).collect::<Vec<_>>());
+        }
+    }
+
+    #[test]
+    fn push_front_back() {
+        let node: NodeRc<_> = (1000..1300).map(ListLeaf).collect();
+        let mut builder = TreeBuilder::from_node(node);
+        for i in 0..1000 {
+            builder.push_front(ListLeaf(999 - i));
+            builder.push_back(ListLeaf(1300 + i));
+        }
+        let mut node = builder.build().unwrap();
+        node.push_back(ListLeaf(2300));
+        node.push_front(ListLeaf(0));
+        assert_balanced(&node);
+        let leaves: Vec<_> = CursorT::new(&node).into_iter().map(|leaf| leaf.0).collect();
+        assert_eq!(leaves, Some(0).into_iter().chain(0..2301).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn compact() {
+        let mut cursor_mut: ::cursor::CursorMut<_, ListPath> = (0..1000).map(ListLeaf).collect();
+        for i in 0..900 {
+            cursor_mut.reset();
+            cursor_mut.goto_min(ListIndex(i % 97));
+            cursor_mut.remove_leaf();
+        }
+        let mut node = cursor_mut.into_root().unwrap();
+        let expected: Vec<_> = CursorT::new(&node).into_iter().cloned().collect();
+        assert!(node.census().nodes[1] > 7);
+        node.compact();
+        assert_balanced(&node);
+        assert!(node.census().nodes[1] <= 7); // 100 leaves
+        assert_eq!(CursorT::new(&node).into_iter().cloned().collect::<Vec<_>>(), expected);
+
+        let text = ::leaf::ArcStr::new("hello world");
+        let mut node: NodeRc<_> = (0..11).map(|i| text.slice(i..i + 1)).collect();
+        node.compact_leaves();
+        assert_eq!(node.leaf().map(|leaf| &**leaf), Some("hello world"));
+    }
+
+    #[test]
+    fn compact_panic() {
+        let mut node: NodeRc<_> = (0..100).map(ListLeaf).collect();
+        let result = ::std::panic::catch_unwind(::std::panic::AssertUnwindSafe(|| {
+            node.compact_with(|leaf, next| {
+                assert!(next.0 != 50, "merge failed");
+                if next.0 % 2 == 1 {
+                    leaf.0 += next.0;
+                }
+                next.0 % 2 == 1
+            });
+        }));
+        assert!(result.is_err());
+        assert_balanced(&node);
+        let leaves: Vec<_> = CursorT::new(&node).into_iter().map(|leaf| leaf.0).collect();
+        let mut expected: Vec<_> = (0..25).map(|i| 4 * i + 1).collect();
+        expected.extend(50..100);
+        assert_eq!(leaves, expected);
+    }
+
+    #[test]
+    fn incremental_compaction() {
+        use super::CompactionState;
+        use node::Arc16;
+        use shared::SharedTree;
+
+        let mut cursor_mut: ::cursor::CursorMut<_, ListPath, ::cursor::conf::Arc33M> =
+            (0..1000).map(ListLeaf).collect();
+        for i in 0..900 {
+            cursor_mut.reset();
+            cursor_mut.goto_min(ListIndex(i % 97));
+            cursor_mut.remove_leaf();
+        }
+        let shared = SharedTree::<_, Arc16<_>>::new(cursor_mut.into_root());
+        let expected: Vec<_> = shared.snapshot().unwrap().into_iter().collect();
+
+        let mut compaction = CompactionState::new(&shared.snapshot().unwrap());
+        let mut steps = 1;
+        while !compaction.step(10) {
+            steps += 1;
+        }
+        assert_eq!(steps, 11);
+        assert!(compaction.publish(&shared));
+        let root = shared.snapshot().unwrap();
+        assert!(root.census().nodes[1] <= 7);
+        assert_eq!(root.into_iter().collect::<Vec<_>>(), expected);
+
+        let compaction = CompactionState::new(&shared.snapshot().unwrap());
+        shared.publish(Some((0..10).map(ListLeaf).collect()));
+        assert!(!compaction.publish(&shared));
+        assert_eq!(shared.snapshot().unwrap().info().count, 10);
+    }
+}
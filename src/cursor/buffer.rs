@@ -0,0 +1,140 @@
+use super::conf::{CMutConf, Rc33M};
+use super::CursorMut;
+use traits::{Leaf, PathInfo};
+use node::{Node, NodesPtr};
+
+use arrayvec::ArrayVec;
+
+/// A write buffer for inserting a run of consecutive leaves next to the current node of a
+/// `CursorMut`, obtained using `CursorMut::write_buffer`.
+///
+/// Inserting leaves one at a time makes the path to the cursor writable and rebalances the tree
+/// for each leaf. This buffer instead collects the leaves into well-filled groups, and inserts all
+/// of them as a single subtree when flushed. The buffer is flushed when dropped, which is also
+/// when the cursor becomes accessible again.
+pub struct WriteBuffer<'a, L, PI, CONF = Rc33M>
+    where L: Leaf + 'a,
+          PI: PathInfo<L::Info> + 'a,
+          CONF: CMutConf<L, PI> + 'a,
+{
+    cursor: &'a mut CursorMut<L, PI, CONF>,
+    after: bool,
+    len: usize,
+    filled: Option<Node<L, CONF::Ptr>>,
+    pending: ArrayVec<<CONF::Ptr as NodesPtr<L>>::Array>,
+}
+
+impl<L, PI, CONF> CursorMut<L, PI, CONF>
+    where L: Leaf,
+          PI: PathInfo<L::Info>,
+          CONF: CMutConf<L, PI>,
+{
+    /// Returns a buffer that inserts leaves pushed to it, in order, before or after the current
+    /// node (as per `after`). See `insert_leaf` for more details.
+    pub fn write_buffer(&mut self, after: bool) -> WriteBuffer<'_, L, PI, CONF> {
+        WriteBuffer {
+            cursor: self,
+            after,
+            len: 0,
+            filled: None,
+            pending: ArrayVec::new(),
+        }
+    }
+}
+
+impl<'a, L, PI, CONF> WriteBuffer<'a, L, PI, CONF>
+    where L: Leaf,
+          PI: PathInfo<L::Info>,
+          CONF: CMutConf<L, PI>,
+{
+    /// Append `leaf` to the buffer.
+    pub fn push(&mut self, leaf: L) {
+        self.len += 1;
+        if let Some(leaf_node) = self.pending.push(Node::from_leaf(leaf)) {
+            // pending is full
+            let group = Node::from_children(
+                            <CONF::Ptr as NodesPtr<L>>::new(self.pending.drain(..).collect()));
+            self.filled = Some(match self.filled.take() {
+                Some(filled) => Node::concat(filled, group),
+                None => group,
+            });
+            self.pending.push(leaf_node);
+        }
+    }
+
+    /// The number of leaves in the buffer that were not yet inserted.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Insert all buffered leaves into the tree. Since the cursor may move during insertion, the
+    /// leaves pushed after a flush will be inserted relative to a different node.
+    pub fn flush(&mut self) {
+        self.len = 0;
+        let mut group = match self.pending.len() {
+            0 => None,
+            1 => self.pending.pop(),
+            _ => Some(Node::from_children(
+                          <CONF::Ptr as NodesPtr<L>>::new(self.pending.drain(..).collect()))),
+        };
+        if let Some(filled) = self.filled.take() {
+            group = Some(match group {
+                Some(group) => Node::concat(filled, group),
+                None => filled,
+            });
+        }
+        if let Some(group) = group {
+            self.cursor.insert(group, self.after);
+        }
+    }
+}
+
+impl<'a, L, PI, CONF> Drop for WriteBuffer<'a, L, PI, CONF>
+    where L: Leaf + 'a,
+          PI: PathInfo<L::Info> + 'a,
+          CONF: CMutConf<L, PI> + 'a,
+{
+    fn drop(&mut self) {
+        self.flush();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use test_help::*;
+
+    #[test]
+    fn write_buffer() {
+        let mut cursor_mut: CursorMutT<_> = (0..10).map(ListLeaf).collect();
+        cursor_mut.reset();
+        cursor_mut.first_leaf();
+        cursor_mut.next_leaf();
+        {
+            let mut buffer = cursor_mut.write_buffer(true);
+            for i in 100..150 {
+                buffer.push(ListLeaf(i));
+            }
+            assert_eq!(buffer.len(), 50);
+        }
+        let root = cursor_mut.into_root().unwrap();
+        let leaves: Vec<_> = CursorT::new(&root).into_iter().map(|leaf| leaf.0).collect();
+        let expected: Vec<_> = (0..2).chain(100..150).chain(2..10).collect();
+        assert_eq!(leaves, expected);
+    }
+
+    #[test]
+    fn write_buffer_empty() {
+        let mut cursor_mut = CursorMutT::new();
+        {
+            let mut buffer = cursor_mut.write_buffer(false);
+            buffer.push(ListLeaf(0));
+            buffer.flush();
+            assert!(buffer.is_empty());
+        }
+        assert_eq!(cursor_mut.first_leaf(), Some(&ListLeaf(0)));
+    }
+}
@@ -1,6 +1,6 @@
 use arrayvec::Array;
 
-use node::{NodesPtr, Arc16, Rc16, Box16};
+use node::{NodesPtr, Arc16, Rc16, Box16, Pool16};
 use traits::Leaf;
 use super::{CStep, CMutStep};
 
@@ -24,3 +24,15 @@ pub trait CMutConf<L: Leaf, PI>: Sized + PtrMark<L> {
 def_cursor_conf!(Arc33M, Arc16, 8);
 def_cursor_conf!(Rc33M, Rc16, 8);
 def_cursor_conf!(Box33M, Box16, 8);
+
+/// Same as `Rc33M`, but using `Pool16` which reuses freed arrays of children.
+pub enum Pool33M {}
+impl<L: Leaf + 'static> PtrMark<L> for Pool33M {
+    type Ptr = Pool16<L>;
+}
+impl<'a, L: Leaf + 'static, PI> CConf<'a, L, PI> for Pool33M {
+    type StepsBuf = [CStep<'a, L, PI, Self>; 8];
+}
+impl<L: Leaf + 'static, PI> CMutConf<L, PI> for Pool33M {
+    type MutStepsBuf = [CMutStep<L, PI, Self>; 8];
+}
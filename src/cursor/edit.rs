@@ -1,13 +1,16 @@
 use super::conf::{CMutConf, Rc33M};
-use super::nav::CursorNav;
-use traits::{Leaf, PathInfo, SubOrd};
+use super::nav::{CursorNav, Seek};
+use traits::{Leaf, LeafLimit, PathInfo, SubOrd};
 use node::{Node, NodesPtr, insert_maybe_split};
+use builder::TreeBuilder;
+use delta::EditDelta;
+use error::{CursorError, TreeError};
 
-use std::{fmt, mem};
+use std::{cmp, fmt, mem};
 use std::iter::FromIterator;
 use std::marker::PhantomData;
 
-use arrayvec::ArrayVec;
+use arrayvec::{Array, ArrayVec};
 
 // Note: The working of `CursorMut` is fundamentally different from `Cursor`. `CursorMut` can
 //       become empty (iff `cur_node` is empty. `cur_node` empty implies `steps` is also empty).
@@ -26,8 +29,17 @@ pub struct CursorMut<L, PI, CONF = Rc33M>
 {
     cur_node: Node<L, CONF::Ptr>,
     steps: ArrayVec<CONF::MutStepsBuf>,
+    deferred: bool,
+    relaxed: bool,
+    generation: u64,
+    deltas: Option<Vec<EditDelta<PI>>>, // the deltas recorded so far, if recording
+    validator: Option<LeafValidator<L>>,
 }
 
+/// A check of domain constraints on leaves (such as being non-empty, or sorted), returning the
+/// reason a leaf is invalid. See `CursorMut::set_validator`.
+pub type LeafValidator<L> = fn(&L) -> Result<(), &'static str>;
+
 pub struct CMutStep<L, PI, CONF>
     where L: Leaf,
           CONF: CMutConf<L, PI>,
@@ -35,6 +47,8 @@ pub struct CMutStep<L, PI, CONF>
     nodes: CONF::Ptr,
     idx: usize,
     path_info: PI,
+    parent_info: Option<L::Info>, // the info of the parent node when descended into it
+    dirty: bool, // whether `nodes` was modified (or parent_info is unusable)
     __phantom: PhantomData<L>,
 }
 
@@ -47,6 +61,11 @@ impl<L, PI, CONF> Clone for CursorMut<L, PI, CONF>
         CursorMut {
             cur_node: self.cur_node.clone(),
             steps: self.steps.clone(),
+            deferred: self.deferred,
+            relaxed: self.relaxed,
+            generation: self.generation,
+            deltas: self.deltas.clone(),
+            validator: self.validator,
         }
     }
 }
@@ -61,6 +80,8 @@ impl<L, PI, CONF> Clone for CMutStep<L, PI, CONF>
             nodes: self.nodes.clone(),
             idx: self.idx,
             path_info: self.path_info.clone(),
+            parent_info: self.parent_info,
+            dirty: self.dirty,
             __phantom: PhantomData,
         }
     }
@@ -73,7 +94,7 @@ impl<L, PI, CONF> CMutStep<L, PI, CONF>
 {
     fn new(nodes: CONF::Ptr, idx: usize, path_info: PI) -> Self {
         let __phantom = PhantomData;
-        CMutStep { nodes, idx, path_info, __phantom }
+        CMutStep { nodes, idx, path_info, parent_info: None, dirty: true, __phantom }
     }
 }
 
@@ -97,6 +118,11 @@ impl<L, PI, CONF> CursorMut<L, PI, CONF>
         CursorMut {
             cur_node: Node::never(),
             steps: ArrayVec::new(),
+            deferred: false,
+            relaxed: false,
+            generation: 0,
+            deltas: None,
+            validator: None,
         }
     }
 
@@ -104,12 +130,118 @@ impl<L, PI, CONF> CursorMut<L, PI, CONF>
         CursorMut {
             cur_node: node,
             steps: ArrayVec::new(),
+            deferred: false,
+            relaxed: false,
+            generation: 0,
+            deltas: None,
+            validator: None,
         }
     }
 
+    /// Same as `from_node`, but fails if `node` is too tall for the cursor to reach its leaves.
+    pub fn try_from_node(node: Node<L, CONF::Ptr>) -> Result<Self, CursorError> {
+        let cursor = Self::from_node(node);
+        match Self::check_height(cursor.cur_node.height()) {
+            Ok(()) => Ok(cursor),
+            Err(kind) => Err(cursor.error("try_from_node", kind)),
+        }
+    }
+
+    /// The maximum height of a tree supported by this cursor.
+    pub fn max_height() -> usize {
+        <CONF::MutStepsBuf as Array>::capacity()
+    }
+
+    /// Returns the root node of the tree, or `None` if the tree is empty.
+    ///
+    /// A root with only one child is replaced by that child, so that a tree of a single leaf is
+    /// returned as a leaf node, which does not hold any allocation. Deferred info is finalized,
+    /// and so is the balance of the tree if relaxed (see `relax_balance`).
     pub fn into_root(mut self) -> Option<Node<L, CONF::Ptr>> {
+        self.finalize_info();
+        if self.relaxed {
+            self.finalize_balance();
+        }
+        let mut root = self.take_current();
+        while root.as_ref().is_some_and(|node| !node.is_leaf() && node.children().len() == 1) {
+            let mut nodes = root.unwrap().into_children_must();
+            root = <CONF::Ptr as NodesPtr<L>>::make_mut(&mut nodes).pop();
+        }
+        root
+    }
+
+    /// Defer recomputing the info of ancestors of modified nodes until `finalize_info` is called
+    /// (or `defer_info(false)`, or `into_root`). This makes many scattered edits cheaper, since
+    /// the ancestors shared by many of them are recomputed only once.
+    ///
+    /// While deferred, the info of the ancestors of modified nodes is outdated, and so is any
+    /// `path_info` derived from them. Navigation should thus rely only on the parts of info that
+    /// the edits do not change (such as a leaf count when only updating leaves in-place).
+    pub fn defer_info(&mut self, defer: bool) {
+        if self.deferred && !defer {
+            self.finalize_info();
+        }
+        self.deferred = defer;
+    }
+
+    /// Recompute all outdated info resulting from deferred edits. Resets the cursor to root.
+    pub fn finalize_info(&mut self) {
+        self.reset();
+        self.cur_node.refresh_stale();
+    }
+
+    /// Allow nodes to become under-filled when nodes are removed, until `finalize_balance` is
+    /// called (or `relax_balance(false)` or `into_root`, which call it). This makes a large batch of removals
+    /// and splices cheaper, since nodes are not merged with their siblings after every removal.
+    ///
+    /// While relaxed, the tree may be deeper than necessary, but it stays valid otherwise.
+    pub fn relax_balance(&mut self, relax: bool) {
+        if self.relaxed && !relax {
+            self.finalize_balance();
+        }
+        self.relaxed = relax;
+    }
+
+    /// Rebalance the tree in a single pass by rebuilding it (see `Node::compact`), which also
+    /// recomputes all outdated info. Resets the cursor to root.
+    ///
+    /// Time: O(n)
+    pub fn finalize_balance(&mut self) {
         self.reset();
-        self.take_current()
+        if !self.is_empty() {
+            self.cur_node.compact();
+        }
+    }
+
+    /// Start (or stop) recording the `EditDelta` of every mutation made through this cursor. The
+    /// deltas are in terms of `path_info`, so they are only accurate while info is not deferred.
+    pub fn record_deltas(&mut self, record: bool) {
+        match (record, self.deltas.is_some()) {
+            (true, false) => self.deltas = Some(Vec::new()),
+            (false, true) => self.deltas = None,
+            _ => (),
+        }
+    }
+
+    /// Returns the deltas recorded since the last call, in the order of the mutations.
+    pub fn take_deltas(&mut self) -> Vec<EditDelta<PI>> {
+        match self.deltas {
+            Some(ref mut deltas) => mem::take(deltas),
+            None => Vec::new(),
+        }
+    }
+
+    /// Set (or unset) a check that every leaf inserted through this cursor should pass. Inserting
+    /// an invalid leaf panics, while the `try_` variants of insertion return an `InvalidLeaf`
+    /// error. Leaves updated in-place are not checked.
+    pub fn set_validator(&mut self, validator: Option<LeafValidator<L>>) {
+        self.validator = validator;
+    }
+
+    /// The number of mutations made to the tree through this cursor (or the cursor it was cloned
+    /// from). Caches of values derived from the tree can use it to detect that it has changed.
+    pub fn generation(&self) -> u64 {
+        self.generation
     }
 
     pub fn current(&self) -> Option<&Node<L, CONF::Ptr>> {
@@ -145,6 +277,10 @@ impl<L, PI, CONF> CursorMut<L, PI, CONF>
 
     /// The cumulative info along the path from root to this node. Returns `PathInfo::identity()`
     /// if the current node is root or cursor is empty.
+    ///
+    /// This includes the info of all nodes to the left of the current node at every level, and so
+    /// is the absolute position of the current node, however the cursor got there. (Each step keeps
+    /// the cumulative info, not just the contribution of its own left siblings.)
     pub fn path_info(&self) -> PI {
         match self.steps.last() {
             Some(cstep) => cstep.path_info,
@@ -155,7 +291,56 @@ impl<L, PI, CONF> CursorMut<L, PI, CONF>
     /// Update the leaf value in-place using `f`. This is a no-op if the current node is not a
     /// leaf.
     pub fn leaf_update<F>(&mut self, f: F) where F: FnOnce(&mut L) {
-        self.cur_node.leaf_update(f);
+        if self.cur_node.is_leaf() {
+            let old_info = self.cur_node.info();
+            self.cur_node.leaf_update(f);
+            self.mark_dirty();
+            if self.deltas.is_some() {
+                let start = self.path_info();
+                let new_end = start.extend(self.cur_node.info());
+                self.record(EditDelta { start, old_end: start.extend(old_info), new_end });
+            }
+            self.mutated();
+        }
+    }
+
+    /// Same as `leaf_update`, but fails if the current node is not a leaf.
+    pub fn try_leaf_update<F>(&mut self, f: F) -> Result<(), CursorError>
+        where F: FnOnce(&mut L)
+    {
+        match self.height() {
+            Some(0) => {
+                self.leaf_update(f);
+                Ok(())
+            }
+            Some(height) => Err(self.error("try_leaf_update", TreeError::NotLeaf { height })),
+            None => Err(self.error("try_leaf_update", TreeError::Empty)),
+        }
+    }
+
+    /// Same as `leaf_update`, but if the leaf ends up over its size limit, it is split into leaves
+    /// within the limit (see `LeafLimit`), the rest of which are inserted after it. The cursor
+    /// may not be at the updated leaf afterwards.
+    pub fn leaf_update_limited<F>(&mut self, f: F) where F: FnOnce(&mut L), L: LeafLimit {
+        let mut rest = None;
+        self.leaf_update(|leaf| {
+            f(leaf);
+            if L::weight(&leaf.compute_info()) > L::max_weight() {
+                let at = leaf.split_point();
+                rest = Some(leaf.split_off(at));
+            }
+        });
+        if let Some(rest) = rest {
+            let node = rest.split_to_limit().into_iter().collect();
+            self.insert(node, true);
+        }
+    }
+
+    /// Recompute the info of the current leaf, and update its ancestors accordingly. Use this after
+    /// the leaf was mutated externally (such as through a `RefCell` in it), which the cursor cannot
+    /// detect. This is a no-op if the current node is not a leaf.
+    pub fn refresh_leaf_info(&mut self) {
+        self.leaf_update(|_| ());
     }
 
     /// The `path_info` till this node and after.
@@ -185,8 +370,8 @@ impl<L, PI, CONF> CursorMut<L, PI, CONF>
 
     pub fn ascend(&mut self) -> Option<&Node<L, CONF::Ptr>> {
         match self.pop_step() {
-            Some(CMutStep { nodes, idx, .. }) => {
-                self.ascend_raw(nodes, idx);
+            Some(cstep) => {
+                self.ascend_raw(cstep);
                 Some(&self.cur_node)
             }
             None => None, // cur_node is the root (or empty)
@@ -197,9 +382,10 @@ impl<L, PI, CONF> CursorMut<L, PI, CONF>
         match self.take_current() {
             Some(cur_node) => {
                 let path_info = self.path_info();
+                let (info, stale) = (cur_node.info(), cur_node.is_stale());
                 match cur_node.into_children() {
                     Ok(nodes) => {
-                        self.descend_raw(nodes, 0, path_info);
+                        self.descend_raw(nodes, 0, path_info, info, stale);
                         Some(&self.cur_node)
                     }
                     Err(mut cur_node) => {
@@ -215,12 +401,13 @@ impl<L, PI, CONF> CursorMut<L, PI, CONF>
     pub fn descend_last(&mut self) -> Option<&Node<L, CONF::Ptr>> {
         match self.take_current() {
             Some(cur_node) => {
-                let path_info = self.path_info().extend(cur_node.info());
+                let (info, stale) = (cur_node.info(), cur_node.is_stale());
+                let path_info = self.path_info().extend(info);
                 match cur_node.into_children() {
                     Ok(nodes) => {
                         let lastidx = nodes.len() - 1;
                         let lastinfo = nodes[lastidx].info();
-                        self.descend_raw(nodes, lastidx, path_info.extend_inv(lastinfo));
+                        self.descend_raw(nodes, lastidx, path_info.extend_inv(lastinfo), info, stale);
                         Some(&self.cur_node)
                     }
                     Err(mut cur_node) => {
@@ -234,7 +421,7 @@ impl<L, PI, CONF> CursorMut<L, PI, CONF>
     }
 
     pub fn left_sibling(&mut self) -> Option<&Node<L, CONF::Ptr>> {
-        let &mut CursorMut { ref mut cur_node, ref mut steps } = self;
+        let &mut CursorMut { ref mut cur_node, ref mut steps, .. } = self;
         match steps.last_mut() {
             Some(&mut CMutStep { ref mut nodes, ref mut idx, ref mut path_info, .. }) => {
                 debug_assert!(!cur_node.is_never());
@@ -255,7 +442,7 @@ impl<L, PI, CONF> CursorMut<L, PI, CONF>
     }
 
     pub fn right_sibling(&mut self) -> Option<&Node<L, CONF::Ptr>> {
-        let &mut CursorMut { ref mut cur_node, ref mut steps } = self;
+        let &mut CursorMut { ref mut cur_node, ref mut steps, .. } = self;
         match steps.last_mut() {
             Some(&mut CMutStep { ref mut nodes, ref mut idx, ref mut path_info, .. }) => {
                 debug_assert!(!cur_node.is_never());
@@ -417,6 +604,16 @@ impl<L, PI, CONF> CursorMut<L, PI, CONF>
     pub fn goto_max<PS: SubOrd<PI>>(&mut self, path_info_sub: PS) -> Option<&L> {
         <Self as CursorNav>::goto_max(self, path_info_sub)
     }
+
+    /// Moves the cursor to the leaf containing the position `path_info_sub`, clamping positions
+    /// out of range to the first or the last leaf, and returns where the position lies. Unlike
+    /// `goto_min`, this always moves to a leaf if the tree is non-empty, so that positions such as
+    /// one past the end can be used without checking.
+    ///
+    /// Conditions for correctness are the same as `goto_min`.
+    pub fn seek<PS: SubOrd<PI>>(&mut self, path_info_sub: PS) -> Seek {
+        <Self as CursorNav>::seek(self, path_info_sub)
+    }
 }
 
 impl<L, PI, CONF> CursorNav for CursorMut<L, PI, CONF>
@@ -475,6 +672,17 @@ impl<L, PI, CONF> CursorNav for CursorMut<L, PI, CONF>
     fn _right_sibling(&mut self) -> Option<&Node<L, CONF::Ptr>> {
         self.right_sibling()
     }
+
+    fn _descend_at(&mut self, idx: usize, path_info: PI) -> Option<&Node<L, CONF::Ptr>> {
+        if idx >= self.current().map_or(0, |node| node.children().len()) {
+            return None;
+        }
+        let cur_node = self.take_current().unwrap();
+        let (info, stale) = (cur_node.info(), cur_node.is_stale());
+        let nodes = cur_node.into_children_must();
+        self.descend_raw(nodes, idx, path_info, info, stale);
+        Some(&self.cur_node)
+    }
 }
 
 // structural modifications
@@ -494,55 +702,31 @@ impl<L, PI, CONF> CursorMut<L, PI, CONF>
         self.insert(Node::from_leaf(leaf), after);
     }
 
+    /// Same as `insert_leaf`, but `leaf` is first split into leaves within its size limit (see
+    /// `LeafLimit`).
+    pub fn insert_leaf_limited(&mut self, leaf: L, after: bool) where L: LeafLimit {
+        let node = leaf.split_to_limit().into_iter().collect();
+        self.insert(node, after);
+    }
+
     /// Insert `newnode` before or after the current node and rebalance. `newnode` can be of any
     /// height.
+    ///
+    /// Panics if a leaf of `newnode` is rejected by the validator (see `set_validator`).
     pub fn insert(&mut self, newnode: Node<L, CONF::Ptr>, after: bool) {
-        let newnode_ht = newnode.height();
-        match self.height() {
-            Some(cur_ht) if cur_ht >= newnode_ht => {
-                while self.cur_node.height() > newnode_ht {
-                    let _res = if after { self.descend_last() } else { self.descend_first() };
-                    debug_assert!(_res.is_some());
-                }
-                return self.insert_simple(newnode, after);
-            }
-            None => {
-                self.cur_node = newnode;
-                return;
-            }
-            _ => (),
+        if let Err(kind) = self.validate(&newnode) {
+            panic!("{}", self.error("insert", kind));
         }
-
-        let mut current = self.cur_node.never_take();
-        current = if after {
-            Node::concat(current, newnode)
-        } else {
-            Node::concat(newnode, current)
-        };
-
-        // TODO investigate possible performance tweaks
-        while let Some(CMutStep { mut nodes, idx, path_info, .. }) = self.pop_step() {
-            if nodes[(idx + 1) % nodes.len()].height() == current.height() {
-                self.push_step(CMutStep::new(nodes, idx, path_info));
-                break;
-            }
-
-            let nodes = <CONF::Ptr as NodesPtr<L>>::make_mut(&mut nodes);
-            let len = nodes.len();
-
-            if idx + 1 < len {
-                let right = Node::from_children(
-                                <CONF::Ptr as NodesPtr<L>>::new(nodes.drain(idx+1..).collect()));
-                current = Node::concat(current, right);
-            }
-
-            if idx > 0 {
-                let left = Node::from_children(
-                               <CONF::Ptr as NodesPtr<L>>::new(nodes.drain(0..idx).collect()));
-                current = Node::concat(left, current);
-            }
+        if self.deltas.is_some() {
+            let start = match self.path_interval() {
+                Some((start, end)) => if after { end } else { start },
+                None => PI::identity(),
+            };
+            let new_end = start.extend(newnode.info());
+            self.record(EditDelta { start, old_end: start, new_end });
         }
-        self.cur_node = current;
+        self.insert_raw(newnode, after);
+        self.mutated();
     }
 
     /// Remove the first leaf under the current node.
@@ -557,19 +741,25 @@ impl<L, PI, CONF> CursorMut<L, PI, CONF>
     /// `path_info` will not increase (or `extend`). The user should ensure that the cursor is at
     /// the correct location after this.
     pub fn remove_node(&mut self) -> Option<Node<L, CONF::Ptr>> {
+        if let Some((start, old_end)) = self.path_interval() {
+            self.record(EditDelta { start, old_end, new_end: start });
+        }
         match self.take_current() {
             Some(cur_node) => {
-                if let Some(mut cstep) = self.pop_step() {
+                while let Some(mut cstep) = self.pop_step() {
                     let dummy = <CONF::Ptr as NodesPtr<L>>::make_mut(&mut cstep.nodes)
                                     .remove(cstep.idx)
                                     .unwrap();
                     debug_assert!(dummy.is_never());
                     if cstep.nodes.len() > 0 {
                         self.fix_current(cstep);
-                    } else {
-                        debug_assert!(self.steps.len() == 0); // should be root
+                        break;
                     }
+                    // only the root can become empty, unless relaxed, where the emptied parent
+                    // is removed as well
+                    debug_assert!(self.steps.is_empty() || self.relaxed);
                 }
+                self.mutated();
                 Some(cur_node)
             },
             None => None, // cursor is empty
@@ -587,6 +777,7 @@ impl<L, PI, CONF> CursorMut<L, PI, CONF>
             return None;
         }
 
+        let start = self.path_info();
         let mut this = Node::never();
         let mut ret = self.cur_node.never_take();
         // Note on time complexity: Even though time complexity of concat is O(log n), the heights
@@ -612,8 +803,48 @@ impl<L, PI, CONF> CursorMut<L, PI, CONF>
         }
 
         self.cur_node = this;
+        let old_end = start.extend(ret.info());
+        self.record(EditDelta { start, old_end, new_end: start });
+        self.mutated();
         Some(ret)
     }
+
+    /// Same as `insert`, but fails (without modifying the tree) if the tree could grow taller than
+    /// `max_height()`. Since an insertion may add a level to the tree, this fails if the taller of
+    /// the tree and `newnode` is already at the maximum height.
+    pub fn try_insert(&mut self, newnode: Node<L, CONF::Ptr>, after: bool)
+        -> Result<(), CursorError>
+    {
+        let height = match self.height() {
+            Some(cur_ht) => cmp::max(self.steps.len() + cur_ht, newnode.height()) + 1,
+            None => newnode.height(),
+        };
+        Self::check_height(height).and_then(|()| self.validate(&newnode))
+                                  .map_err(|kind| self.error("try_insert", kind))?;
+        self.insert(newnode, after);
+        Ok(())
+    }
+
+    /// Same as `insert_leaf`, but fails as `try_insert` does.
+    pub fn try_insert_leaf(&mut self, leaf: L, after: bool) -> Result<(), CursorError> {
+        self.try_insert(Node::from_leaf(leaf), after)
+    }
+
+    /// Same as `remove_node`, but fails if the cursor is empty.
+    pub fn try_remove_node(&mut self) -> Result<Node<L, CONF::Ptr>, CursorError> {
+        if self.is_empty() {
+            return Err(self.error("try_remove_node", TreeError::Empty));
+        }
+        Ok(self.remove_node().unwrap())
+    }
+
+    /// Same as `remove_leaf`, but fails if the cursor is empty.
+    pub fn try_remove_leaf(&mut self) -> Result<L, CursorError> {
+        if self.is_empty() {
+            return Err(self.error("try_remove_leaf", TreeError::Empty));
+        }
+        Ok(self.remove_leaf().unwrap())
+    }
 }
 
 impl<L, PI, CONF> CursorMut<L, PI, CONF>
@@ -621,17 +852,69 @@ impl<L, PI, CONF> CursorMut<L, PI, CONF>
           PI: PathInfo<L::Info>,
           CONF: CMutConf<L, PI>,
 {
+    // `insert` without the paranoid checks.
+    fn insert_raw(&mut self, newnode: Node<L, CONF::Ptr>, after: bool) {
+        let newnode_ht = newnode.height();
+        match self.height() {
+            Some(cur_ht) if cur_ht >= newnode_ht => {
+                while self.cur_node.height() > newnode_ht {
+                    let _res = if after { self.descend_last() } else { self.descend_first() };
+                    debug_assert!(_res.is_some());
+                }
+                return self.insert_simple(newnode, after);
+            }
+            None => {
+                self.cur_node = newnode;
+                return;
+            }
+            _ => (),
+        }
+
+        let mut current = self.cur_node.never_take();
+        current = if after {
+            Node::concat(current, newnode)
+        } else {
+            Node::concat(newnode, current)
+        };
+
+        // TODO investigate possible performance tweaks
+        while let Some(CMutStep { mut nodes, idx, path_info, .. }) = self.pop_step() {
+            if nodes[(idx + 1) % nodes.len()].height() == current.height() {
+                self.push_step(CMutStep::new(nodes, idx, path_info));
+                break;
+            }
+
+            let nodes = <CONF::Ptr as NodesPtr<L>>::make_mut(&mut nodes);
+            let len = nodes.len();
+
+            if idx + 1 < len {
+                let right = Node::from_children(
+                                <CONF::Ptr as NodesPtr<L>>::new(nodes.drain(idx+1..).collect()));
+                current = Node::concat(current, right);
+            }
+
+            if idx > 0 {
+                let left = Node::from_children(
+                               <CONF::Ptr as NodesPtr<L>>::new(nodes.drain(0..idx).collect()));
+                current = Node::concat(left, current);
+            }
+        }
+        self.cur_node = current;
+    }
+
     fn insert_simple(&mut self, mut newnode: Node<L, CONF::Ptr>, after: bool) {
         if self.is_empty() {
             self.cur_node = newnode;
             return;
         }
 
-        let &mut CursorMut { ref mut cur_node, ref mut steps } = self;
+        let &mut CursorMut { ref mut cur_node, ref mut steps, .. } = self;
         loop {
             debug_assert_eq!(cur_node.height(), newnode.height());
             match steps.last_mut() {
-                Some(&mut CMutStep { ref mut nodes, ref mut idx, ref mut path_info, .. }) => {
+                Some(&mut CMutStep { ref mut nodes, ref mut idx, ref mut path_info,
+                                     ref mut dirty, .. }) => {
+                    *dirty = true; // nodes will be modified
                     let maybe_split;
                     {
                         let nodes = <CONF::Ptr as NodesPtr<L>>::make_mut(nodes);
@@ -700,7 +983,7 @@ impl<L, PI, CONF> CursorMut<L, PI, CONF>
         debug_assert!(nodes_len > 0); // nodes should never be empty
         debug_assert!(nodes.iter().all(|n| !n.is_never())); // nodes should be all valid
         let steps_len = self.steps.len();
-        if nodes_len >= <CONF::Ptr as NodesPtr<L>>::max_size()/2 || steps_len == 0 {
+        if nodes_len >= <CONF::Ptr as NodesPtr<L>>::max_size()/2 || steps_len == 0 || self.relaxed {
             let at_right_end = idx == nodes_len;
             if at_right_end {
                 idx -= 1;
@@ -709,7 +992,7 @@ impl<L, PI, CONF> CursorMut<L, PI, CONF>
             if at_right_end {
                 path_info = path_info.extend_inv(self.cur_node.info());
             }
-            debug_assert!(self.cur_node.is_leaf() || self.cur_node.has_min_size());
+            debug_assert!(self.cur_node.is_leaf() || self.cur_node.has_min_size() || self.relaxed);
             self.push_step(CMutStep::new(nodes, idx, path_info));
         } else { // steps_len > 0
             debug_assert_eq!(nodes_len, <CONF::Ptr as NodesPtr<L>>::max_size()/2 - 1);
@@ -767,17 +1050,38 @@ impl<L, PI, CONF> CursorMut<L, PI, CONF>
         }
     }
 
-    fn ascend_raw(&mut self, mut nodes: CONF::Ptr, idx: usize) {
+    fn ascend_raw(&mut self, cstep: CMutStep<L, PI, CONF>) {
         debug_assert!(!self.cur_node.is_never());
+        let CMutStep { mut nodes, idx, parent_info, dirty, .. } = cstep;
         self.cur_node.never_swap(&mut <CONF::Ptr as NodesPtr<L>>::make_mut(&mut nodes)[idx]);
-        let parent = Node::from_children(nodes); // gather info
+        if dirty {
+            self.mark_dirty();
+        }
+        let parent = match parent_info {
+            Some(info) if !dirty => Node::from_children_info(nodes, info, false), // unchanged
+            Some(info) if self.deferred => Node::from_children_info(nodes, info, true),
+            _ => Node::from_children(nodes), // gather info
+        };
         self.cur_node = parent;
+        instrument!(Ascend { height: self.cur_node.height() });
     }
 
-    fn descend_raw(&mut self, mut nodes: CONF::Ptr, idx: usize, path_info: PI) {
+    fn descend_raw(&mut self, mut nodes: CONF::Ptr, idx: usize, path_info: PI, info: L::Info,
+                   stale: bool) {
         debug_assert!(self.cur_node.is_never());
         self.cur_node.never_swap(&mut <CONF::Ptr as NodesPtr<L>>::make_mut(&mut nodes)[idx]);
-        self.push_step(CMutStep::new(nodes, idx, path_info));
+        let mut cstep = CMutStep::new(nodes, idx, path_info);
+        cstep.parent_info = Some(info);
+        cstep.dirty = stale;
+        self.push_step(cstep);
+        instrument!(Descend { height: self.cur_node.height() });
+    }
+
+    // Mark the parent of the current node as modified.
+    fn mark_dirty(&mut self) {
+        if let Some(cstep) = self.steps.last_mut() {
+            cstep.dirty = true;
+        }
     }
 
     fn push_step(&mut self, cstep: CMutStep<L, PI, CONF>) {
@@ -791,12 +1095,139 @@ impl<L, PI, CONF> CursorMut<L, PI, CONF>
         self.steps.pop()
     }
 
+    // Describe the failure of `op` along with the current state of the cursor.
+    fn error(&self, op: &'static str, kind: TreeError) -> CursorError {
+        let path = self.steps.iter().map(|cstep| cstep.idx).collect();
+        CursorError::new(op, path, self.height(), kind)
+    }
+
+    // Check the leaves of `node` using the validator, if any.
+    fn validate(&self, node: &Node<L, CONF::Ptr>) -> Result<(), TreeError> {
+        if let Some(validator) = self.validator {
+            for leaf in node.leaves() {
+                validator(leaf).map_err(|reason| TreeError::InvalidLeaf { reason })?;
+            }
+        }
+        Ok(())
+    }
+
+    fn check_height(height: usize) -> Result<(), TreeError> {
+        let max = Self::max_height();
+        if height > max {
+            return Err(TreeError::DepthExceeded { height, max });
+        }
+        Ok(())
+    }
+
     fn take_current(&mut self) -> Option<Node<L, CONF::Ptr>> {
         match self.cur_node {
             Node::Never(_) => None,
             ref mut cur_node => Some(cur_node.never_take()),
         }
     }
+
+    fn record(&mut self, delta: EditDelta<PI>) {
+        if let Some(ref mut deltas) = self.deltas {
+            deltas.push(delta);
+        }
+    }
+
+    // Called after every mutation of the tree.
+    fn mutated(&mut self) {
+        self.generation += 1;
+        self.paranoid_check();
+    }
+
+    // Verify the invariants along the spine of the cursor (only with the `paranoid` feature).
+    #[inline]
+    fn paranoid_check(&self) {
+        #[cfg(feature = "paranoid")]
+        self.check_spine();
+    }
+
+    // Verify the balance of the current node, its ancestors and their siblings, and the info of
+    // those nodes (unless deferred). Panics with a description of the first violation found.
+    #[cfg(feature = "paranoid")]
+    fn check_spine(&self) {
+        use traits::Info;
+
+        let max_size = <CONF::Ptr as NodesPtr<L>>::max_size();
+        let check_info = !self.deferred;
+        // whether two infos are known to differ (see `Info::paranoid_eq`)
+        let differ = |a: L::Info, b: L::Info| a.paranoid_eq(&b) == Some(false);
+        // Check a node against its children (but not any deeper).
+        let check_node = |node: &Node<L, CONF::Ptr>, at: &dyn fmt::Display| {
+            match node.leaf() {
+                Some(leaf) => if check_info {
+                    assert!(!differ(node.info(), leaf.compute_info()),
+                            "paranoid: leaf {} has an info different from what the leaf computes",
+                            at);
+                },
+                None => {
+                    let children = node.children();
+                    assert!(!children.is_empty() && children.len() <= max_size,
+                            "paranoid: node {} has {} children (max {})",
+                            at, children.len(), max_size);
+                    for (i, child) in children.iter().enumerate() {
+                        assert!(child.height() + 1 == node.height(),
+                                "paranoid: child {} of node {} has height {}, expected {}",
+                                i, at, child.height(), node.height() - 1);
+                    }
+                    if check_info && !node.is_stale() {
+                        let gathered = children[1..].iter()
+                                                    .fold(children[0].info(),
+                                                          |info, child| info.gather(child.info()));
+                        assert!(!differ(node.info(), gathered),
+                                "paranoid: node {} has an info different from what its children \
+                                 gather", at);
+                    }
+                }
+            }
+        };
+
+        if self.is_empty() {
+            assert!(self.steps.is_empty(),
+                    "paranoid: empty cursor with {} steps", self.steps.len());
+            return;
+        }
+        let depth = self.steps.len();
+        check_node(&self.cur_node, &format_args!("at depth {} (current)", depth));
+        if depth > 0 && !self.cur_node.is_leaf() && !self.relaxed {
+            assert!(self.cur_node.has_min_size(),
+                    "paranoid: current node at depth {} has {} children (min {})",
+                    depth, self.cur_node.children().len(), max_size / 2);
+        }
+
+        // the fresh info and the height of the node at the current level of the spine
+        let (mut info, mut height) = (self.cur_node.info(), self.cur_node.height());
+        let mut modified = false; // whether any node below the current level was modified
+        for (depth, cstep) in self.steps.iter().enumerate().rev() {
+            let nodes = &*cstep.nodes;
+            assert!(cstep.idx < nodes.len() && nodes[cstep.idx].is_never(),
+                    "paranoid: step at depth {} has index {} not pointing to the current path",
+                    depth, cstep.idx);
+            let min_size = if depth == 0 || self.relaxed { 1 } else { max_size / 2 };
+            assert!(nodes.len() >= min_size && nodes.len() <= max_size,
+                    "paranoid: node at depth {} has {} children (min {}, max {})",
+                    depth, nodes.len(), min_size, max_size);
+            for (i, node) in nodes.iter().enumerate().filter(|&(i, _)| i != cstep.idx) {
+                assert!(node.height() == height,
+                        "paranoid: sibling {} at depth {} has height {}, expected {}",
+                        i, depth + 1, node.height(), height);
+                check_node(node, &format_args!("{} at depth {}", i, depth + 1));
+            }
+            info = nodes.iter().enumerate().skip(1).fold(
+                if cstep.idx == 0 { info } else { nodes[0].info() },
+                |acc, (i, node)| acc.gather(if i == cstep.idx { info } else { node.info() }));
+            modified |= cstep.dirty; // the change propagates to the parent info on ascending
+            if check_info && !modified {
+                assert!(cstep.parent_info.is_some_and(|parent_info| !differ(parent_info, info)),
+                        "paranoid: unmodified node at depth {} has an info different from what \
+                         its children gather", depth);
+            }
+            height += 1;
+        }
+    }
 }
 
 impl<L, PI, CONF> FromIterator<L> for CursorMut<L, PI, CONF>
@@ -820,16 +1251,8 @@ impl<L, PI, CONF> Extend<L> for CursorMut<L, PI, CONF>
         where I: IntoIterator<Item=L>
     {
         self.reset();
-        let mut iter = iter.into_iter().map(Node::from_leaf);
-        loop {
-            let nodes = iter.by_ref()
-                            .take(<CONF::Ptr as NodesPtr<L>>::max_size())
-                            .collect::<ArrayVec<<CONF::Ptr as NodesPtr<L>>::Array>>();
-            if nodes.len() > 0 {
-                self.insert(Node::from_children(<CONF::Ptr as NodesPtr<L>>::new(nodes)), true);
-            } else {
-                break;
-            }
+        if let Some(node) = TreeBuilder::from_iter(iter).build() {
+            self.insert(node, true);
         }
     }
 }
@@ -1009,5 +1432,242 @@ mod tests {
         }
     }
 
+    #[test]
+    fn update_info() {
+        let mut cursor_mut: CursorMut<_, ListPath> = (0..128).map(ListLeaf).collect();
+        cursor_mut.reset();
+        cursor_mut.first_leaf();
+        while cursor_mut.next_leaf().is_some() {} // navigation only
+        assert_eq!(cursor_mut.current().unwrap().info(), ListInfo { count: 128, sum: 127*128/2 });
+        cursor_mut.goto_min(ListIndex(100));
+        cursor_mut.leaf_update(|leaf| leaf.0 = 0);
+        cursor_mut.prev_leaf(); // ascends through the modified nodes
+        cursor_mut.goto_min(ListIndex(10));
+        cursor_mut.leaf_update(|leaf| leaf.0 = 0);
+        cursor_mut.reset();
+        assert_eq!(cursor_mut.current().unwrap().info(), ListInfo { count: 128, sum: 127*128/2 - 110 });
+    }
+
+    #[test]
+    fn deferred_info() {
+        let mut cursor_mut: CursorMut<_, ListPath> = (0..1000).map(ListLeaf).collect();
+        cursor_mut.defer_info(true);
+        for i in 0..100 {
+            cursor_mut.goto_min(ListIndex((i * 37) % 1000)); // index is unaffected by updates
+            cursor_mut.leaf_update(|leaf| leaf.0 += 1);
+        }
+        let root = cursor_mut.into_root().unwrap();
+        assert_eq!(root.info(), ListInfo { count: 1000, sum: 999*1000/2 + 100 });
+        fn assert_fresh(node: &NodeRc<ListLeaf>) {
+            assert!(!node.is_stale());
+            for child in node.children() {
+                assert_fresh(child);
+            }
+        }
+        assert_fresh(&root);
+    }
+
+    #[test]
+    fn trivial_root() {
+        let cursor_mut: CursorMutT<_> = Some(ListLeaf(0)).into_iter().collect();
+        assert!(cursor_mut.into_root().unwrap().is_leaf());
+
+        let mut cursor_mut: CursorMutT<_> = (0..20).map(ListLeaf).collect();
+        for _ in 0..19 {
+            cursor_mut.remove_leaf();
+        }
+        assert_eq!(cursor_mut.into_root().unwrap().leaf(), Some(&ListLeaf(19)));
+
+        let cursor_mut: CursorMutT<ListLeaf> = None.into_iter().collect();
+        assert!(cursor_mut.into_root().is_none());
+    }
+
+    #[cfg(feature = "paranoid")]
+    #[test]
+    #[should_panic(expected = "different from what the leaf computes")]
+    fn paranoid() {
+        use traits::Leaf;
+        use std::cell::Cell;
+
+        #[derive(Clone)]
+        struct Impure(Cell<usize>); // computes a different info every time
+
+        impl Leaf for Impure {
+            type Info = usize;
+            fn compute_info(&self) -> usize {
+                self.0.set(self.0.get() + 1);
+                self.0.get()
+            }
+        }
+
+        let mut cursor_mut: CursorMut<_, usize> = (0..100).map(|_| Impure(Cell::new(0))).collect();
+        cursor_mut.goto_min(50);
+        cursor_mut.leaf_update(|_| ());
+    }
+
+    #[test]
+    fn generation() {
+        let mut cursor_mut: CursorMut<_, ListPath> = (0..100).map(ListLeaf).collect();
+        let generation = cursor_mut.generation();
+        cursor_mut.goto_min(ListIndex(50));
+        cursor_mut.next_leaf();
+        assert_eq!(cursor_mut.generation(), generation);
+        cursor_mut.leaf_update(|leaf| leaf.0 = 0);
+        cursor_mut.remove_leaf();
+        cursor_mut.insert_leaf(ListLeaf(0), true);
+        assert_eq!(cursor_mut.generation(), generation + 3);
+        let mut cursor_mut = CursorMut::<ListLeaf, ListPath>::new();
+        assert!(cursor_mut.remove_node().is_none());
+        assert_eq!(cursor_mut.generation(), 0);
+    }
+
+    #[test]
+    fn deltas() {
+        use delta::EditDelta;
+
+        let mut cursor_mut: CursorMut<_, ListPath> = (0..100).map(ListLeaf).collect();
+        let path = |index, run| ListPath { index, run };
+        cursor_mut.record_deltas(true);
+        cursor_mut.goto_min(ListIndex(10));
+        cursor_mut.leaf_update(|leaf| leaf.0 = 20);
+        cursor_mut.goto_min(ListIndex(50));
+        cursor_mut.remove_leaf();
+        cursor_mut.goto_min(ListIndex(90));
+        cursor_mut.insert_leaf(ListLeaf(1), true);
+        assert_eq!(cursor_mut.take_deltas(), vec![
+            EditDelta { start: path(10, 45), old_end: path(11, 55), new_end: path(11, 65) },
+            EditDelta { start: path(50, 1235), old_end: path(51, 1285), new_end: path(50, 1235) },
+            EditDelta { start: path(91, 4146), old_end: path(91, 4146), new_end: path(92, 4147) },
+        ]);
+        cursor_mut.goto_min(ListIndex(99));
+        cursor_mut.split_off();
+        assert_eq!(cursor_mut.take_deltas(), vec![
+            EditDelta { start: path(99, 4812), old_end: path(100, 4911), new_end: path(99, 4812) },
+        ]);
+    }
+
+    #[test]
+    fn try_ops() {
+        use error::TreeError;
+        use node::{NodesPtr, Rc16};
+
+        let mut cursor_mut: CursorMut<_, ListPath> = CursorMut::new();
+        assert_eq!(cursor_mut.try_remove_leaf().map_err(TreeError::from), Err(TreeError::Empty));
+        assert_eq!(cursor_mut.try_leaf_update(|_| ()).unwrap_err().kind(), TreeError::Empty);
+        for i in 0..100 {
+            cursor_mut.try_insert_leaf(ListLeaf(i), true).unwrap();
+        }
+        cursor_mut.reset();
+        cursor_mut.descend_first();
+        cursor_mut.right_sibling();
+        let err = cursor_mut.try_leaf_update(|_| ()).unwrap_err();
+        assert_eq!((err.op(), err.path(), err.height()), ("try_leaf_update", &[1][..], Some(1)));
+        assert_eq!(err.to_string(), "try_leaf_update failed at path [1] (height 1): \
+                                     expected a leaf, but the node has height 1");
+        cursor_mut.reset();
+        assert_eq!(cursor_mut.try_remove_leaf(), Ok(ListLeaf(0)));
+
+        let max = CursorMut::<ListLeaf, ListPath>::max_height();
+        let mut tall = NodeRc::from_leaf(ListLeaf(0));
+        while tall.height() < max + 1 {
+            tall = NodeRc::from_children(Rc16::new(Some(tall).into_iter().collect()));
+        }
+        assert_eq!(cursor_mut.try_insert(tall.clone(), true).unwrap_err().kind(),
+                   TreeError::DepthExceeded { height: max + 2, max });
+        assert_eq!(cursor_mut.into_root().unwrap().info().count, 99);
+        assert!(CursorMut::<_, ListPath>::try_from_node(tall).is_err());
+
+        let node: NodeRc<_> = (0..10).map(ListLeaf).collect();
+        assert_eq!(node.try_get(9), Ok(&ListLeaf(9)));
+        assert_eq!(node.try_get(10), Err(TreeError::OutOfBounds { index: 10, len: 10 }));
+    }
+
+    #[test]
+    fn leaf_limit() {
+        use leaf::{StringChunk, TextInfo};
+
+        let max = StringChunk::MAX_LEN;
+        let mut cursor_mut = CursorMut::<_, TextInfo>::new();
+        cursor_mut.insert_leaf_limited(StringChunk::from(&*"é".repeat(max)), false);
+        cursor_mut.first_leaf();
+        cursor_mut.leaf_update_limited(|chunk| *chunk = StringChunk::from(&*"a".repeat(3 * max)));
+        let root = cursor_mut.into_root().unwrap();
+        assert_eq!(root.info().bytes, 3 * max + max);
+        assert!(root.leaves().all(|chunk| chunk.len() <= max && !chunk.is_empty()));
+        assert_eq!(root.leaves().count(), 4);
+    }
+
+    #[test]
+    fn validator() {
+        use error::TreeError;
+
+        let mut cursor_mut = CursorMut::<_, ListPath>::new();
+        cursor_mut.set_validator(Some(|leaf: &ListLeaf| {
+            if leaf.0 < 100 { Ok(()) } else { Err("too large") }
+        }));
+        cursor_mut.insert_leaf(ListLeaf(1), false);
+        let err = cursor_mut.try_insert_leaf(ListLeaf(100), true).unwrap_err();
+        assert_eq!(err.kind(), TreeError::InvalidLeaf { reason: "too large" });
+        let node: NodeRc<_> = (90..110).map(ListLeaf).collect();
+        assert!(cursor_mut.try_insert(node, true).is_err());
+        cursor_mut.try_insert_leaf(ListLeaf(2), true).unwrap();
+        assert_eq!(cursor_mut.into_root().unwrap().info().count, 2);
+    }
+
+    #[test]
+    fn relaxed_balance() {
+        let mut cursor_mut = CursorMut::<_, ListPath>::from_node((0..1000).map(ListLeaf).collect());
+        let mut expected: Vec<_> = (0..1000).collect();
+        cursor_mut.relax_balance(true);
+        remove_relaxed(&mut cursor_mut, &mut expected);
+        let relaxed = cursor_mut.clone();
+        cursor_mut.reset();
+        // besides the root, some internal nodes have less than half of the 16 children allowed
+        let census = cursor_mut.current().unwrap().census();
+        assert!(census.children[..8].iter().sum::<usize>() > 1, "{:?}", census.children);
+
+        cursor_mut.relax_balance(false);
+        let root = cursor_mut.into_root().unwrap();
+        assert_balanced(&root);
+        assert!(root.leaves().map(|leaf| leaf.0).eq(expected.iter().cloned()));
+
+        let root = relaxed.into_root().unwrap(); // still relaxed
+        assert_balanced(&root);
+        assert!(root.leaves().map(|leaf| leaf.0).eq(expected));
+    }
+
+    // Remove random leaves from `cursor_mut`, inserting one every now and then, and update
+    // `expected` (the values of the leaves) accordingly.
+    fn remove_relaxed(cursor_mut: &mut CursorMut<ListLeaf, ListPath>, expected: &mut Vec<usize>) {
+        for i in 0..900 {
+            let index = rand_usize(expected.len());
+            cursor_mut.reset();
+            cursor_mut.goto_min(ListIndex(index));
+            assert_eq!(cursor_mut.remove_leaf(), Some(ListLeaf(expected.remove(index))));
+            if i % 10 == 0 && index < expected.len() {
+                cursor_mut.reset();
+                cursor_mut.goto_min(ListIndex(index));
+                cursor_mut.insert_leaf(ListLeaf(1000 + i), false);
+                expected.insert(index, 1000 + i);
+            }
+        }
+    }
+
+    #[test]
+    fn absolute_path_info() {
+        let mut cursor_mut = CursorMut::<_, ListPath>::from_node((0..1000).map(ListLeaf).collect());
+        cursor_mut.descend_last();
+        cursor_mut.left_sibling();
+        cursor_mut.descend_first();
+        cursor_mut.right_sibling();
+        while cursor_mut.descend_last().is_some() {}
+        let leaf = cursor_mut.leaf().unwrap().0;
+        assert_eq!(cursor_mut.path_info(), ListPath { index: leaf, run: (0..leaf).sum() });
+        cursor_mut.ascend();
+        let (start, _) = cursor_mut.path_interval().unwrap();
+        let first = cursor_mut.current().unwrap().leaves().next().unwrap().0;
+        assert_eq!(start, ListPath { index: first, run: (0..first).sum() });
+    }
+
     // FIXME need more tests (create verify_balanced function?)
 }
@@ -0,0 +1,167 @@
+use super::conf::{CConf, Rc33M};
+use super::view::Cursor;
+use node::Node;
+use traits::{LeafElems, PathInfo};
+
+/// A cursor over the elements of leaves that are containers (see `LeafElems`), such as the chars
+/// of a rope. Moving across leaf boundaries is handled transparently, and empty leaves are
+/// skipped.
+///
+/// The cursor is either at an element, or at the end of the tree (past its last element).
+pub struct ElemCursor<'a, L, PI, CONF = Rc33M>
+    where L: LeafElems + 'a,
+          CONF: CConf<'a, L, PI>,
+          CONF::Ptr: 'a,
+{
+    cursor: Cursor<'a, L, PI, CONF>,
+    offset: usize, // the offset of the current element within the current leaf
+}
+
+impl<'a, L, PI, CONF> Clone for ElemCursor<'a, L, PI, CONF>
+    where L: LeafElems + 'a,
+          PI: PathInfo<L::Info>,
+          CONF: CConf<'a, L, PI>,
+          CONF::Ptr: 'a,
+{
+    fn clone(&self) -> Self {
+        ElemCursor {
+            cursor: self.cursor.clone(),
+            offset: self.offset,
+        }
+    }
+}
+
+impl<'a, L, PI, CONF> ElemCursor<'a, L, PI, CONF>
+    where L: LeafElems + 'a,
+          PI: PathInfo<L::Info>,
+          CONF: CConf<'a, L, PI>,
+          CONF::Ptr: 'a,
+{
+    /// Create a cursor at the first element of the tree at `node`.
+    pub fn new(node: &'a Node<L, CONF::Ptr>) -> Self {
+        let mut cursor = Cursor::new(node);
+        cursor.first_leaf();
+        let mut elem_cursor = ElemCursor { cursor, offset: 0 };
+        elem_cursor.skip_empty();
+        elem_cursor
+    }
+
+    /// Create a cursor at the element at `offset` within the current leaf of `cursor`. `offset`
+    /// should be at an element boundary, and may be the end of the leaf.
+    ///
+    /// Panics if `cursor` is not at a leaf.
+    pub fn from_cursor(cursor: Cursor<'a, L, PI, CONF>, offset: usize) -> Self {
+        let len = cursor.leaf().expect("cursor is not at a leaf").end_offset();
+        assert!(offset <= len, "offset {} out of bounds (len: {})", offset, len);
+        let mut elem_cursor = ElemCursor { cursor, offset };
+        elem_cursor.skip_empty();
+        elem_cursor
+    }
+
+    /// Returns the underlying leaf cursor, which is at the leaf containing the current element.
+    pub fn leaf_cursor(&self) -> &Cursor<'a, L, PI, CONF> {
+        &self.cursor
+    }
+
+    /// The offset of the current element within its leaf.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// The path info at the start of the leaf containing the current element, along with the
+    /// offset of the element within that leaf.
+    pub fn path_info(&self) -> (PI, usize) {
+        (self.cursor.path_info(), self.offset)
+    }
+
+    /// Returns the current element, or `None` if at the end of the tree.
+    pub fn element(&self) -> Option<L::Elem> {
+        match self.cursor.leaf() {
+            Some(leaf) if self.offset < leaf.end_offset() => Some(leaf.elem_at(self.offset).0),
+            _ => None,
+        }
+    }
+
+    /// Move to the next element and return it. Returns `None` (and stays at the end of the tree)
+    /// if there are no more elements.
+    pub fn next_element(&mut self) -> Option<L::Elem> {
+        let len = match self.cursor.leaf() {
+            Some(leaf) if self.offset < leaf.end_offset() => leaf.elem_at(self.offset).1,
+            _ => return None,
+        };
+        self.offset += len;
+        self.skip_empty();
+        self.element()
+    }
+
+    /// Move to the previous element and return it. Returns `None` (and stays put) if at the first
+    /// element of the tree.
+    pub fn prev_element(&mut self) -> Option<L::Elem> {
+        if self.offset == 0 {
+            let mut cursor = self.cursor.clone();
+            loop {
+                match cursor.prev_leaf() {
+                    Some(leaf) if leaf.end_offset() > 0 => break,
+                    Some(_) => (),
+                    None => return None,
+                }
+            }
+            self.offset = cursor.leaf().unwrap().end_offset();
+            self.cursor = cursor;
+        }
+        let (elem, len) = self.cursor.leaf().unwrap().elem_before(self.offset);
+        self.offset -= len;
+        Some(elem)
+    }
+
+    // Move past the end of the current leaf (and any empty leaves after it) to the start of the
+    // next non-empty leaf, if any.
+    fn skip_empty(&mut self) {
+        match self.cursor.leaf() {
+            Some(leaf) if self.offset == leaf.end_offset() => (),
+            _ => return,
+        }
+        let mut cursor = self.cursor.clone();
+        loop {
+            match cursor.next_leaf() {
+                Some(leaf) if leaf.end_offset() > 0 => {
+                    self.cursor = cursor;
+                    self.offset = 0;
+                    return;
+                }
+                Some(_) => (),
+                None => return,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ElemCursor;
+    use leaf::ArcStr;
+    use node::{Node, Rc16};
+
+    #[test]
+    fn elements() {
+        let text = "héllo wörld";
+        let node: Node<_, Rc16<_>> = vec!["hé", "", "llo w", "ö", "rld", ""].into_iter()
+            .map(ArcStr::new).collect();
+        let mut cursor = ElemCursor::<_, usize>::new(&node);
+        let mut chars = vec![cursor.element().unwrap()];
+        while let Some(ch) = cursor.next_element() {
+            chars.push(ch);
+        }
+        assert_eq!(chars.iter().collect::<String>(), text);
+        assert_eq!(cursor.path_info(), (10, 3));
+
+        let mut chars = Vec::new();
+        while let Some(ch) = cursor.prev_element() {
+            chars.push(ch);
+            let (leaf_start, offset) = cursor.path_info();
+            assert!(text[leaf_start + offset..].starts_with(ch));
+        }
+        assert_eq!(chars.iter().rev().collect::<String>(), text);
+        assert_eq!(cursor.path_info(), (0, 0));
+    }
+}
@@ -0,0 +1,89 @@
+use super::conf::{CMutConf, Rc33M};
+use super::CursorMut;
+use traits::{Leaf, PathInfo, SubOrd};
+use node::Node;
+
+/// A tree that remembers the path to the last accessed leaf (its finger), and starts subsequent
+/// seeks from there.
+///
+/// A seek from the finger only ascends as far as the lowest common ancestor of the two leaves,
+/// and descends back from it. So accessing a leaf at a distance `d` (in number of leaves) from the
+/// previous one usually takes O(log d) steps instead of O(log n). This makes localized workloads,
+/// such as sequential parsing with occasional backtracking, cheaper than seeking from the root.
+pub struct FingeredTree<L, PI, CONF = Rc33M>
+    where L: Leaf,
+          PI: PathInfo<L::Info>,
+          CONF: CMutConf<L, PI>,
+{
+    cursor: CursorMut<L, PI, CONF>,
+}
+
+impl<L, PI, CONF> FingeredTree<L, PI, CONF>
+    where L: Leaf,
+          PI: PathInfo<L::Info>,
+          CONF: CMutConf<L, PI>,
+{
+    pub fn new(root: Node<L, CONF::Ptr>) -> Self {
+        FingeredTree { cursor: CursorMut::from_node(root) }
+    }
+
+    /// Returns the first leaf satisfying `path_info_sub <= path_info`, and moves the finger to it.
+    /// See `CursorMut::goto_min` for details.
+    pub fn get_min<PS: SubOrd<PI>>(&mut self, path_info_sub: PS) -> Option<&L> {
+        self.cursor.goto_min(path_info_sub)
+    }
+
+    /// Returns the last leaf satisfying `path_info_sub >= path_info.extend(leaf.info())`, and
+    /// moves the finger to it. See `CursorMut::goto_max` for details.
+    pub fn get_max<PS: SubOrd<PI>>(&mut self, path_info_sub: PS) -> Option<&L> {
+        self.cursor.goto_max(path_info_sub)
+    }
+
+    /// The leaf at the finger, if any.
+    pub fn leaf(&self) -> Option<&L> {
+        self.cursor.leaf()
+    }
+
+    /// The path info till the leaf at the finger.
+    pub fn path_info(&self) -> PI {
+        self.cursor.path_info()
+    }
+
+    /// Update the leaf at the finger in-place using `f`. See `CursorMut::leaf_update`.
+    pub fn leaf_update<F>(&mut self, f: F) where F: FnOnce(&mut L) {
+        self.cursor.leaf_update(f)
+    }
+
+    /// The underlying cursor, which is at the finger. Moving it moves the finger.
+    pub fn cursor_mut(&mut self) -> &mut CursorMut<L, PI, CONF> {
+        &mut self.cursor
+    }
+
+    pub fn into_root(self) -> Option<Node<L, CONF::Ptr>> {
+        self.cursor.into_root()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FingeredTree;
+    use test_help::*;
+
+    #[test]
+    fn seek_around() {
+        let root: NodeRc<_> = (0..1000).map(ListLeaf).collect();
+        let mut tree = FingeredTree::<_, ListPath>::new(root);
+        let mut idx = 0;
+        for _ in 0..500 {
+            idx = (idx + 1000 + rand_usize(20) - 8) % 1000;
+            assert_eq!(tree.get_min(ListIndex(idx)), Some(&ListLeaf(idx)));
+            assert_eq!(tree.path_info().index, idx);
+        }
+        assert_eq!(tree.get_max(ListIndex(10)), Some(&ListLeaf(9)));
+        tree.leaf_update(|leaf| leaf.0 = 0);
+        assert_eq!(tree.get_min(ListIndex(990)), Some(&ListLeaf(990)));
+        assert_eq!(tree.path_info().run, 989 * 990 / 2 - 9);
+        let root = tree.into_root().unwrap();
+        assert_eq!(root.info(), ListInfo { count: 1000, sum: 999 * 1000 / 2 - 9 });
+    }
+}
@@ -1,12 +1,20 @@
 mod view;
 mod edit;
 mod nav;
+mod buffer;
+mod finger;
+mod elem;
+mod zip;
 pub mod conf;
 
-pub use self::nav::actions;
+pub use self::nav::{actions, Seek};
 
 pub use self::view::Cursor;
-pub use self::edit::CursorMut;
+pub use self::edit::{CursorMut, LeafValidator};
+pub use self::buffer::WriteBuffer;
+pub use self::finger::FingeredTree;
+pub use self::elem::ElemCursor;
+pub use self::zip::ZipCursor;
 
 #[doc(hidden)]
 pub use self::view::CStep;
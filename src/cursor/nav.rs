@@ -1,7 +1,24 @@
 use node::Node;
 use traits::{Leaf, PathInfo, SubOrd};
 use node::NodesPtr;
-use self::actions::{NodeAction, LeafAction};
+use self::actions::{DescendAction, NodeAction, LeafAction};
+
+/// Where a position sought using `seek` lies relative to the leaves of a tree.
+///
+/// Out-of-range positions are handled by the cursor APIs as follows: `seek` and `chunk_at` clamp
+/// to the first or the last leaf, while `goto_min`, `goto_max` and `Node::locate` return `None`
+/// (as documented on each).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Seek {
+    /// The position is before the start of the tree. The cursor is at the first leaf.
+    Before,
+    /// The position is within the tree, including at its end. The cursor is at the leaf containing
+    /// it (as in `Node::locate`).
+    Within,
+    /// The position is beyond the end of the tree. The cursor is at the last leaf, or empty if the
+    /// tree is.
+    After,
+}
 
 pub trait CursorNav: Sized {
     type Leaf: Leaf;
@@ -21,6 +38,8 @@ pub trait CursorNav: Sized {
     fn _descend_last(&mut self) -> Option<&Node<Self::Leaf, Self::NodesPtr>>;
     fn _left_sibling(&mut self) -> Option<&Node<Self::Leaf, Self::NodesPtr>>;
     fn _right_sibling(&mut self) -> Option<&Node<Self::Leaf, Self::NodesPtr>>;
+    fn _descend_at(&mut self, idx: usize, path_info: Self::PathInfo)
+        -> Option<&Node<Self::Leaf, Self::NodesPtr>>;
 
     fn first_leaf(&mut self) -> Option<&Self::Leaf> {
         while self._descend_first().is_some() {}
@@ -137,11 +156,9 @@ pub trait CursorNav: Sized {
         debug_assert!(!satisfies(self._path_info(), self._current().unwrap().info()));
 
         // descend till the last leaf that don't satisfy the condition
-        while self.action_till::<JAS::DescendToFalse, _>(|path_info, info| satisfies(path_info, info)) {
-            status = FindStatus::HitTrue;
-            if !self.action_till::<JAS::SiblingToFalse, _>(|path_info, info| !satisfies(path_info, info)) {
-                // there must be a sibling that don't satisfy the condition
-                unreachable!();
+        while let Some(hit_true) = JAS::DescendToFalse::act_on(self, &satisfies) {
+            if hit_true {
+                status = FindStatus::HitTrue;
             }
         }
 
@@ -197,6 +214,35 @@ pub trait CursorNav: Sized {
         self.jump_to::<actions::PrefixMin, _>(satisfies)
     }
 
+    fn seek<PS: SubOrd<Self::PathInfo>>(&mut self, path_info_sub: PS) -> Seek {
+        use std::cmp::Ordering;
+
+        self._reset();
+        let start = Self::PathInfo::identity();
+        let end = match self._current() {
+            Some(root) => start.extend(root.info()),
+            None => return Seek::After,
+        };
+        if path_info_sub.sub_cmp(&start) == Ordering::Less {
+            self.first_leaf();
+            return Seek::Before;
+        }
+        if path_info_sub.sub_cmp(&end) == Ordering::Greater {
+            self.last_leaf();
+            return Seek::After;
+        }
+        // the first leaf starting at or after the position, then the one before it if needed
+        let satisfies = |path_info, _info| path_info_sub.sub_cmp(&path_info) != Ordering::Greater;
+        let found = self.jump_to::<actions::PrefixMin, _>(satisfies).is_some();
+        if !found {
+            self._reset();
+            self.last_leaf();
+        } else if path_info_sub.sub_cmp(&self._path_info()) == Ordering::Less {
+            self.prev_leaf();
+        }
+        Seek::Within
+    }
+
     fn goto_max<PS: SubOrd<Self::PathInfo>>(&mut self, path_info_sub: PS) -> Option<&Self::Leaf> {
         use std::cmp::Ordering;
 
@@ -211,8 +257,32 @@ pub trait CursorNav: Sized {
     }
 }
 
+// Returns the index of the first of `nodes` for which `pred(path_info, info)` holds (or
+// `nodes.len()` if none), along with the path info at its start, given that `pred` holds for a
+// suffix of `nodes`. Using a binary search, `pred` is called O(log n) times, while the path infos
+// are still extended O(n) times. This pays off over a linear scan when `pred` is costlier than
+// `extend`, such as when comparing long keys, and costs a little more otherwise (see the `seek`
+// benchmarks).
+fn partition_nodes<L, NP, PI, F>(nodes: &[Node<L, NP>], mut path_info: PI, pred: F) -> (usize, PI)
+    where L: Leaf, NP: NodesPtr<L>, PI: PathInfo<L::Info>, F: Fn(PI, L::Info) -> bool,
+{
+    let (mut lo, mut hi) = (0, nodes.len());
+    while lo < hi {
+        let mid = lo + (hi - lo) / 2;
+        let mid_info = nodes[lo..mid].iter().fold(path_info, |pi, node| pi.extend(node.info()));
+        if pred(mid_info, nodes[mid].info()) {
+            hi = mid;
+        } else {
+            lo = mid + 1;
+            path_info = mid_info.extend(nodes[mid].info());
+        }
+    }
+    (lo, path_info)
+}
+
 pub mod actions {
-    use super::{CursorNav, Node};
+    use super::{partition_nodes, CursorNav, Node};
+    use traits::{Leaf, PathInfo};
 
     pub trait NodeAction {
         fn act_on<C: CursorNav>(cursor: &mut C) -> Option<&Node<C::Leaf, C::NodesPtr>>;
@@ -292,13 +362,60 @@ pub mod actions {
         }
     }
 
+    pub trait DescendAction {
+        /// Descend to a child that does not satisfy the condition, if the current node is not a
+        /// leaf. Returns whether a sibling of that child (in the direction of the jump) satisfies
+        /// the condition.
+        fn act_on<C, F>(cursor: &mut C, satisfies: &F) -> Option<bool>
+            where C: CursorNav, F: Fn(C::PathInfo, <C::Leaf as Leaf>::Info) -> bool;
+    }
+
+    /// Descend to the last child not satisfying a condition that holds for a suffix of children.
+    pub enum DescendLastFalse {}
+    impl DescendAction for DescendLastFalse {
+        fn act_on<C, F>(cursor: &mut C, satisfies: &F) -> Option<bool>
+            where C: CursorNav, F: Fn(C::PathInfo, <C::Leaf as Leaf>::Info) -> bool,
+        {
+            let (idx, path_info, hit_true) = {
+                let nodes = cursor._current()?.children();
+                let (end, path_info) = partition_nodes(nodes, cursor._path_info(), satisfies);
+                if end == 0 {
+                    return None; // a leaf, or the condition holds for all children
+                }
+                (end - 1, path_info.extend_inv(nodes[end - 1].info()), end < nodes.len())
+            };
+            cursor._descend_at(idx, path_info);
+            Some(hit_true)
+        }
+    }
+
+    /// Descend to the first child not satisfying a condition that holds for a prefix of children.
+    pub enum DescendFirstFalse {}
+    impl DescendAction for DescendFirstFalse {
+        fn act_on<C, F>(cursor: &mut C, satisfies: &F) -> Option<bool>
+            where C: CursorNav, F: Fn(C::PathInfo, <C::Leaf as Leaf>::Info) -> bool,
+        {
+            let (idx, path_info) = {
+                let nodes = cursor._current()?.children();
+                let not_satisfies = |path_info, info| !satisfies(path_info, info);
+                let (idx, path_info) = partition_nodes(nodes, cursor._path_info(), not_satisfies);
+                if idx == nodes.len() {
+                    return None; // a leaf, or the condition holds for all children
+                }
+                (idx, path_info)
+            };
+            cursor._descend_at(idx, path_info);
+            Some(idx > 0)
+        }
+    }
+
     #[doc(hidden)]
     pub trait JumpActionSet {
         type AscendToTrue: NodeAction;
         type AscendToFalse: NodeAction;
         type TrueRootToLeaf: LeafAction;
         type SiblingToFalse: NodeAction;
-        type DescendToFalse: NodeAction;
+        type DescendToFalse: DescendAction;
         type FalseLeafToTrue: LeafAction;
     }
 
@@ -309,7 +426,7 @@ pub mod actions {
         type AscendToFalse = LeftMaybeAscend;
         type TrueRootToLeaf = FirstLeaf;
         type SiblingToFalse = LeftSibling;
-        type DescendToFalse = DescendLast;
+        type DescendToFalse = DescendLastFalse;
         type FalseLeafToTrue = NextLeaf;
     }
 
@@ -320,7 +437,7 @@ pub mod actions {
         type AscendToFalse = RightMaybeAscend;
         type TrueRootToLeaf = LastLeaf;
         type SiblingToFalse = RightSibling;
-        type DescendToFalse = DescendFirst;
+        type DescendToFalse = DescendFirstFalse;
         type FalseLeafToTrue = PrevLeaf;
     }
 }
@@ -1,12 +1,14 @@
 use super::conf::{CConf, Rc33M};
-use super::nav::CursorNav;
-use node::Node;
-use traits::{Leaf, PathInfo, SubOrd};
+use super::nav::{CursorNav, Seek};
+use iter::Children;
+use node::{Node, TreePath};
+use traits::{Info, Leaf, PathInfo, SubOrd};
 use mines::SliceExt; // for boom_get
 
 use arrayvec::ArrayVec;
 
 use std::fmt;
+use std::ops::Deref;
 
 /// An object that can be used to traverse a `Node`.
 ///
@@ -132,6 +134,10 @@ impl<'a, L, PI, CONF> Cursor<'a, L, PI, CONF>
     }
 
     /// Returns a reference to the current node, where the cursor is at.
+    ///
+    /// The reference borrows from the tree rather than the cursor, so the subtree (along with its
+    /// gathered info) can be handed to other components, such as a cache keyed by subtree, while
+    /// the cursor moves on.
     pub fn current(&self) -> &'a Node<L, CONF::Ptr> {
         match self.steps.last() {
             Some(cstep) => unsafe { &cstep.nodes.boom_get(cstep.idx) },
@@ -139,6 +145,14 @@ impl<'a, L, PI, CONF> Cursor<'a, L, PI, CONF>
         }
     }
 
+    /// Returns an iterator over the children of the current node (none if it is a leaf), without
+    /// moving the cursor. Each child is yielded as `(index, path_info, info, child)`, where
+    /// `path_info` is the cumulative info at the start of the child, as `path_info()` would be
+    /// after descending to it.
+    pub fn children(&self) -> Children<'a, L, CONF::Ptr, PI> {
+        Children::new(self.current(), self.path_info())
+    }
+
     /// Returns a reference to the leaf's value if the current node is a leaf.
     pub fn leaf(&self) -> Option<&'a L> {
         self.current().leaf()
@@ -163,12 +177,35 @@ impl<'a, L, PI, CONF> Cursor<'a, L, PI, CONF>
         }
     }
 
+    /// The info gathered from the current node and all leaves after it, i.e., the part of the
+    /// root's info not yet consumed by `path_info`.
+    ///
+    /// Time: O(log n)
+    pub fn remaining_info(&self) -> L::Info {
+        let mut info = self.current().info();
+        for cstep in self.steps.iter().rev() {
+            for sibling in &cstep.nodes[cstep.idx + 1..] {
+                info = info.gather(sibling.info());
+            }
+        }
+        info
+    }
+
+    /// The path from the root to the current node, for use with `Node::get_by_path`.
+    pub fn tree_path(&self) -> TreePath {
+        TreePath(self.steps.iter().map(|cstep| cstep.idx).collect())
+    }
+
     pub fn reset(&mut self) {
         self.steps.clear();
     }
 
     pub fn ascend(&mut self) -> Option<&'a Node<L, CONF::Ptr>> {
-        self.steps.pop().map(|cstep| &cstep.nodes[cstep.idx])
+        let ret = self.steps.pop().map(|cstep| &cstep.nodes[cstep.idx]);
+        if ret.is_some() {
+            instrument!(Ascend { height: self.height() });
+        }
+        ret
     }
 
     pub fn descend_first(&mut self) -> Option<&'a Node<L, CONF::Ptr>> {
@@ -199,6 +236,7 @@ impl<'a, L, PI, CONF> Cursor<'a, L, PI, CONF>
     }
 
     fn descend_raw(&mut self, nodes: &'a [Node<L, CONF::Ptr>], idx: usize, path_info: PI) {
+        instrument!(Descend { height: nodes[idx].height() });
         // ArrayVec::push(e) returns Some(e) on overflow!
         assert!(self.steps.push(CStep { nodes, idx, path_info }).is_none());
     }
@@ -310,6 +348,30 @@ impl<'a, L, PI, CONF> Cursor<'a, L, PI, CONF>
         let short_lived: Option<&L> = <Self as CursorNav>::goto_max(self, path_info_sub);
         unsafe { ::std::mem::transmute(short_lived) }
     }
+
+    /// See [`CursorMut::seek`] for more details.
+    ///
+    /// [`CursorMut::seek`]: struct.CursorMut.html#method.seek
+    pub fn seek<PS: SubOrd<PI>>(&mut self, path_info_sub: PS) -> Seek {
+        <Self as CursorNav>::seek(self, path_info_sub)
+    }
+}
+
+impl<'a, L, PI, CONF> Cursor<'a, L, PI, CONF>
+    where L: Leaf + Deref + 'a,
+          PI: PathInfo<L::Info>,
+          CONF: CConf<'a, L, PI>,
+          CONF::Ptr: 'a,
+{
+    /// Moves the cursor to the leaf containing `path_info_sub` using `seek` (clamping to the first
+    /// or the last leaf if out of range), and returns its contents along with its `path_info` (such
+    /// as the offset of the chunk in a sequence). Returns `None` if the tree is empty.
+    ///
+    /// Conditions for correctness are the same as that of `goto_min`.
+    pub fn chunk_at<PS: SubOrd<PI>>(&mut self, path_info_sub: PS) -> Option<(&'a L::Target, PI)> {
+        self.seek(path_info_sub);
+        self.leaf().map(|leaf| (&**leaf, self.path_info()))
+    }
 }
 
 impl<'a, L, PI, CONF> CursorNav for Cursor<'a, L, PI, CONF>
@@ -369,6 +431,16 @@ impl<'a, L, PI, CONF> CursorNav for Cursor<'a, L, PI, CONF>
     fn _right_sibling(&mut self) -> Option<&Node<L, CONF::Ptr>> {
         self.right_sibling()
     }
+
+    fn _descend_at(&mut self, idx: usize, path_info: PI) -> Option<&Node<L, CONF::Ptr>> {
+        let nodes = self.current().children();
+        if idx < nodes.len() {
+            self.descend_raw(nodes, idx, path_info);
+            Some(&nodes[idx])
+        } else {
+            None
+        }
+    }
 }
 
 impl<'a, L, PI, CONF> IntoIterator for Cursor<'a, L, PI, CONF>
@@ -447,5 +519,97 @@ mod tests {
         assert_eq!(cursor.path_info(), ListPath { index: 19, run: 19*20/2 });
     }
 
+    #[test]
+    fn seek() {
+        use super::Seek;
+
+        let tree: NodeRc<_> = (0..100).map(ListLeaf).collect();
+        let mut cursor = Cursor::<_, ListPath>::new(&tree);
+        assert_eq!(cursor.seek(ListRun(1000)), Seek::Within);
+        assert_eq!((cursor.leaf(), cursor.path_info().run), (Some(&ListLeaf(45)), 990));
+        assert_eq!(cursor.seek(ListIndex(100)), Seek::Within);
+        assert_eq!(cursor.leaf(), Some(&ListLeaf(99)));
+        assert_eq!(cursor.seek(ListIndex(101)), Seek::After);
+        assert_eq!(cursor.leaf(), Some(&ListLeaf(99)));
+
+        let mut cursor_mut = ::cursor::CursorMut::<_, ListPath>::new();
+        assert_eq!(cursor_mut.seek(ListIndex(1)), Seek::After);
+        cursor_mut.insert_leaf(ListLeaf(1), false);
+        assert_eq!(cursor_mut.seek(ListIndex(5)), Seek::After);
+        assert_eq!(cursor_mut.leaf(), Some(&ListLeaf(1)));
+    }
+
+    #[test]
+    fn remaining_info() {
+        let tree: NodeRc<_> = (1..101).map(ListLeaf).collect();
+        let mut cursor = Cursor::<_, ListPath>::new(&tree);
+        assert_eq!(cursor.remaining_info(), tree.info());
+        cursor.goto_min(ListIndex(60));
+        assert_eq!(cursor.leaf().unwrap(), &ListLeaf(61));
+        assert_eq!(cursor.remaining_info(), ListInfo { count: 40, sum: (61..101).sum() });
+    }
+
+    #[test]
+    fn current_subtree() {
+        let tree: NodeRc<_> = (0..100).map(ListLeaf).collect();
+        let mut cursor = Cursor::<_, ListPath>::new(&tree);
+        let mut subtrees = Vec::new();
+        let mut next = cursor.descend_first();
+        while next.is_some() {
+            subtrees.push(cursor.current());
+            next = cursor.right_sibling();
+        }
+        drop(cursor);
+        assert_eq!(subtrees.len(), tree.children().len());
+        assert_eq!(subtrees.iter().map(|subtree| subtree.info().count).sum::<usize>(), 100);
+    }
+
+    #[test]
+    fn children() {
+        let tree: NodeRc<_> = (0..1000).map(ListLeaf).collect();
+        let mut cursor = Cursor::<_, ListPath>::new(&tree);
+        cursor.descend_last();
+        let children: Vec<_> = cursor.children().collect();
+        assert_eq!(children.len(), cursor.current().children().len());
+        let (index, path_info, info, child) = children[1];
+        assert_eq!(info, child.info());
+        cursor.descend_first();
+        cursor.right_sibling();
+        assert_eq!((index, path_info), (1, cursor.path_info()));
+        assert!(::std::ptr::eq(child, cursor.current()));
+        while cursor.descend_first().is_some() {}
+        assert_eq!(cursor.children().len(), 0);
+    }
+
+    #[test]
+    fn descend_probes() {
+        use std::cell::Cell;
+        use std::cmp::Ordering;
+        use traits::SubOrd;
+
+        struct Counted<'a>(usize, &'a Cell<usize>);
+        impl<'a> SubOrd<ListPath> for Counted<'a> {
+            fn sub_cmp(&self, rhs: &ListPath) -> Ordering {
+                self.1.set(self.1.get() + 1);
+                self.0.cmp(&rhs.index)
+            }
+        }
+
+        let tree: NodeRc<_> = (0..4096).map(ListLeaf).collect();
+        assert_eq!(tree.height(), 3);
+        let probes = Cell::new(0);
+        let mut cursor = Cursor::<_, ListPath>::new(&tree);
+        for i in 0..4096 {
+            probes.set(0);
+            cursor.reset();
+            assert_eq!(cursor.goto_min(Counted(i, &probes)), Some(&ListLeaf(i)));
+            assert!(probes.get() <= 16, "{} probes for {}", probes.get(), i); // 1 + 3 * 5
+            probes.set(0);
+            cursor.reset();
+            assert_eq!(cursor.goto_max(Counted(i + 1, &probes)), Some(&ListLeaf(i)));
+            assert!(probes.get() <= 16, "{} probes for {}", probes.get(), i);
+        }
+    }
+
     // FIXME need more tests
 }
@@ -0,0 +1,243 @@
+use node::{Node, NodesPtr};
+use traits::{Leaf, LeafSplit, PathInfo};
+
+use std::cmp;
+use std::slice;
+
+// One side of a `ZipCursor`.
+struct Side<'a, L: Leaf + 'a, NP: NodesPtr<L> + 'a, PI> {
+    next: Option<&'a Node<L, NP>>, // the next subtree not yet passed
+    part: Option<L>, // the rest of a partly passed leaf, which comes before `next`
+    stack: Vec<slice::Iter<'a, Node<L, NP>>>,
+    path_info: PI, // the path info at the start of `part`, or else of `next`
+}
+
+impl<'a, L, NP, PI> Side<'a, L, NP, PI>
+    where L: LeafSplit, NP: NodesPtr<L>, PI: PathInfo<L::Info>,
+{
+    // The units of the metric in `part`, or else in `next`.
+    fn units<F: Fn(&L::Info) -> usize>(&self, metric: &F) -> Option<usize> {
+        match self.part {
+            Some(ref part) => Some(metric(&part.compute_info())),
+            None => self.next.map(|node| metric(&node.info())),
+        }
+    }
+
+    // Whether `next` is the current subtree, and not a leaf.
+    fn at_internal(&self) -> bool {
+        self.part.is_none() && self.next.is_some_and(|node| !node.is_leaf())
+    }
+
+    // Move past `part`, or else `next`.
+    fn pass(&mut self) {
+        if let Some(part) = self.part.take() {
+            self.path_info = self.path_info.extend(part.compute_info());
+        } else if let Some(node) = self.next {
+            self.path_info = self.path_info.extend(node.info());
+            self.advance();
+        }
+    }
+
+    // Replace `next` with the subtree right after it.
+    fn advance(&mut self) {
+        self.next = None;
+        while let Some(iter) = self.stack.last_mut() {
+            if let Some(node) = iter.next() {
+                self.next = Some(node);
+                return;
+            }
+            self.stack.pop();
+        }
+    }
+
+    // Replace `next` with its first child.
+    fn descend(&mut self) {
+        let node = self.next.take().unwrap();
+        self.stack.push(node.children().iter());
+        self.next = self.stack.last_mut().unwrap().next();
+    }
+
+    // Move past the first `at` units of the current leaf (having `units` units), and return them.
+    fn split_off(&mut self, at: usize, units: usize) -> L {
+        let mut head = match self.part.take() {
+            Some(part) => part,
+            None => {
+                let leaf = self.next.and_then(Node::leaf).unwrap().clone();
+                self.advance();
+                leaf
+            }
+        };
+        if at < units {
+            self.part = Some(head.split_off(at));
+        }
+        self.path_info = self.path_info.extend(head.compute_info());
+        head
+    }
+}
+
+/// A cursor that walks two trees (typically two versions of the same tree) in lockstep, advancing
+/// both by the same amount of an additive metric of the info, and stops where their contents
+/// diverge.
+///
+/// The units of the metric should be the offsets used by `LeafSplit::split_off`, such as bytes for
+/// text. The trees may thus be chunked into leaves differently: where the leaf boundaries of both
+/// sides differ, leaves are compared piecewise, up to the next boundary of either side, until the
+/// boundaries coincide again.
+///
+/// Subtrees shared between the trees at the same position are passed over as a whole, so finding
+/// the first divergence between a tree and an edited copy of it takes O(log n) time, regardless of
+/// where the edit is.
+pub struct ZipCursor<'a, L: Leaf + 'a, NP: NodesPtr<L> + 'a, PI, F> {
+    left: Side<'a, L, NP, PI>,
+    right: Side<'a, L, NP, PI>,
+    metric: F,
+}
+
+impl<'a, L, NP, PI, F> ZipCursor<'a, L, NP, PI, F>
+    where L: LeafSplit + PartialEq, NP: NodesPtr<L>, PI: PathInfo<L::Info>,
+          F: Fn(&L::Info) -> usize,
+{
+    /// Create a cursor at the start of both `left` and `right`, advancing by `metric`.
+    pub fn new(left: &'a Node<L, NP>, right: &'a Node<L, NP>, metric: F) -> Self {
+        let side = |root| Side {
+            next: Some(root),
+            part: None,
+            stack: Vec::new(),
+            path_info: PI::identity(),
+        };
+        ZipCursor { left: side(left), right: side(right), metric }
+    }
+
+    /// The path info at the current position in each tree.
+    pub fn path_info(&self) -> (PI, PI) {
+        (self.left.path_info, self.right.path_info)
+    }
+
+    /// Advance both sides past the contents (and shared subtrees) that are equal, up to the next
+    /// pair of differing pieces of leaves, and return the path info at the start of them in each
+    /// tree. If one tree runs out before the other, that is a divergence as well. Returns `None`
+    /// (at the end of both trees) if no divergence is left.
+    ///
+    /// The differing pieces are passed over, so that the next call finds the next divergence.
+    pub fn next_divergence(&mut self) -> Option<(PI, PI)> {
+        loop {
+            let (left_units, right_units) = match (self.left.units(&self.metric),
+                                                   self.right.units(&self.metric)) {
+                (Some(0), _) => {
+                    self.left.pass(); // empty
+                    continue;
+                }
+                (_, Some(0)) => {
+                    self.right.pass();
+                    continue;
+                }
+                (Some(left_units), Some(right_units)) => (left_units, right_units),
+                (None, None) => return None,
+                _ => {
+                    let found = self.path_info();
+                    self.left.pass();
+                    self.right.pass();
+                    return Some(found);
+                }
+            };
+            let (left_internal, right_internal) = (self.left.at_internal(),
+                                                   self.right.at_internal());
+            let whole = self.left.part.is_none() && self.right.part.is_none();
+            if whole && self.left.next.unwrap().ptr_eq(self.right.next.unwrap()) {
+                self.left.pass();
+                self.right.pass();
+            } else if left_internal && right_internal {
+                if left_units >= right_units {
+                    self.left.descend();
+                }
+                if left_units <= right_units {
+                    self.right.descend();
+                }
+            } else if left_internal {
+                self.left.descend();
+            } else if right_internal {
+                self.right.descend();
+            } else if whole && left_units == right_units {
+                // the leaf boundaries coincide
+                let found = self.path_info();
+                let equal = self.left.next.unwrap().leaf() == self.right.next.unwrap().leaf();
+                self.left.pass();
+                self.right.pass();
+                if !equal {
+                    return Some(found);
+                }
+            } else {
+                let found = self.path_info();
+                let units = cmp::min(left_units, right_units);
+                let left = self.left.split_off(units, left_units);
+                let right = self.right.split_off(units, right_units);
+                if left != right {
+                    return Some(found);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ZipCursor;
+    use cursor::CursorMut;
+    use leaf::{ByteIndex, StringChunk, TextInfo};
+    use test_help::*;
+
+    fn text() -> String {
+        (0..1000).map(|i| format!("line {}\n", i)).collect()
+    }
+
+    // A tree of `text` in chunks of `len` bytes (`text` must be ASCII).
+    fn chunked(text: &str, len: usize) -> NodeRc<StringChunk> {
+        text.as_bytes().chunks(len)
+            .map(|chunk| StringChunk::new(::std::str::from_utf8(chunk).unwrap()))
+            .collect()
+    }
+
+    fn bytes(info: &TextInfo) -> usize {
+        info.bytes
+    }
+
+    #[test]
+    fn divergences() {
+        let old = chunked(&text(), 10);
+        let mut cursor_mut = CursorMut::<_, TextInfo>::from_node(old.clone());
+        for &pos in &[3000, 7000] {
+            cursor_mut.goto_min(ByteIndex(pos));
+            cursor_mut.leaf_update(|chunk| *chunk = StringChunk::new("#".repeat(chunk.len())));
+        }
+        let new = cursor_mut.into_root().unwrap();
+
+        let mut zip = ZipCursor::<_, _, TextInfo, _>::new(&old, &new, bytes);
+        let (left, right) = zip.next_divergence().unwrap();
+        assert_eq!((left.bytes, right.bytes), (3000, 3000));
+        assert_eq!(zip.next_divergence().map(|(left, _)| left.bytes), Some(7000));
+        assert_eq!(zip.next_divergence(), None);
+
+        let shorter = chunked(&text()[..5000], 10);
+        let mut zip = ZipCursor::<_, _, TextInfo, _>::new(&old, &shorter, bytes);
+        let (left, right) = zip.next_divergence().unwrap();
+        assert_eq!((left.bytes, right.bytes), (5000, 5000));
+    }
+
+    #[test]
+    fn differently_chunked() {
+        let text = text();
+        let (sevens, tens) = (chunked(&text, 7), chunked(&text, 10));
+        assert_eq!(ZipCursor::<_, _, TextInfo, _>::new(&sevens, &tens, bytes).next_divergence(),
+                   None);
+
+        let mut edited = text.clone().into_bytes();
+        edited[1234] = b'#';
+        let edited = chunked(&String::from_utf8(edited).unwrap(), 10);
+        let mut zip = ZipCursor::<_, _, TextInfo, _>::new(&sevens, &edited, bytes);
+        // the piece between the boundaries at 1232 and 1239 of `sevens`, with none of `edited`
+        let (left, right) = zip.next_divergence().unwrap();
+        assert_eq!((left.bytes, right.bytes), (1232, 1232));
+        assert_eq!(zip.path_info().0, TextInfo::of(&text[..1239]));
+        assert_eq!(zip.next_divergence(), None);
+    }
+}
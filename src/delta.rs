@@ -0,0 +1,103 @@
+//! Descriptions of edits made to a tree, in terms of path info.
+
+use std::ops::{Add, Sub};
+
+/// An edit that replaced the extent `start..old_end` of a tree by `start..new_end`, where the
+/// bounds are path info (such as offsets) at the boundaries of leaves. An insertion has an empty
+/// old extent, and a removal an empty new extent.
+///
+/// `CursorMut` can record the deltas of its mutations (see `CursorMut::record_deltas`), so that
+/// positions into the tree kept elsewhere (such as marks) can be adjusted without diffing.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct EditDelta<PI> {
+    pub start: PI,
+    pub old_end: PI,
+    pub new_end: PI,
+}
+
+/// Which side of an edit a position sticks to, when the edit is made right at (or around) it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Gravity {
+    /// Stay before the inserted contents, like the end of a selection.
+    Left,
+    /// Move past the inserted contents, like a text cursor.
+    Right,
+}
+
+impl<PI> EditDelta<PI> {
+    /// Convert each bound using `f`. Useful for adjusting positions that are in terms of only one
+    /// of the components of the path info (such as the offset of a `ListPath`).
+    pub fn map<T, F: FnMut(PI) -> T>(self, mut f: F) -> EditDelta<T> {
+        EditDelta { start: f(self.start), old_end: f(self.old_end), new_end: f(self.new_end) }
+    }
+}
+
+impl<PI> EditDelta<PI> where PI: Copy + Ord + Add<Output=PI> + Sub<Output=PI> {
+    /// Returns where `pos` (a position before the edit) ends up after the edit.
+    ///
+    /// Positions before the edit are not affected, and those after it are shifted by the change in
+    /// length. Positions within the replaced extent (including its bounds) move to `start` if they
+    /// have `Left` gravity, or to `new_end` if they have `Right` gravity.
+    pub fn adjust(&self, pos: PI, gravity: Gravity) -> PI {
+        if pos < self.start {
+            pos
+        } else if pos > self.old_end {
+            pos - self.old_end + self.new_end
+        } else {
+            match gravity {
+                Gravity::Left => self.start,
+                Gravity::Right => self.new_end,
+            }
+        }
+    }
+}
+
+/// Adjust `positions` for each of `deltas` in turn (such as those taken from a `CursorMut`), so
+/// that they point to the same places in the tree after the edits as before them.
+pub fn adjust_all<PI, I>(deltas: I, positions: &mut [(PI, Gravity)])
+    where PI: Copy + Ord + Add<Output=PI> + Sub<Output=PI>,
+          I: IntoIterator<Item=EditDelta<PI>>,
+{
+    for delta in deltas {
+        for &mut (ref mut pos, gravity) in positions.iter_mut() {
+            *pos = delta.adjust(*pos, gravity);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{adjust_all, EditDelta, Gravity};
+    use cursor::CursorMut;
+    use test_help::*;
+
+    #[test]
+    fn adjust() {
+        let insert = EditDelta { start: 5, old_end: 5, new_end: 8 };
+        assert_eq!(insert.adjust(4, Gravity::Right), 4);
+        assert_eq!(insert.adjust(5, Gravity::Left), 5);
+        assert_eq!(insert.adjust(5, Gravity::Right), 8);
+        assert_eq!(insert.adjust(6, Gravity::Left), 9);
+
+        let replace = EditDelta { start: 5, old_end: 10, new_end: 7 };
+        assert_eq!(replace.adjust(5, Gravity::Right), 7);
+        assert_eq!(replace.adjust(7, Gravity::Left), 5);
+        assert_eq!(replace.adjust(10, Gravity::Left), 5);
+        assert_eq!(replace.adjust(10, Gravity::Right), 7);
+        assert_eq!(replace.adjust(11, Gravity::Left), 8);
+    }
+
+    #[test]
+    fn adjust_recorded() {
+        let mut cursor_mut: CursorMut<_, ListPath> = (0..100).map(ListLeaf).collect();
+        cursor_mut.record_deltas(true);
+        cursor_mut.goto_min(ListIndex(20));
+        cursor_mut.insert_leaf(ListLeaf(100), false);
+        cursor_mut.goto_min(ListIndex(50));
+        cursor_mut.remove_leaf();
+        let mut positions = [(20, Gravity::Left), (20, Gravity::Right), (60, Gravity::Left)];
+        let deltas = cursor_mut.take_deltas().into_iter().map(|d| d.map(|path| path.index));
+        adjust_all(deltas, &mut positions);
+        assert_eq!(positions, [(20, Gravity::Left), (21, Gravity::Right), (60, Gravity::Left)]);
+    }
+}
@@ -0,0 +1,218 @@
+//! A persistent double-ended queue.
+
+use builder::TreeBuilder;
+use cursor::CursorMut;
+use cursor::conf::{CMutConf, PtrMark, Rc33M};
+use iter::Leaves;
+use node::Node;
+use traits::Leaf;
+
+use std::iter::FromIterator;
+
+/// An item in a `Deque`. Each item is a leaf counting as one.
+#[derive(Clone, Debug)]
+pub struct Item<T>(T);
+
+impl<T: Clone> Leaf for Item<T> {
+    type Info = usize;
+
+    fn compute_info(&self) -> usize {
+        1
+    }
+}
+
+type ItemNode<T, CONF> = Node<Item<T>, <CONF as PtrMark<Item<T>>>::Ptr>;
+
+/// A double-ended queue with O(log n) pushes and pops at both ends, indexing, concatenation and
+/// splitting.
+///
+/// Cloning a deque takes O(1) time, and the clones share all the nodes that neither of them
+/// modifies afterwards. So keeping many versions of a deque around is cheap.
+pub struct Deque<T: Clone, CONF = Rc33M>
+    where CONF: CMutConf<Item<T>, usize>,
+{
+    root: Option<ItemNode<T, CONF>>,
+}
+
+impl<T: Clone, CONF> Clone for Deque<T, CONF>
+    where CONF: CMutConf<Item<T>, usize>,
+{
+    fn clone(&self) -> Self {
+        Deque { root: self.root.clone() }
+    }
+}
+
+impl<T: Clone, CONF> Default for Deque<T, CONF>
+    where CONF: CMutConf<Item<T>, usize>,
+{
+    fn default() -> Self {
+        Deque::new()
+    }
+}
+
+impl<T: Clone, CONF> Deque<T, CONF>
+    where CONF: CMutConf<Item<T>, usize>,
+{
+    pub fn new() -> Self {
+        Deque { root: None }
+    }
+
+    /// The number of items.
+    pub fn len(&self) -> usize {
+        self.root.as_ref().map_or(0, Node::info)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.root.is_none()
+    }
+
+    /// Returns the item at `index`, or `None` if `index` is out of bounds.
+    ///
+    /// Time: O(log n)
+    pub fn get(&self, index: usize) -> Option<&T> {
+        self.root.as_ref().and_then(|root| root.get(index)).map(|item| &item.0)
+    }
+
+    pub fn front(&self) -> Option<&T> {
+        self.get(0)
+    }
+
+    pub fn back(&self) -> Option<&T> {
+        self.len().checked_sub(1).and_then(|index| self.get(index))
+    }
+
+    pub fn push_back(&mut self, value: T) {
+        self.root = Some(match self.root.take() {
+            Some(mut root) => {
+                root.push_back(Item(value));
+                root
+            }
+            None => Node::from_leaf(Item(value)),
+        });
+    }
+
+    pub fn push_front(&mut self, value: T) {
+        self.root = Some(match self.root.take() {
+            Some(mut root) => {
+                root.push_front(Item(value));
+                root
+            }
+            None => Node::from_leaf(Item(value)),
+        });
+    }
+
+    pub fn pop_back(&mut self) -> Option<T> {
+        let (rest, item) = self.root.take()?.pop_back();
+        self.root = rest;
+        Some(item.0)
+    }
+
+    pub fn pop_front(&mut self) -> Option<T> {
+        let (rest, item) = self.root.take()?.pop_front();
+        self.root = rest;
+        Some(item.0)
+    }
+
+    /// Move all items of `other` to the back of this deque.
+    ///
+    /// Time: O(log n)
+    pub fn append(&mut self, other: Deque<T, CONF>) {
+        self.root = match (self.root.take(), other.root) {
+            (Some(root1), Some(root2)) => Some(Node::concat(root1, root2)),
+            (root1, root2) => root1.or(root2),
+        };
+    }
+
+    /// Split the deque into two at `at`, and return the items from `at` onwards.
+    ///
+    /// Panics if `at > self.len()`.
+    ///
+    /// Time: O(log n)
+    pub fn split_off(&mut self, at: usize) -> Deque<T, CONF> {
+        let len = self.len();
+        assert!(at <= len, "split index {} out of bounds (len: {})", at, len);
+        if at == len {
+            return Deque::new();
+        }
+        let mut cursor = CursorMut::<_, usize, CONF>::from_node(self.root.take().unwrap());
+        cursor.goto_min(at);
+        let right = cursor.split_off();
+        self.root = cursor.into_root();
+        Deque { root: right }
+    }
+
+    /// Returns an iterator over the items, from front to back.
+    pub fn iter(&self) -> Iter<'_, T, CONF> {
+        Iter { leaves: self.root.as_ref().map(Node::leaves) }
+    }
+}
+
+/// An iterator over the items of a `Deque`. See `Deque::iter`.
+pub struct Iter<'a, T: Clone + 'a, CONF: PtrMark<Item<T>> + 'a> {
+    leaves: Option<Leaves<'a, Item<T>, CONF::Ptr>>,
+}
+
+impl<'a, T: Clone, CONF: PtrMark<Item<T>>> Iterator for Iter<'a, T, CONF> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<&'a T> {
+        self.leaves.as_mut().and_then(|leaves| leaves.next()).map(|item| &item.0)
+    }
+}
+
+impl<T: Clone, CONF> Extend<T> for Deque<T, CONF>
+    where CONF: CMutConf<Item<T>, usize>,
+{
+    fn extend<I: IntoIterator<Item=T>>(&mut self, iter: I) {
+        for value in iter {
+            self.push_back(value);
+        }
+    }
+}
+
+impl<T: Clone, CONF> FromIterator<T> for Deque<T, CONF>
+    where CONF: CMutConf<Item<T>, usize>,
+{
+    fn from_iter<I: IntoIterator<Item=T>>(iter: I) -> Self {
+        Deque { root: iter.into_iter().map(Item).collect::<TreeBuilder<_, _>>().build() }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Deque;
+
+    #[test]
+    fn push_pop() {
+        let mut deque: Deque<usize> = Deque::new();
+        for i in 0..100 {
+            deque.push_back(i);
+            deque.push_front(i);
+        }
+        let snapshot = deque.clone();
+        assert_eq!((deque.len(), deque.front(), deque.back()), (200, Some(&99), Some(&99)));
+        for i in (0..100).rev() {
+            assert_eq!(deque.pop_front(), Some(i));
+            assert_eq!(deque.pop_back(), Some(i));
+        }
+        assert!(deque.is_empty());
+        assert_eq!(deque.pop_back(), None);
+        assert!(snapshot.iter().cloned().eq((0..100).rev().chain(0..100)));
+    }
+
+    #[test]
+    fn split_append() {
+        let mut deque: Deque<usize> = (0..1000).collect();
+        for &at in &[1000, 700, 0, 300] {
+            let mut left = deque.clone();
+            let right = left.split_off(at);
+            assert!(left.iter().cloned().eq(0..at));
+            assert!(right.iter().cloned().eq(at..1000));
+            assert_eq!(right.get(0), if at < 1000 { Some(&at) } else { None });
+            left.append(right);
+            assert!(left.iter().cloned().eq(0..1000));
+        }
+        deque.extend(1000..1010);
+        assert_eq!(deque.len(), 1010);
+    }
+}
@@ -0,0 +1,445 @@
+//! Diffing and three-way merging of tree versions.
+//!
+//! Versions derived from each other through copy-on-write editing share most of their internal
+//! nodes. Such shared subtrees are compared by pointer identity and treated as single units, so
+//! only the leaves of modified subtrees are ever compared individually.
+
+use node::{concat_opt, Node, NodesPtr};
+use traits::Leaf;
+
+use std::collections::HashSet;
+use std::ops::Range;
+
+/// A replacement of the leaves in `old` by the leaves in `new`, as leaf-index ranges.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Change {
+    pub old: Range<usize>,
+    pub new: Range<usize>,
+}
+
+/// A region of `base` that was changed differently by both sides of a merge, as leaf-index
+/// ranges into each of the three versions.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Conflict {
+    pub base: Range<usize>,
+    pub ours: Range<usize>,
+    pub theirs: Range<usize>,
+}
+
+/// Compute the changes to the leaves of `old` that produce the leaves of `new`, in order.
+///
+/// The changes are minimal in the number of compared units, where a subtree shared between both
+/// versions counts as a single unit. Takes O((N + M) D) time where N and M are the number of
+/// units, and D is the size of the difference.
+pub fn diff<L, NP>(old: &Node<L, NP>, new: &Node<L, NP>) -> Vec<Change>
+    where L: Leaf + PartialEq, NP: NodesPtr<L>,
+{
+    let mut old_nodes = HashSet::new();
+    collect_internal(old, &mut old_nodes);
+    let mut new_tokens = Vec::new();
+    let mut new_nodes = HashSet::new();
+    tokenize(new, &old_nodes, &mut new_nodes, &mut new_tokens);
+    let mut old_tokens = Vec::new();
+    tokenize(old, &new_nodes, &mut HashSet::new(), &mut old_tokens);
+
+    let old_offsets = leaf_offsets(&old_tokens);
+    let new_offsets = leaf_offsets(&new_tokens);
+    shortest_edit(&old_tokens, &new_tokens).into_iter()
+        .map(|(old, new)| Change {
+            old: old_offsets[old.start]..old_offsets[old.end],
+            new: new_offsets[new.start]..new_offsets[new.end],
+        })
+        .collect()
+}
+
+/// Combine the changes made in `ours` and `theirs`, both derived from `base`.
+///
+/// Changes of either side that do not overlap are applied to `base`. Two changes overlap if
+/// their ranges in `base` intersect, or if both insert at the same position. Identical changes
+/// made on both sides are applied once. Returns the merged version (`None` if empty), or the list
+/// of all overlapping regions.
+///
+/// The merged version is spliced together out of subtrees of the three versions, so that it
+/// shares all subtrees within unchanged regions with `base`, and within changes with either side.
+pub fn merge<L, NP>(base: &Node<L, NP>, ours: &Node<L, NP>, theirs: &Node<L, NP>)
+    -> Result<Option<Node<L, NP>>, Vec<Conflict>>
+    where L: Leaf + PartialEq, NP: NodesPtr<L>,
+{
+    let mut changes: Vec<(Side, Change)> = Vec::new();
+    changes.extend(diff(base, ours).into_iter().map(|c| (Side::Ours, c)));
+    changes.extend(diff(base, theirs).into_iter().map(|c| (Side::Theirs, c)));
+    changes.sort_by_key(|(_, c)| (c.old.start, c.old.end));
+
+    let ours_leaves = leaves(ours);
+    let theirs_leaves = leaves(theirs);
+
+    // the leaf ranges making up the merged version, of `base` (`None`) or either side
+    let mut merged: Vec<(Option<Side>, Range<usize>)> = Vec::new();
+    let mut conflicts = Vec::new();
+    let mut base_pos = 0;
+    let (mut ours_delta, mut theirs_delta) = (0isize, 0isize);
+    let mut changes = changes.into_iter().peekable();
+    while let Some((side, change)) = changes.next() {
+        // gather all changes transitively overlapping with this one
+        let mut group = vec![(side, change)];
+        let mut end = group[0].1.old.end;
+        let mut insert_at_end = group[0].1.old.start == end;
+        while let Some((_, next)) = changes.peek() {
+            let next_empty = next.old.start == next.old.end;
+            if next.old.start < end || (next.old.start == end && next_empty && insert_at_end) {
+                if next.old.end > end {
+                    end = next.old.end;
+                    insert_at_end = false;
+                }
+                insert_at_end |= next_empty && next.old.end == end;
+            } else {
+                break;
+            }
+            group.push(changes.next().unwrap());
+        }
+
+        let start = group[0].1.old.start;
+        merged.push((None, base_pos..start));
+        base_pos = end;
+        let same_side = group.iter().all(|&(side, _)| side == group[0].0);
+        let identical = group.len() == 2 && {
+            let (c1, c2) = (&group[0].1, &group[1].1);
+            c1.old == c2.old && ours_leaves[c1.new.clone()] == theirs_leaves[c2.new.clone()]
+        };
+        if same_side || identical {
+            let (side, ref change) = group[0];
+            merged.push((Some(side), change.new.clone()));
+        } else {
+            let (mut ours_grow, mut theirs_grow) = (0isize, 0isize);
+            for &(side, ref change) in &group {
+                let grow = change.new.len() as isize - change.old.len() as isize;
+                match side {
+                    Side::Ours => ours_grow += grow,
+                    Side::Theirs => theirs_grow += grow,
+                }
+            }
+            let shift = |pos: usize, delta: isize| (pos as isize + delta) as usize;
+            conflicts.push(Conflict {
+                base: start..end,
+                ours: shift(start, ours_delta)..shift(end, ours_delta + ours_grow),
+                theirs: shift(start, theirs_delta)..shift(end, theirs_delta + theirs_grow),
+            });
+        }
+        for (side, change) in group {
+            let grow = change.new.len() as isize - change.old.len() as isize;
+            match side {
+                Side::Ours => ours_delta += grow,
+                Side::Theirs => theirs_delta += grow,
+            }
+        }
+    }
+    merged.push((None, base_pos..count_leaves(base)));
+
+    if !conflicts.is_empty() {
+        return Err(conflicts);
+    }
+    merged.retain(|(_, range)| range.start < range.end);
+    let mut parts: Vec<Option<Node<L, NP>>> = merged.iter().map(|_| None).collect();
+    for &(version, node) in &[(None, base), (Some(Side::Ours), ours), (Some(Side::Theirs), theirs)] {
+        let (indices, ranges): (Vec<_>, Vec<_>) = merged.iter().enumerate()
+            .filter(|&(_, &(v, _))| v == version)
+            .map(|(i, (_, range))| (i, range.clone()))
+            .unzip();
+        for (i, part) in indices.into_iter().zip(extract(node, &ranges)) {
+            parts[i] = part;
+        }
+    }
+    Ok(parts.into_iter().fold(None, concat_opt))
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Side {
+    Ours,
+    Theirs,
+}
+
+// A unit of comparison: either a subtree present in both versions, or a single leaf.
+enum Token<'a, L: Leaf + 'a, NP: 'a> {
+    Shared(&'a Node<L, NP>),
+    Leaf(&'a L),
+}
+
+impl<'a, L, NP> Token<'a, L, NP> where L: Leaf + PartialEq, NP: NodesPtr<L> {
+    fn same(&self, other: &Self) -> bool {
+        match (self, other) {
+            (&Token::Shared(node1), &Token::Shared(node2)) => node1.ptr_eq(node2),
+            (&Token::Leaf(leaf1), &Token::Leaf(leaf2)) => leaf1 == leaf2,
+            _ => false,
+        }
+    }
+}
+
+type NodeId<L, NP> = *const Node<L, NP>;
+
+fn node_id<L: Leaf, NP: NodesPtr<L>>(node: &Node<L, NP>) -> NodeId<L, NP> {
+    node.children().as_ptr()
+}
+
+fn collect_internal<L, NP>(node: &Node<L, NP>, ids: &mut HashSet<NodeId<L, NP>>)
+    where L: Leaf, NP: NodesPtr<L>,
+{
+    if !node.is_leaf() {
+        ids.insert(node_id(node));
+        for child in node.children() {
+            collect_internal(child, ids);
+        }
+    }
+}
+
+// Split `node` into tokens, keeping subtrees found in `shared` whole. The ids of all visited
+// internal nodes are added to `visited`.
+fn tokenize<'a, L, NP>(node: &'a Node<L, NP>,
+                       shared: &HashSet<NodeId<L, NP>>,
+                       visited: &mut HashSet<NodeId<L, NP>>,
+                       tokens: &mut Vec<Token<'a, L, NP>>)
+    where L: Leaf, NP: NodesPtr<L>,
+{
+    match node.leaf() {
+        Some(leaf) => tokens.push(Token::Leaf(leaf)),
+        None => {
+            visited.insert(node_id(node));
+            if shared.contains(&node_id(node)) {
+                tokens.push(Token::Shared(node));
+            } else {
+                for child in node.children() {
+                    tokenize(child, shared, visited, tokens);
+                }
+            }
+        }
+    }
+}
+
+pub(crate) fn count_leaves<L: Leaf, NP: NodesPtr<L>>(node: &Node<L, NP>) -> usize {
+    match node.height() {
+        0 => 1,
+        1 => node.children().len(),
+        _ => node.children().iter().map(count_leaves).sum(),
+    }
+}
+
+// The leaf index at which each token starts, followed by the total number of leaves.
+fn leaf_offsets<L: Leaf, NP: NodesPtr<L>>(tokens: &[Token<L, NP>]) -> Vec<usize> {
+    let mut offsets = Vec::with_capacity(tokens.len() + 1);
+    let mut offset = 0;
+    offsets.push(0);
+    for token in tokens {
+        offset += match *token {
+            Token::Shared(node) => count_leaves(node),
+            Token::Leaf(_) => 1,
+        };
+        offsets.push(offset);
+    }
+    offsets
+}
+
+// Returns the subtrees covering each of `ranges` (sorted and disjoint leaf-index ranges) of the
+// leaves of `node`, concatenated out of whole subtrees of `node` wherever possible.
+fn extract<L, NP>(node: &Node<L, NP>, ranges: &[Range<usize>]) -> Vec<Option<Node<L, NP>>>
+    where L: Leaf, NP: NodesPtr<L>,
+{
+    let mut parts: Vec<_> = ranges.iter().map(|_| None).collect();
+    extract_rec(node, 0, count_leaves(node), ranges, &mut parts);
+    parts
+}
+
+fn extract_rec<L, NP>(node: &Node<L, NP>, offset: usize, count: usize, ranges: &[Range<usize>],
+                      parts: &mut [Option<Node<L, NP>>])
+    where L: Leaf, NP: NodesPtr<L>,
+{
+    let lo = ranges.partition_point(|range| range.end <= offset);
+    let hi = lo + ranges[lo..].partition_point(|range| range.start < offset + count);
+    match hi - lo {
+        0 => (),
+        1 if ranges[lo].start <= offset && offset + count <= ranges[lo].end => {
+            parts[lo] = concat_opt(parts[lo].take(), Some(node.clone()));
+        }
+        _ => {
+            let mut offset = offset;
+            for child in node.children() {
+                let count = count_leaves(child);
+                extract_rec(child, offset, count, &ranges[lo..hi], &mut parts[lo..hi]);
+                offset += count;
+            }
+        }
+    }
+}
+
+fn leaves<L: Leaf, NP: NodesPtr<L>>(node: &Node<L, NP>) -> Vec<&L> {
+    fn visit<'a, L: Leaf, NP: NodesPtr<L>>(node: &'a Node<L, NP>, leaves: &mut Vec<&'a L>) {
+        match node.leaf() {
+            Some(leaf) => leaves.push(leaf),
+            None => for child in node.children() { visit(child, leaves) },
+        }
+    }
+    let mut leaves = Vec::new();
+    visit(node, &mut leaves);
+    leaves
+}
+
+// Myers' O(ND) difference algorithm. Returns the differing regions as pairs of token ranges.
+fn shortest_edit<L, NP>(old: &[Token<L, NP>], new: &[Token<L, NP>]) -> Vec<(Range<usize>, Range<usize>)>
+    where L: Leaf + PartialEq, NP: NodesPtr<L>,
+{
+    let (n, m) = (old.len() as isize, new.len() as isize);
+    let max = n + m;
+    let idx = |k: isize| (k + max) as usize;
+    let mut v = vec![0isize; 2 * max as usize + 2];
+    let mut trace = Vec::new();
+    'search: for d in 0..max + 1 {
+        trace.push(v.clone());
+        let mut k = -d;
+        while k <= d {
+            let mut x = if k == -d || (k != d && v[idx(k - 1)] < v[idx(k + 1)]) {
+                v[idx(k + 1)]
+            } else {
+                v[idx(k - 1)] + 1
+            };
+            let mut y = x - k;
+            while x < n && y < m && old[x as usize].same(&new[y as usize]) {
+                x += 1;
+                y += 1;
+            }
+            v[idx(k)] = x;
+            if x >= n && y >= m {
+                break 'search;
+            }
+            k += 2;
+        }
+    }
+
+    // walk back through the trace collecting matching pairs
+    let mut matches = Vec::new();
+    let (mut x, mut y) = (n, m);
+    for (d, v) in trace.iter().enumerate().rev() {
+        let d = d as isize;
+        let k = x - y;
+        let (prev_x, prev_y) = if d == 0 {
+            (0, 0)
+        } else {
+            let prev_k = if k == -d || (k != d && v[idx(k - 1)] < v[idx(k + 1)]) { k + 1 } else { k - 1 };
+            (v[idx(prev_k)], v[idx(prev_k)] - prev_k)
+        };
+        while x > prev_x && y > prev_y {
+            x -= 1;
+            y -= 1;
+            matches.push((x as usize, y as usize));
+        }
+        x = prev_x;
+        y = prev_y;
+    }
+    matches.reverse();
+
+    let mut hunks = Vec::new();
+    let (mut x, mut y) = (0, 0);
+    for (mx, my) in matches.into_iter().chain(Some((n as usize, m as usize))) {
+        if mx > x || my > y {
+            hunks.push((x..mx, y..my));
+        }
+        x = mx + 1;
+        y = my + 1;
+    }
+    hunks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{diff, merge, Change, Conflict};
+    use cursor::CursorMut;
+    use test_help::*;
+
+    fn edited<F>(base: &NodeRc<ListLeaf>, edit: F) -> NodeRc<ListLeaf>
+        where F: FnOnce(&mut CursorMut<ListLeaf, ListPath>)
+    {
+        let mut cursor = CursorMut::from_node(base.clone());
+        edit(&mut cursor);
+        cursor.into_root().unwrap()
+    }
+
+    fn values(node: &NodeRc<ListLeaf>) -> Vec<usize> {
+        CursorT::new(node).into_iter().map(|leaf| leaf.0).collect()
+    }
+
+    #[test]
+    fn diff_shared() {
+        let base: NodeRc<_> = (0..1000).map(ListLeaf).collect();
+        let ours = edited(&base, |cursor| {
+            cursor.goto_min(ListIndex(500));
+            cursor.remove_leaf();
+            cursor.goto_min(ListIndex(700));
+            cursor.insert_leaf(ListLeaf(7000), false);
+        });
+        assert_eq!(diff(&base, &ours), vec![
+            Change { old: 500..501, new: 500..500 },
+            Change { old: 701..701, new: 700..701 },
+        ]);
+        assert_eq!(diff(&base, &base), vec![]);
+    }
+
+    #[test]
+    fn merge_disjoint() {
+        let base: NodeRc<_> = (0..100).map(ListLeaf).collect();
+        let ours = edited(&base, |cursor| {
+            cursor.goto_min(ListIndex(10));
+            cursor.leaf_update(|leaf| leaf.0 = 1000);
+        });
+        let theirs = edited(&base, |cursor| {
+            cursor.goto_min(ListIndex(50));
+            cursor.remove_leaf();
+            cursor.goto_min(ListIndex(10));
+            cursor.leaf_update(|leaf| leaf.0 = 1000);
+        });
+        let merged = merge(&base, &ours, &theirs).unwrap().unwrap();
+        let mut expected: Vec<_> = (0..100).filter(|&i| i != 50).collect();
+        expected[10] = 1000;
+        assert_eq!(values(&merged), expected);
+    }
+
+    #[test]
+    fn merge_shared() {
+        let base: NodeRc<_> = (0..1000).map(ListLeaf).collect();
+        let ours = edited(&base, |cursor| {
+            cursor.goto_min(ListIndex(100));
+            cursor.insert_leaf(ListLeaf(1000), false);
+        });
+        let theirs = edited(&base, |cursor| {
+            cursor.goto_min(ListIndex(900));
+            cursor.leaf_update(|leaf| leaf.0 = 2000);
+        });
+        let merged = merge(&base, &ours, &theirs).unwrap().unwrap();
+        assert_balanced(&merged);
+        let mut expected: Vec<_> = (0..1000).collect();
+        expected[900] = 2000;
+        expected.insert(100, 1000);
+        assert_eq!(values(&merged), expected);
+        // all but the nodes along the edited paths and the spliced edges are shared with `base`
+        let stats = merged.shared_with(&base);
+        assert!(stats.shared_nodes * 5 > stats.nodes * 4, "{:?}", stats);
+    }
+
+    #[test]
+    fn merge_conflict() {
+        let base: NodeRc<_> = (0..100).map(ListLeaf).collect();
+        let ours = edited(&base, |cursor| {
+            cursor.goto_min(ListIndex(20));
+            cursor.leaf_update(|leaf| leaf.0 = 1000);
+            cursor.goto_min(ListIndex(30));
+            cursor.insert_leaf(ListLeaf(2000), false);
+        });
+        let theirs = edited(&base, |cursor| {
+            cursor.goto_min(ListIndex(20));
+            cursor.remove_leaf();
+            cursor.goto_min(ListIndex(29));
+            cursor.insert_leaf(ListLeaf(3000), false);
+        });
+        let conflicts = merge(&base, &ours, &theirs).err().unwrap();
+        assert_eq!(conflicts, vec![
+            Conflict { base: 20..21, ours: 20..21, theirs: 20..20 },
+            Conflict { base: 30..30, ours: 30..31, theirs: 29..30 },
+        ]);
+    }
+}
@@ -0,0 +1,105 @@
+//! Errors returned by the fallible (`try_`) variants of operations that would otherwise panic.
+//!
+//! Cursor operations return a `CursorError`, which also describes where the cursor was, so that
+//! the traversal state can be reproduced from a bug report.
+
+use std::error::Error;
+use std::fmt;
+
+/// The reason an operation on a tree failed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TreeError {
+    /// The tree would be taller than the maximum height supported by the cursor.
+    DepthExceeded { height: usize, max: usize },
+    /// The cursor (or the tree) is empty.
+    Empty,
+    /// A leaf being inserted was rejected by the validator of the cursor, for the given reason.
+    InvalidLeaf { reason: &'static str },
+    /// Two trees expected to have the same number of leaves do not.
+    LengthMismatch { left: usize, right: usize },
+    /// A subtree does not have the height of the subtree it would replace.
+    HeightMismatch { expected: usize, found: usize },
+    /// The current node is not a leaf.
+    NotLeaf { height: usize },
+    /// The index is not less than the length.
+    OutOfBounds { index: usize, len: usize },
+}
+
+impl fmt::Display for TreeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            TreeError::DepthExceeded { height, max } =>
+                write!(f, "tree of height {} exceeds the maximum supported height {}", height, max),
+            TreeError::Empty => write!(f, "tree is empty"),
+            TreeError::InvalidLeaf { reason } => write!(f, "invalid leaf: {}", reason),
+            TreeError::LengthMismatch { left, right } =>
+                write!(f, "trees have different numbers of leaves ({} and {})", left, right),
+            TreeError::HeightMismatch { expected, found } =>
+                write!(f, "expected a subtree of height {}, found height {}", expected, found),
+            TreeError::NotLeaf { height } =>
+                write!(f, "expected a leaf, but the node has height {}", height),
+            TreeError::OutOfBounds { index, len } =>
+                write!(f, "index {} out of bounds (len: {})", index, len),
+        }
+    }
+}
+
+impl Error for TreeError {}
+
+/// An error from a fallible cursor operation, along with the state of the cursor when it failed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CursorError {
+    op: &'static str,
+    path: Vec<usize>,
+    height: Option<usize>,
+    kind: TreeError,
+}
+
+impl CursorError {
+    pub(crate) fn new(op: &'static str, path: Vec<usize>, height: Option<usize>, kind: TreeError)
+        -> Self
+    {
+        CursorError { op, path, height, kind }
+    }
+
+    /// The name of the operation that failed.
+    pub fn op(&self) -> &'static str {
+        self.op
+    }
+
+    /// The index of each node (among its siblings) on the path from the root to the current node.
+    pub fn path(&self) -> &[usize] {
+        &self.path
+    }
+
+    /// The height of the current node, or `None` if the cursor was empty.
+    pub fn height(&self) -> Option<usize> {
+        self.height
+    }
+
+    pub fn kind(&self) -> TreeError {
+        self.kind
+    }
+}
+
+impl fmt::Display for CursorError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} failed at path {:?}", self.op, self.path)?;
+        if let Some(height) = self.height {
+            write!(f, " (height {})", height)?;
+        }
+        write!(f, ": {}", self.kind)
+    }
+}
+
+impl Error for CursorError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&self.kind)
+    }
+}
+
+impl From<CursorError> for TreeError {
+    fn from(err: CursorError) -> TreeError {
+        err.kind
+    }
+}
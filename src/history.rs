@@ -0,0 +1,173 @@
+//! Undo/redo history of tree versions.
+//!
+//! Since nodes are copy-on-write, keeping around old roots of a tree only costs the nodes that
+//! were modified since, which makes storing a full snapshot per undo step practical.
+
+use node::{Node, NodesPtr};
+use traits::Leaf;
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// A bounded list of tree versions with a pointer to the current version.
+///
+/// A version is represented by `Option<Node>`, where `None` represents an empty tree (as
+/// returned by `CursorMut::into_root`).
+pub struct History<L: Leaf, NP> {
+    states: VecDeque<Option<Node<L, NP>>>,
+    current: usize,
+    limit: usize,
+    coalesce_window: Duration,
+    last_checkpoint: Option<Instant>,
+}
+
+impl<L: Leaf, NP: NodesPtr<L>> Clone for History<L, NP> {
+    fn clone(&self) -> Self {
+        History {
+            states: self.states.clone(),
+            current: self.current,
+            limit: self.limit,
+            coalesce_window: self.coalesce_window,
+            last_checkpoint: self.last_checkpoint,
+        }
+    }
+}
+
+impl<L: Leaf, NP: NodesPtr<L>> History<L, NP> {
+    /// Create a new history with `initial` as the current version, retaining at most `limit` undo
+    /// steps.
+    pub fn new(initial: Option<Node<L, NP>>, limit: usize) -> Self {
+        let mut states = VecDeque::new();
+        states.push_back(initial);
+        History {
+            states,
+            current: 0,
+            limit,
+            coalesce_window: Duration::from_secs(0),
+            last_checkpoint: None,
+        }
+    }
+
+    /// Checkpoints made within `window` of the previous checkpoint replace it instead of creating
+    /// a new undo step. A zero duration (the default) disables coalescing.
+    pub fn set_coalesce_window(&mut self, window: Duration) {
+        self.coalesce_window = window;
+    }
+
+    /// Make sure that the next checkpoint starts a new undo step, even if it is within the
+    /// coalescing window. Useful when the edit location changes, for instance.
+    pub fn break_coalescing(&mut self) {
+        self.last_checkpoint = None;
+    }
+
+    /// Returns the current version.
+    pub fn current(&self) -> Option<&Node<L, NP>> {
+        self.states[self.current].as_ref()
+    }
+
+    /// Record `state` as the new current version. All versions that could have been redone are
+    /// discarded.
+    pub fn checkpoint(&mut self, state: Option<Node<L, NP>>) {
+        self.checkpoint_at(state, Instant::now());
+    }
+
+    /// Same as `checkpoint`, but using `now` as the time of this checkpoint for coalescing.
+    pub fn checkpoint_at(&mut self, state: Option<Node<L, NP>>, now: Instant) {
+        self.states.truncate(self.current + 1);
+        let coalesce = match self.last_checkpoint {
+            Some(last) => self.current > 0 && now.duration_since(last) < self.coalesce_window,
+            None => false,
+        };
+        if coalesce {
+            self.states[self.current] = state;
+        } else {
+            self.states.push_back(state);
+            if self.states.len() > self.limit + 1 {
+                self.states.pop_front();
+            }
+            self.current = self.states.len() - 1;
+        }
+        self.last_checkpoint = Some(now);
+    }
+
+    pub fn can_undo(&self) -> bool {
+        self.current > 0
+    }
+
+    pub fn can_redo(&self) -> bool {
+        self.current + 1 < self.states.len()
+    }
+
+    /// Move to the previous version. Returns `false` if there is none.
+    pub fn undo(&mut self) -> bool {
+        self.last_checkpoint = None;
+        if self.can_undo() {
+            self.current -= 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Move to the next version, undoing an `undo`. Returns `false` if there is none.
+    pub fn redo(&mut self) -> bool {
+        self.last_checkpoint = None;
+        if self.can_redo() {
+            self.current += 1;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::History;
+    use node::Rc16;
+    use test_help::*;
+
+    use std::time::{Duration, Instant};
+
+    fn first(history: &History<ListLeaf, Rc16<ListLeaf>>) -> usize {
+        CursorT::new(history.current().unwrap()).first_leaf().unwrap().0
+    }
+
+    #[test]
+    fn undo_redo() {
+        let node = |i| Some(NodeRc::from_leaf(ListLeaf(i)));
+        let mut history = History::new(node(0), 2);
+        history.checkpoint(node(1));
+        history.checkpoint(node(2));
+        history.checkpoint(node(3));
+        assert!(history.undo());
+        assert!(history.undo());
+        assert_eq!(first(&history), 1);
+        assert!(!history.undo()); // limited to 2 undo steps
+        assert!(history.redo());
+        assert_eq!(first(&history), 2);
+        history.checkpoint(node(4));
+        assert!(!history.redo());
+        assert!(history.undo());
+        assert_eq!(first(&history), 2);
+    }
+
+    #[test]
+    fn coalesce() {
+        let node = |i| Some(NodeRc::from_leaf(ListLeaf(i)));
+        let mut history = History::new(node(0), 8);
+        history.set_coalesce_window(Duration::from_secs(60));
+        let now = Instant::now();
+        history.checkpoint_at(node(1), now);
+        history.checkpoint_at(node(2), now + Duration::from_secs(1));
+        history.checkpoint_at(node(3), now + Duration::from_secs(2));
+        assert_eq!(first(&history), 3);
+        history.break_coalescing();
+        history.checkpoint_at(node(4), now + Duration::from_secs(3));
+        assert!(history.undo());
+        assert_eq!(first(&history), 3);
+        assert!(history.undo());
+        assert_eq!(first(&history), 0);
+        assert!(!history.can_undo());
+    }
+}
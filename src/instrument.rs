@@ -0,0 +1,122 @@
+//! Events emitted by cursors and tree operations, for measuring how an editing pattern uses the
+//! tree, such as how many arrays of children it copies (requires the `instrument` feature).
+//!
+//! Events are sent to the sink of the current thread, if any, set using `set_sink`. Events emitted
+//! while the sink is running (such as by tree operations within it) are dropped.
+
+use node::{Node, NodesPtr};
+use traits::Leaf;
+
+use std::cell::RefCell;
+
+/// An event emitted on traversing or restructuring a tree.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Event {
+    /// A cursor descended to a node of this height.
+    Descend { height: usize },
+    /// A cursor ascended to a node of this height.
+    Ascend { height: usize },
+    /// The array of children of a node of this height was copied to be mutated, since it was
+    /// shared (copy-on-write).
+    Clone { height: usize },
+    /// A node of this height overflowed, and was split into two.
+    Split { height: usize },
+    /// Two adjacent nodes of this height were merged into one.
+    Merge { height: usize },
+}
+
+type Sink = Box<dyn FnMut(Event)>;
+
+thread_local!(static SINK: RefCell<Option<Sink>> = RefCell::new(None));
+
+/// Send the events emitted on this thread to `sink`, replacing the previous sink.
+pub fn set_sink<F: FnMut(Event) + 'static>(sink: F) {
+    SINK.with(|cell| *cell.borrow_mut() = Some(Box::new(sink)));
+}
+
+/// Stop sending the events emitted on this thread.
+pub fn clear_sink() {
+    SINK.with(|cell| *cell.borrow_mut() = None);
+}
+
+pub(crate) fn emit(event: Event) {
+    let _ = SINK.try_with(|cell| {
+        if let Ok(mut sink) = cell.try_borrow_mut() {
+            if let Some(ref mut sink) = *sink {
+                sink(event);
+            }
+        }
+    });
+}
+
+// The height of the node owning the array of children `nodes`. The child being edited through a
+// `CursorMut` is a placeholder without a height, so the height is taken from another child (or is
+// 1 if there is no other child, as happens only in trees with relaxed balance).
+pub(crate) fn nodes_height<L: Leaf, NP: NodesPtr<L>>(nodes: &[Node<L, NP>]) -> usize {
+    nodes.iter().find(|node| !node.is_never()).map_or(1, |node| node.height() + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{clear_sink, set_sink, Event};
+    use test_help::*;
+
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    #[test]
+    fn edit_first_leaf() {
+        let events = Rc::new(RefCell::new(Vec::new()));
+        let sink = events.clone();
+        set_sink(move |event| sink.borrow_mut().push(event));
+
+        let root: NodeRc<_> = (0..1000).map(ListLeaf).collect();
+        let mut cursor_mut = ::cursor::CursorMut::<_, ListPath>::from_node(root.clone());
+        cursor_mut.first_leaf();
+        cursor_mut.leaf_update(|leaf| leaf.0 += 1);
+        let shared = cursor_mut.clone();
+        cursor_mut.leaf_update(|leaf| leaf.0 += 1); // edits arrays holding placeholders
+        cursor_mut.reset();
+        clear_sink();
+        drop(shared);
+        assert_eq!(cursor_mut.into_root().unwrap().first_leaf(), &ListLeaf(2));
+        let clones = events.borrow().iter().filter(|event| match **event {
+            Event::Clone { height } => height <= root.height(),
+            _ => false,
+        }).count();
+        assert!(clones >= root.height());
+    }
+
+    #[test]
+    fn events() {
+        let events = Rc::new(RefCell::new(Vec::new()));
+        let sink = events.clone();
+        set_sink(move |event| sink.borrow_mut().push(event));
+
+        let root: NodeRc<_> = (0..1000).map(ListLeaf).collect();
+        let mut cursor_mut = ::cursor::CursorMut::<_, ListPath>::from_node(root.clone());
+        events.borrow_mut().clear();
+        cursor_mut.goto_min(ListIndex(500));
+        cursor_mut.leaf_update(|leaf| leaf.0 += 1);
+        cursor_mut.reset();
+        {
+            let events = events.borrow();
+            let count = |f: fn(&Event) -> bool| events.iter().filter(|event| f(event)).count();
+            assert_eq!(count(|event| matches!(*event, Event::Clone { .. })), root.height());
+            assert!(count(|event| matches!(*event, Event::Descend { .. })) >= root.height());
+            assert!(count(|event| matches!(*event, Event::Ascend { .. })) >= root.height());
+        }
+
+        for i in 0..100 {
+            cursor_mut.insert_leaf(ListLeaf(i), false);
+        }
+        clear_sink();
+        cursor_mut.insert_leaf(ListLeaf(0), false);
+        let events = events.borrow();
+        assert!(events.iter().any(|event| matches!(*event, Event::Split { .. })));
+        assert!(events.iter().all(|event| match *event {
+            Event::Descend { height } | Event::Ascend { height } | Event::Clone { height }
+                | Event::Split { height } | Event::Merge { height } => height <= root.height() + 1,
+        }));
+    }
+}
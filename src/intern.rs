@@ -0,0 +1,127 @@
+//! Deduplication of structurally identical subtrees.
+//!
+//! An `Interner` replaces every internal node of a tree by a previously seen node with the same
+//! leaves and shape (if any), so that they share a single allocation. This saves memory for highly
+//! repetitive contents (such as logs or genomes), at the cost of keeping the interned nodes alive.
+//!
+//! Nodes are interned bottom-up. So the children of two identical nodes are already shared when
+//! they are compared, which makes comparing them a matter of comparing pointers. Only the nodes
+//! right above the leaves are compared by hashing and comparing their leaves.
+
+use node::{Node, NodesPtr};
+use traits::Leaf;
+
+use std::collections::HashMap;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+type NodeId<L, NP> = *const Node<L, NP>;
+
+/// A table of interned nodes. See the module documentation.
+pub struct Interner<L: Leaf, NP: NodesPtr<L>> {
+    leaf_parents: HashMap<u64, Vec<Node<L, NP>>>, // nodes of height 1 by the hash of their leaves
+    parents: HashMap<Vec<NodeId<L, NP>>, Node<L, NP>>, // other nodes by their children
+}
+
+impl<L: Leaf + Hash + Eq, NP: NodesPtr<L>> Default for Interner<L, NP> {
+    fn default() -> Self {
+        Interner::new()
+    }
+}
+
+impl<L: Leaf + Hash + Eq, NP: NodesPtr<L>> Interner<L, NP> {
+    pub fn new() -> Self {
+        Interner { leaf_parents: HashMap::new(), parents: HashMap::new() }
+    }
+
+    /// Returns `node` with each of its subtrees replaced by an identical interned one, interning
+    /// the subtrees not seen before. Use this on trees right after they are built or compacted.
+    pub fn intern(&mut self, node: Node<L, NP>) -> Node<L, NP> {
+        match node.height() {
+            0 => node,
+            1 => {
+                let mut hasher = DefaultHasher::new();
+                for child in node.children() {
+                    child.leaf().unwrap().hash(&mut hasher);
+                }
+                let bucket = self.leaf_parents.entry(hasher.finish()).or_default();
+                let same_leaves = |other: &Node<L, NP>| {
+                    other.children().len() == node.children().len()
+                        && other.children().iter().zip(node.children())
+                                .all(|(c1, c2)| c1.leaf() == c2.leaf())
+                };
+                match bucket.iter().find(|&other| same_leaves(other)) {
+                    Some(interned) => interned.clone(),
+                    None => {
+                        bucket.push(node.clone());
+                        node
+                    }
+                }
+            }
+            _ => {
+                let mut nodes = node.into_children_must();
+                for child in NP::make_mut(&mut nodes).iter_mut() {
+                    let interned = self.intern(child.never_take());
+                    *child = interned;
+                }
+                let ids = nodes.iter().map(|child| child.children().as_ptr()).collect();
+                self.parents.entry(ids).or_insert_with(|| Node::from_children(nodes)).clone()
+            }
+        }
+    }
+
+    /// The number of distinct internal nodes interned.
+    pub fn len(&self) -> usize {
+        self.leaf_parents.values().map(Vec::len).sum::<usize>() + self.parents.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Forget all interned nodes, releasing the ones not used by any tree.
+    pub fn clear(&mut self) {
+        self.leaf_parents.clear();
+        self.parents.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Interner;
+    use test_help::*;
+
+    use std::collections::HashSet;
+
+    #[test]
+    fn intern() {
+        // 64 leaves repeated 100 times, so all nodes of height 1 have the same leaves
+        let node: NodeRc<_> = (0..6400).map(|i| ListLeaf(i % 64)).collect();
+        let mut interner = Interner::new();
+        let interned = interner.intern(node.clone());
+        assert_eq!(interned, node);
+        assert_balanced(&interned);
+
+        fn internal_nodes(node: &NodeRc<ListLeaf>, ids: &mut HashSet<*const NodeRc<ListLeaf>>) {
+            if !node.is_leaf() && ids.insert(node.children().as_ptr()) {
+                for child in node.children() {
+                    internal_nodes(child, ids);
+                }
+            }
+        }
+        let mut ids = HashSet::new();
+        internal_nodes(&interned, &mut ids);
+        assert_eq!(ids.len(), interner.len());
+        assert!(ids.len() < 10);
+
+        let first_leaf_parent = |mut node: &NodeRc<ListLeaf>| {
+            while node.height() > 1 {
+                node = &node.children()[0];
+            }
+            node.clone()
+        };
+        let other: NodeRc<_> = (0..640).map(|i| ListLeaf(i % 64)).collect();
+        let other = interner.intern(other);
+        assert!(first_leaf_parent(&other).ptr_eq(&first_leaf_parent(&interned)));
+    }
+}
@@ -0,0 +1,210 @@
+//! Adapters between trees of byte chunks and `std::io`.
+
+use builder::TreeBuilder;
+use node::{Node, NodesPtr};
+use traits::{Count, Leaf};
+
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::mem;
+use std::ops::Deref;
+
+/// A reader over the bytes of a tree whose leaves dereference to byte slices, and are counted
+/// (using `Count`) by their length in bytes.
+///
+/// Reading copies directly out of the leaves, and seeking takes O(log n) time.
+pub struct TreeReader<'a, L: Leaf + 'a, NP: NodesPtr<L> + 'a> {
+    root: Option<&'a Node<L, NP>>,
+    pos: usize,
+    chunk: &'a [u8], // the bytes of the current leaf, starting from `pos`
+}
+
+impl<'a, L, NP> TreeReader<'a, L, NP>
+    where L: Leaf + Deref<Target=[u8]>, L::Info: Count, NP: NodesPtr<L>,
+{
+    /// Create a reader at the start of the tree at `root` (which may be empty).
+    pub fn new(root: Option<&'a Node<L, NP>>) -> Self {
+        TreeReader { root, pos: 0, chunk: &[] }
+    }
+
+    /// The current position, in bytes.
+    pub fn position(&self) -> usize {
+        self.pos
+    }
+
+    /// The length of the tree, in bytes.
+    pub fn len(&self) -> usize {
+        self.root.map_or(0, |root| root.info().count())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // Find the leaf containing `self.pos`, and set `chunk` to its bytes from `pos`.
+    fn load_chunk(&mut self) {
+        self.chunk = &[];
+        let mut node = match self.root {
+            Some(root) if self.pos < root.info().count() => root,
+            _ => return,
+        };
+        let mut offset = self.pos;
+        while !node.is_leaf() {
+            for child in node.children() {
+                let count = child.info().count();
+                if offset < count {
+                    node = child;
+                    break;
+                }
+                offset -= count;
+            }
+        }
+        self.chunk = &node.leaf().unwrap()[offset..];
+    }
+}
+
+impl<'a, L, NP> Read for TreeReader<'a, L, NP>
+    where L: Leaf + Deref<Target=[u8]>, L::Info: Count, NP: NodesPtr<L>,
+{
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.chunk.is_empty() {
+            self.load_chunk();
+        }
+        let len = self.chunk.len().min(buf.len());
+        buf[..len].copy_from_slice(&self.chunk[..len]);
+        self.chunk = &self.chunk[len..];
+        self.pos += len;
+        Ok(len)
+    }
+}
+
+/// Seeking beyond the end is allowed, in which case reads return nothing.
+impl<'a, L, NP> Seek for TreeReader<'a, L, NP>
+    where L: Leaf + Deref<Target=[u8]>, L::Info: Count, NP: NodesPtr<L>,
+{
+    fn seek(&mut self, from: SeekFrom) -> io::Result<u64> {
+        let (base, delta) = match from {
+            SeekFrom::Start(pos) => (0, pos as i64),
+            SeekFrom::End(delta) => (self.len() as i64, delta),
+            SeekFrom::Current(delta) => (self.pos as i64, delta),
+        };
+        match base.checked_add(delta) {
+            Some(pos) if pos >= 0 => {
+                self.pos = pos as usize;
+                self.chunk = &[];
+                Ok(pos as u64)
+            }
+            _ => Err(io::Error::new(io::ErrorKind::InvalidInput, "seek to a negative position")),
+        }
+    }
+}
+
+/// A writer that appends the bytes written to a tree, as leaves of at most `max_leaf` bytes. Only
+/// the last leaf written may be shorter, so the leaves are as large as allowed.
+///
+/// Leaves are added as soon as they are full, so at most one leaf worth of bytes is buffered. Call
+/// `finish` to get the tree; dropping the writer discards it.
+pub struct TreeWriter<L: Leaf, NP: NodesPtr<L>> {
+    builder: TreeBuilder<L, NP>,
+    pending: Vec<u8>, // the bytes of the last leaf, not added yet
+    max_leaf: usize,
+}
+
+impl<L, NP> TreeWriter<L, NP>
+    where L: Leaf + From<Vec<u8>>, NP: NodesPtr<L>,
+{
+    /// Create a writer that builds a new tree, with leaves of at most `max_leaf` bytes.
+    ///
+    /// Panics if `max_leaf` is zero.
+    pub fn new(max_leaf: usize) -> Self {
+        assert!(max_leaf > 0, "max_leaf must be positive");
+        TreeWriter { builder: TreeBuilder::new(), pending: Vec::new(), max_leaf }
+    }
+
+    /// Create a writer that appends to the tree at `root`. See `new`.
+    pub fn append_to(root: Node<L, NP>, max_leaf: usize) -> Self {
+        let mut writer = TreeWriter::new(max_leaf);
+        writer.builder = TreeBuilder::from_node(root);
+        writer
+    }
+
+    /// Returns the tree with all the bytes written, or `None` if the tree is empty.
+    pub fn finish(mut self) -> Option<Node<L, NP>> {
+        if !self.pending.is_empty() {
+            self.builder.push_back(L::from(self.pending));
+        }
+        self.builder.build()
+    }
+}
+
+impl<L, NP> Write for TreeWriter<L, NP>
+    where L: Leaf + From<Vec<u8>>, NP: NodesPtr<L>,
+{
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut rest = buf;
+        while !rest.is_empty() {
+            if self.pending.len() == self.max_leaf {
+                let leaf = mem::replace(&mut self.pending, Vec::with_capacity(self.max_leaf));
+                self.builder.push_back(L::from(leaf));
+            }
+            let len = rest.len().min(self.max_leaf - self.pending.len());
+            self.pending.extend_from_slice(&rest[..len]);
+            rest = &rest[len..];
+        }
+        Ok(buf.len())
+    }
+
+    /// Does nothing, since a partially filled leaf is only added by `finish`.
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{TreeReader, TreeWriter};
+    use leaf::ArcSlice;
+    use test_help::*;
+
+    use std::io::{Read, Seek, SeekFrom, Write};
+
+    #[test]
+    fn read_seek() {
+        let bytes: Vec<u8> = (0..1000).map(|i| i as u8).collect();
+        let node: NodeRc<_> = bytes.chunks(7).map(|chunk| ArcSlice::new(chunk.to_vec())).collect();
+        let mut reader = TreeReader::new(Some(&node));
+        let mut out = Vec::new();
+        reader.read_to_end(&mut out).unwrap();
+        assert_eq!(out, bytes);
+
+        assert_eq!(reader.seek(SeekFrom::End(-10)).unwrap(), 990);
+        let mut buf = [0; 4];
+        reader.read_exact(&mut buf).unwrap();
+        assert_eq!(buf[..], bytes[990..994]);
+        assert_eq!(reader.seek(SeekFrom::Current(-500)).unwrap(), 494);
+        reader.read_exact(&mut buf).unwrap();
+        assert_eq!(buf[..], bytes[494..498]);
+        assert!(reader.seek(SeekFrom::Current(-1000)).is_err());
+        reader.seek(SeekFrom::Start(2000)).unwrap();
+        assert_eq!(reader.read(&mut buf).unwrap(), 0);
+    }
+
+    #[test]
+    fn write() {
+        let bytes: Vec<u8> = (0..1000).map(|i| i as u8).collect();
+        let mut writer = TreeWriter::new(64);
+        for chunk in bytes.chunks(100) {
+            writer.write_all(chunk).unwrap();
+        }
+        let node: NodeRc<ArcSlice<u8>> = writer.finish().unwrap();
+        assert!(node.leaves().all(|leaf| leaf.len() == 64 || leaf.len() == 1000 % 64));
+        assert_balanced(&node);
+
+        let mut writer = TreeWriter::append_to(node, 64);
+        writer.write_all(b"tail").unwrap();
+        let node = writer.finish().unwrap();
+        let mut out = Vec::new();
+        TreeReader::new(Some(&node)).read_to_end(&mut out).unwrap();
+        assert_eq!(out[..1000], bytes[..]);
+        assert_eq!(&out[1000..], b"tail");
+    }
+}
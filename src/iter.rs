@@ -0,0 +1,575 @@
+//! Iterators over the leaves of a `Node`.
+
+use diff::count_leaves;
+use error::TreeError;
+use node::{Node, NodesPtr};
+use traits::{Leaf, LeafElems, PathInfo};
+use tree::OptionNode;
+
+use arrayvec::{self, ArrayVec};
+
+use std::collections::VecDeque;
+use std::mem;
+use std::ops::Deref;
+use std::slice;
+
+/// An iterator over the leaves of a tree, in order. See `Node::leaves`.
+pub struct Leaves<'a, L: Leaf + 'a, NP: NodesPtr<L> + 'a> {
+    root: Option<&'a Node<L, NP>>, // the root, until the first call to `next`
+    stack: Vec<slice::Iter<'a, Node<L, NP>>>,
+    reversed: bool, // whether the leaves are iterated from the last one
+}
+
+/// An iterator over the contents of the leaves of a tree, in order. See `Node::chunks`.
+pub struct Chunks<'a, L: Leaf + 'a, NP: NodesPtr<L> + 'a> {
+    leaves: Leaves<'a, L, NP>,
+}
+
+/// An iterator over the elements of the leaves of a tree (see `LeafElems`), in order or in reverse.
+/// See `Node::iter_elements`.
+pub struct Elements<'a, L: LeafElems + 'a, NP: NodesPtr<L> + 'a> {
+    leaves: Leaves<'a, L, NP>,
+    leaf: Option<&'a L>,
+    offset: usize, // the offset of the next element within `leaf`
+}
+
+/// An iterator over the pairs of adjacent leaves of a tree, in order. See `Node::leaf_pairs`.
+pub struct LeafPairs<'a, L: Leaf + 'a, NP: NodesPtr<L> + 'a> {
+    leaves: Leaves<'a, L, NP>,
+    prev: Option<&'a L>,
+}
+
+/// An iterator over the runs of `n` adjacent leaves of a tree, in order. See `Node::leaf_windows`.
+pub struct LeafWindows<'a, L: Leaf + 'a, NP: NodesPtr<L> + 'a> {
+    leaves: Leaves<'a, L, NP>,
+    window: VecDeque<&'a L>,
+    size: usize,
+}
+
+/// An iterator over the leaves of a tree along with the cumulative path info before (or after) each
+/// of them. See `Node::scan_exclusive` and `Node::scan_inclusive`.
+pub struct Scan<'a, L: Leaf + 'a, NP: NodesPtr<L> + 'a, PI> {
+    leaves: Leaves<'a, L, NP>,
+    path_info: PI, // the path info before the next leaf
+    inclusive: bool,
+}
+
+/// An iterator over the children of a node, along with the index, the path info at the start and the
+/// info of each. See `Cursor::children`.
+pub struct Children<'a, L: Leaf + 'a, NP: NodesPtr<L> + 'a, PI> {
+    nodes: slice::Iter<'a, Node<L, NP>>,
+    index: usize, // the index of the next child
+    path_info: PI, // the path info before the next child
+}
+
+/// An iterator over the subtrees at a given height, in order, along with the path info at the
+/// start of each. See `Node::subtrees_at_height`.
+pub struct Subtrees<'a, L: Leaf + 'a, NP: NodesPtr<L> + 'a, PI> {
+    root: Option<&'a Node<L, NP>>, // the root, until the first call to `next`
+    stack: Vec<slice::Iter<'a, Node<L, NP>>>,
+    height: usize,
+    path_info: PI, // the path info at the start of the next subtree
+}
+
+/// An iterator over the pairs of leaves at the same index in two trees. See `zip_leaves`.
+pub struct ZipLeaves<'a, L, M, NP, NQ>
+    where L: Leaf + 'a, M: Leaf + 'a, NP: NodesPtr<L> + 'a, NQ: NodesPtr<M> + 'a,
+{
+    left: Leaves<'a, L, NP>,
+    right: Leaves<'a, M, NQ>,
+}
+
+/// An iterator that moves the leaves out of a tree, in order. See `IntoIterator for Node`.
+pub struct IntoLeaves<L: Leaf, NP: NodesPtr<L>> {
+    root: Option<Node<L, NP>>, // the root, until the first call to `next`
+    stack: Vec<arrayvec::IntoIter<NP::Array>>,
+}
+
+impl<L: Leaf, NP: NodesPtr<L>> Node<L, NP> {
+    /// Returns an iterator over the leaves of this tree, in order.
+    pub fn leaves(&self) -> Leaves<'_, L, NP> {
+        Leaves { root: Some(self), stack: Vec::new(), reversed: false }
+    }
+
+    /// Returns an iterator over the slices (or strings) that the leaves of this tree dereference
+    /// to, in order. Useful for zero-copy processing of the contents, such as vectored writes.
+    pub fn chunks(&self) -> Chunks<'_, L, NP> where L: Deref {
+        Chunks { leaves: self.leaves() }
+    }
+
+    /// Returns an iterator over the pairs of adjacent leaves of this tree, in order. Nothing is
+    /// returned if the tree has a single leaf.
+    pub fn leaf_pairs(&self) -> LeafPairs<'_, L, NP> {
+        LeafPairs { leaves: self.leaves(), prev: None }
+    }
+
+    /// Returns an iterator over every run of `size` adjacent leaves of this tree, in order, like
+    /// `slice::windows`.
+    ///
+    /// Panics if `size` is zero.
+    pub fn leaf_windows(&self, size: usize) -> LeafWindows<'_, L, NP> {
+        assert!(size > 0, "window size must be positive");
+        LeafWindows { leaves: self.leaves(), window: VecDeque::with_capacity(size), size }
+    }
+
+    /// Returns an iterator over the leaves of this tree along with the path info at the start of
+    /// each (i.e., the prefix sums of the leaf infos, excluding the leaf).
+    pub fn scan_exclusive<PI: PathInfo<L::Info>>(&self) -> Scan<'_, L, NP, PI> {
+        Scan { leaves: self.leaves(), path_info: PI::identity(), inclusive: false }
+    }
+
+    /// Same as `scan_exclusive`, but with the path info at the end of each leaf.
+    pub fn scan_inclusive<PI: PathInfo<L::Info>>(&self) -> Scan<'_, L, NP, PI> {
+        Scan { leaves: self.leaves(), path_info: PI::identity(), inclusive: true }
+    }
+
+    /// Returns an iterator over the subtrees of this tree at `height` (leaves being at height 0),
+    /// in order, along with the path info at the start of each. Nothing is returned if `height`
+    /// is greater than the height of this tree.
+    ///
+    /// Since the tree is balanced, these subtrees partition the leaves into chunks of similar size
+    /// (between `(max/2)^height` and `max^height` leaves, where `max` is the maximum number of
+    /// children of a node), which is handy for splitting up work.
+    pub fn subtrees_at_height<PI>(&self, height: usize) -> Subtrees<'_, L, NP, PI>
+        where PI: PathInfo<L::Info>,
+    {
+        Subtrees { root: Some(self), stack: Vec::new(), height, path_info: PI::identity() }
+    }
+
+    /// Visit the subtrees of this tree from the root down, in order, calling `visit` with the path
+    /// info at the start of each subtree along with the subtree (whose info is its gathered info).
+    /// The children of a subtree are visited only if it is taller than `min_height` and `visit`
+    /// returns `true` for it. Otherwise it is treated as an opaque chunk.
+    ///
+    /// This lets algorithms that do not need the leaves (such as progress reporting or coarse
+    /// rendering) work on as few nodes as they need. See also `subtrees_at_height`.
+    pub fn visit_subtrees<PI, F>(&self, min_height: usize, mut visit: F)
+        where PI: PathInfo<L::Info>, F: FnMut(PI, &Node<L, NP>) -> bool,
+    {
+        self.visit_subtrees_rec(PI::identity(), min_height, &mut visit);
+    }
+
+    // Same as `visit_subtrees`, for this subtree starting at `path_info`.
+    fn visit_subtrees_rec<PI, F>(&self, mut path_info: PI, min_height: usize, visit: &mut F)
+        where PI: PathInfo<L::Info>, F: FnMut(PI, &Node<L, NP>) -> bool,
+    {
+        if visit(path_info, self) && self.height() > min_height {
+            for child in self.children() {
+                child.visit_subtrees_rec(path_info, min_height, visit);
+                path_info = path_info.extend(child.info());
+            }
+        }
+    }
+
+    /// Returns an iterator over the individual elements of the leaves of this tree (such as the
+    /// chars of string chunks), in order.
+    pub fn iter_elements(&self) -> Elements<'_, L, NP> where L: LeafElems {
+        Elements { leaves: self.leaves(), leaf: None, offset: 0 }
+    }
+
+    /// Same as `iter_elements`, but iterates from the last element to the first.
+    pub fn iter_elements_rev(&self) -> Elements<'_, L, NP> where L: LeafElems {
+        let leaves = Leaves { root: Some(self), stack: Vec::new(), reversed: true };
+        Elements { leaves, leaf: None, offset: 0 }
+    }
+}
+
+impl<'a, L: Leaf, NP: NodesPtr<L>> Iterator for Leaves<'a, L, NP> {
+    type Item = &'a L;
+
+    fn next(&mut self) -> Option<&'a L> {
+        let mut next = self.root.take();
+        loop {
+            match next {
+                Some(node) => match node.leaf() {
+                    Some(leaf) => return Some(leaf),
+                    None => self.stack.push(node.children().iter()),
+                },
+                None => if self.stack.is_empty() {
+                    return None;
+                },
+            }
+            let reversed = self.reversed;
+            next = self.stack.last_mut()
+                .and_then(|iter| if reversed { iter.next_back() } else { iter.next() });
+            if next.is_none() {
+                self.stack.pop();
+            }
+        }
+    }
+}
+
+/// Returns an iterator over the pairs of leaves at the same index in `left` and `right`, such as a
+/// text and a layer of annotations aligned with it. Returns an error if the trees have different
+/// numbers of leaves.
+///
+/// Note: To find the leaves that differ between two versions of the same tree, use `diff::diff`,
+/// which skips over the subtrees shared between them.
+pub fn zip_leaves<'a, L, M, NP, NQ>(left: &'a Node<L, NP>, right: &'a Node<M, NQ>)
+    -> Result<ZipLeaves<'a, L, M, NP, NQ>, TreeError>
+    where L: Leaf, M: Leaf, NP: NodesPtr<L>, NQ: NodesPtr<M>,
+{
+    let (left_len, right_len) = (count_leaves(left), count_leaves(right));
+    if left_len != right_len {
+        return Err(TreeError::LengthMismatch { left: left_len, right: right_len });
+    }
+    Ok(ZipLeaves { left: left.leaves(), right: right.leaves() })
+}
+
+impl<'a, L: Leaf, NP: NodesPtr<L>> Iterator for LeafPairs<'a, L, NP> {
+    type Item = (&'a L, &'a L);
+
+    fn next(&mut self) -> Option<(&'a L, &'a L)> {
+        let prev = match self.prev {
+            Some(prev) => prev,
+            None => self.leaves.next()?,
+        };
+        let next = self.leaves.next()?;
+        self.prev = Some(next);
+        Some((prev, next))
+    }
+}
+
+impl<'a, L: Leaf, NP: NodesPtr<L>> Iterator for LeafWindows<'a, L, NP> {
+    type Item = Vec<&'a L>;
+
+    fn next(&mut self) -> Option<Vec<&'a L>> {
+        if self.window.len() == self.size {
+            self.window.pop_front();
+        }
+        while self.window.len() < self.size {
+            self.window.push_back(self.leaves.next()?);
+        }
+        Some(self.window.iter().cloned().collect())
+    }
+}
+
+impl<'a, L, NP, PI> Iterator for Scan<'a, L, NP, PI>
+    where L: Leaf, NP: NodesPtr<L>, PI: PathInfo<L::Info>,
+{
+    type Item = (PI, &'a L);
+
+    fn next(&mut self) -> Option<(PI, &'a L)> {
+        let leaf = self.leaves.next()?;
+        let start = self.path_info;
+        self.path_info = start.extend(leaf.compute_info());
+        Some((if self.inclusive { self.path_info } else { start }, leaf))
+    }
+}
+
+impl<'a, L: Leaf, NP: NodesPtr<L>, PI> Children<'a, L, NP, PI> {
+    pub(crate) fn new(node: &'a Node<L, NP>, path_info: PI) -> Self {
+        Children { nodes: node.children().iter(), index: 0, path_info }
+    }
+}
+
+impl<'a, L, NP, PI> Iterator for Children<'a, L, NP, PI>
+    where L: Leaf, NP: NodesPtr<L>, PI: PathInfo<L::Info>,
+{
+    type Item = (usize, PI, L::Info, &'a Node<L, NP>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let child = self.nodes.next()?;
+        let (index, path_info, info) = (self.index, self.path_info, child.info());
+        self.index += 1;
+        self.path_info = path_info.extend(info);
+        Some((index, path_info, info, child))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.nodes.size_hint()
+    }
+}
+
+impl<'a, L, NP, PI> ExactSizeIterator for Children<'a, L, NP, PI>
+    where L: Leaf, NP: NodesPtr<L>, PI: PathInfo<L::Info>,
+{}
+
+impl<'a, L, NP, PI> Iterator for Subtrees<'a, L, NP, PI>
+    where L: Leaf, NP: NodesPtr<L>, PI: PathInfo<L::Info>,
+{
+    type Item = (PI, &'a Node<L, NP>);
+
+    fn next(&mut self) -> Option<(PI, &'a Node<L, NP>)> {
+        let mut next = self.root.take();
+        loop {
+            match next {
+                Some(node) if node.height() == self.height => {
+                    let path_info = self.path_info;
+                    self.path_info = path_info.extend(node.info());
+                    return Some((path_info, node));
+                }
+                Some(node) if node.height() > self.height => {
+                    self.stack.push(node.children().iter());
+                }
+                Some(_) => return None, // the tree is shorter than `height`
+                None => if self.stack.is_empty() {
+                    return None;
+                },
+            }
+            next = self.stack.last_mut().and_then(|iter| iter.next());
+            if next.is_none() {
+                self.stack.pop();
+            }
+        }
+    }
+}
+
+impl<'a, L, M, NP, NQ> Iterator for ZipLeaves<'a, L, M, NP, NQ>
+    where L: Leaf, M: Leaf, NP: NodesPtr<L>, NQ: NodesPtr<M>,
+{
+    type Item = (&'a L, &'a M);
+
+    fn next(&mut self) -> Option<(&'a L, &'a M)> {
+        match (self.left.next(), self.right.next()) {
+            (Some(left), Some(right)) => Some((left, right)),
+            _ => None,
+        }
+    }
+}
+
+impl<'a, L: Leaf, NP: NodesPtr<L>> IntoIterator for &'a Node<L, NP> {
+    type Item = &'a L;
+    type IntoIter = Leaves<'a, L, NP>;
+
+    fn into_iter(self) -> Leaves<'a, L, NP> {
+        self.leaves()
+    }
+}
+
+/// Leaves are moved out of the nodes not shared with other trees, and cloned out of the rest.
+impl<L: Leaf, NP: NodesPtr<L>> IntoIterator for Node<L, NP> {
+    type Item = L;
+    type IntoIter = IntoLeaves<L, NP>;
+
+    fn into_iter(self) -> IntoLeaves<L, NP> {
+        IntoLeaves { root: Some(self), stack: Vec::new() }
+    }
+}
+
+impl<L: Leaf, NP: NodesPtr<L>> OptionNode<L, NP> {
+    /// Returns an iterator over the leaves of this tree (if any), in order.
+    pub fn leaves(&self) -> Leaves<'_, L, NP> {
+        Leaves { root: self.root(), stack: Vec::new(), reversed: false }
+    }
+}
+
+impl<'a, L: Leaf, NP: NodesPtr<L>> IntoIterator for &'a OptionNode<L, NP> {
+    type Item = &'a L;
+    type IntoIter = Leaves<'a, L, NP>;
+
+    fn into_iter(self) -> Leaves<'a, L, NP> {
+        self.leaves()
+    }
+}
+
+impl<L: Leaf, NP: NodesPtr<L>> IntoIterator for OptionNode<L, NP> {
+    type Item = L;
+    type IntoIter = IntoLeaves<L, NP>;
+
+    fn into_iter(self) -> IntoLeaves<L, NP> {
+        IntoLeaves { root: self.into_root(), stack: Vec::new() }
+    }
+}
+
+impl<L: Leaf, NP: NodesPtr<L>> Iterator for IntoLeaves<L, NP> {
+    type Item = L;
+
+    fn next(&mut self) -> Option<L> {
+        let mut next = self.root.take();
+        loop {
+            match next {
+                Some(node) => match node.into_children() {
+                    Ok(mut nodes) => {
+                        let nodes = mem::replace(NP::make_mut(&mut nodes), ArrayVec::new());
+                        self.stack.push(nodes.into_iter());
+                    }
+                    Err(node) => return node.into_leaf().ok(),
+                },
+                None => if self.stack.is_empty() {
+                    return None;
+                },
+            }
+            next = self.stack.last_mut().and_then(|iter| iter.next());
+            if next.is_none() {
+                self.stack.pop();
+            }
+        }
+    }
+}
+
+impl<'a, L, NP> Iterator for Chunks<'a, L, NP>
+    where L: Leaf + Deref, NP: NodesPtr<L>,
+{
+    type Item = &'a L::Target;
+
+    fn next(&mut self) -> Option<&'a L::Target> {
+        self.leaves.next().map(|leaf| &**leaf)
+    }
+}
+
+impl<'a, L: LeafElems, NP: NodesPtr<L>> Iterator for Elements<'a, L, NP> {
+    type Item = L::Elem;
+
+    fn next(&mut self) -> Option<L::Elem> {
+        loop {
+            if let Some(leaf) = self.leaf {
+                if self.leaves.reversed && self.offset > 0 {
+                    let (elem, len) = leaf.elem_before(self.offset);
+                    self.offset -= len;
+                    return Some(elem);
+                } else if !self.leaves.reversed && self.offset < leaf.end_offset() {
+                    let (elem, len) = leaf.elem_at(self.offset);
+                    self.offset += len;
+                    return Some(elem);
+                }
+            }
+            let leaf = self.leaves.next()?;
+            self.offset = if self.leaves.reversed { leaf.end_offset() } else { 0 };
+            self.leaf = Some(leaf);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cursor::Cursor;
+    use leaf::ArcStr;
+    use traits::Leaf;
+    use test_help::*;
+
+    use std::ptr;
+
+    #[test]
+    fn leaves() {
+        for &len in &[1, 16, 17, 300] {
+            let node: NodeRc<_> = (0..len).map(ListLeaf).collect();
+            assert!(node.leaves().map(|leaf| leaf.0).eq(0..len));
+        }
+    }
+
+    #[derive(Clone)]
+    struct BoxLeaf(Box<usize>);
+
+    impl Leaf for BoxLeaf {
+        type Info = usize;
+        fn compute_info(&self) -> usize { 1 }
+    }
+
+    #[test]
+    fn into_iter() {
+        let node: NodeRc<_> = (0..300).map(|i| BoxLeaf(Box::new(i))).collect();
+        let shared = node.children()[1].clone();
+        let addrs = |node: &NodeRc<BoxLeaf>| -> Vec<*const usize> {
+            node.into_iter().map(|leaf| &*leaf.0 as *const _).collect()
+        };
+        let (all_addrs, shared_addrs) = (addrs(&node), addrs(&shared));
+
+        let leaves: Vec<_> = node.into_iter().collect();
+        assert!(leaves.iter().map(|leaf| *leaf.0).eq(0..300));
+        let moved = leaves.iter().zip(all_addrs).filter(|&(leaf, addr)| ptr::eq(&*leaf.0, addr));
+        // only the leaves in the shared subtree were cloned
+        assert_eq!(moved.count(), 300 - shared_addrs.len());
+        let leaves: Vec<_> = shared.into_iter().collect();
+        assert!(leaves.iter().zip(shared_addrs).all(|(leaf, addr)| ptr::eq(&*leaf.0, addr)));
+    }
+
+    #[test]
+    fn chunks() {
+        let text = ArcStr::new("the quick brown fox");
+        let node: NodeRc<_> = (0..19).step_by(4).map(|i| text.slice(i..::std::cmp::min(i + 4, 19)))
+                                                 .collect();
+        assert_eq!(node.chunks().collect::<String>(), "the quick brown fox");
+
+        let mut cursor = Cursor::<_, usize>::new(&node);
+        assert_eq!(cursor.chunk_at(0), Some(("the ", 0)));
+        assert_eq!(cursor.chunk_at(5), Some(("quic", 4)));
+        assert_eq!(cursor.chunk_at(16), Some(("fox", 16)));
+        assert_eq!(cursor.chunk_at(19), Some(("fox", 16)));
+    }
+
+    #[test]
+    fn elements() {
+        let text = "héllo wörld";
+        let node: NodeRc<_> = vec!["hé", "", "llo w", "ö", "rld"].into_iter()
+            .map(ArcStr::new).collect();
+        assert_eq!(node.iter_elements().collect::<String>(), text);
+        assert!(node.iter_elements_rev().eq(text.chars().rev()));
+    }
+
+    #[test]
+    fn pairs_windows() {
+        let node: NodeRc<_> = (0..100).map(ListLeaf).collect();
+        assert!(node.leaf_pairs().all(|(prev, next)| prev.0 + 1 == next.0));
+        assert_eq!(node.leaf_pairs().count(), 99);
+        assert_eq!(NodeRc::from_leaf(ListLeaf(0)).leaf_pairs().count(), 0);
+        let windows: Vec<_> = node.leaf_windows(3).collect();
+        assert_eq!(windows.len(), 98);
+        assert_eq!(windows[5], vec![&ListLeaf(5), &ListLeaf(6), &ListLeaf(7)]);
+        assert_eq!(node.leaf_windows(101).count(), 0);
+    }
+
+    #[test]
+    fn scan() {
+        let node: NodeRc<_> = (0..100).map(ListLeaf).collect();
+        for (path, leaf) in node.scan_exclusive::<ListPath>() {
+            assert_eq!(path, ListPath { index: leaf.0, run: (0..leaf.0).sum() });
+        }
+        let last = node.scan_inclusive::<ListPath>().last().unwrap().0;
+        assert_eq!(last, ListPath { index: 100, run: 4950 });
+    }
+
+    #[test]
+    fn subtrees() {
+        let node: NodeRc<_> = (0..1000).map(ListLeaf).collect();
+        let subtrees: Vec<_> = node.subtrees_at_height::<ListPath>(1).collect();
+        assert!(subtrees.iter().all(|&(_, subtree)| subtree.height() == 1));
+        let mut index = 0;
+        for (path, subtree) in subtrees {
+            assert_eq!(path.index, index);
+            assert_eq!(subtree.leaves().next(), Some(&ListLeaf(index)));
+            index += subtree.children().len();
+        }
+        assert_eq!(index, 1000);
+        assert_eq!(node.subtrees_at_height::<()>(0).count(), 1000);
+        assert_eq!(node.subtrees_at_height::<()>(node.height()).count(), 1);
+        assert_eq!(node.subtrees_at_height::<()>(node.height() + 1).count(), 0);
+    }
+
+    #[test]
+    fn visit_subtrees() {
+        let node: NodeRc<_> = (0..1000).map(ListLeaf).collect();
+        let mut chunks = Vec::new();
+        node.visit_subtrees(1, |path: ListPath, subtree| {
+            if subtree.height() == 1 {
+                chunks.push((path.index, subtree.info().count));
+            }
+            true
+        });
+        assert_eq!(chunks.len(), node.subtrees_at_height::<()>(1).count());
+        assert!(chunks.windows(2).all(|pair| pair[0].0 + pair[0].1 == pair[1].0));
+
+        // treat the root's children as opaque chunks
+        let mut counts = Vec::new();
+        node.visit_subtrees(0, |_: (), subtree| {
+            counts.push(subtree.info().count);
+            counts.len() == 1
+        });
+        assert_eq!(counts.len(), 1 + node.children().len());
+        assert_eq!(counts[1..].iter().sum::<usize>(), counts[0]);
+    }
+
+    #[test]
+    fn zip() {
+        use error::TreeError;
+        use super::zip_leaves;
+
+        let text: NodeRc<_> = vec!["a", "bc", "def"].into_iter().map(ArcStr::new).collect();
+        let lens: NodeRc<_> = (1..4).map(ListLeaf).collect();
+        let mut pairs = zip_leaves(&text, &lens).unwrap();
+        assert!(pairs.all(|(chunk, len)| chunk.len() == len.0));
+        let longer: NodeRc<_> = (0..4).map(ListLeaf).collect();
+        assert_eq!(zip_leaves(&text, &longer).err(),
+                   Some(TreeError::LengthMismatch { left: 3, right: 4 }));
+    }
+}
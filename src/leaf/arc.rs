@@ -0,0 +1,244 @@
+use binary::{self, LeafCodec};
+use traits::{Leaf, LeafElems, LeafMerge, LeafSplit};
+
+use std::io::{self, Read, Write};
+use std::ops::{Deref, Range};
+use std::sync::Arc;
+
+/// A slice of a shared string. Slicing and splitting never copies the string.
+///
+/// The info of this leaf is its length in bytes.
+#[derive(Clone, Debug)]
+pub struct ArcStr {
+    data: Arc<str>,
+    start: usize,
+    len: usize,
+}
+
+/// A slice of a shared array. Slicing and splitting never copies the array.
+///
+/// The info of this leaf is its length.
+#[derive(Debug)]
+pub struct ArcSlice<T> {
+    data: Arc<[T]>,
+    start: usize,
+    len: usize,
+}
+
+impl ArcStr {
+    pub fn new<S: Into<Arc<str>>>(s: S) -> ArcStr {
+        let data = s.into();
+        let len = data.len();
+        ArcStr { data, start: 0, len }
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.data[self.start..self.start + self.len]
+    }
+
+    /// Returns the sub-slice at the byte `range` of this slice, sharing the same string.
+    ///
+    /// Panics if `range` is out of bounds, or not on char boundaries.
+    pub fn slice(&self, range: Range<usize>) -> ArcStr {
+        let _ = &self.as_str()[range.clone()]; // check bounds and char boundaries
+        ArcStr { data: self.data.clone(), start: self.start + range.start, len: range.len() }
+    }
+}
+
+impl Deref for ArcStr {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl PartialEq for ArcStr {
+    fn eq(&self, other: &ArcStr) -> bool {
+        self.as_str() == other.as_str()
+    }
+}
+
+impl Eq for ArcStr {}
+
+impl Leaf for ArcStr {
+    type Info = usize;
+
+    fn compute_info(&self) -> usize {
+        self.len
+    }
+}
+
+impl LeafSplit for ArcStr {
+    /// Panics if `at` is not a char boundary within this slice.
+    fn split_off(&mut self, at: usize) -> ArcStr {
+        let right = self.slice(at..self.len);
+        self.len = at;
+        right
+    }
+}
+
+impl LeafMerge for ArcStr {
+    /// Merges only adjacent slices of the same string, so that no copying is needed.
+    fn try_merge(&mut self, other: &ArcStr) -> bool {
+        let mergeable = Arc::ptr_eq(&self.data, &other.data)
+                        && self.start + self.len == other.start;
+        if mergeable {
+            self.len += other.len;
+        }
+        mergeable
+    }
+}
+
+impl LeafElems for ArcStr {
+    type Elem = char;
+
+    fn end_offset(&self) -> usize {
+        self.len
+    }
+
+    fn elem_at(&self, offset: usize) -> (char, usize) {
+        let ch = self.as_str()[offset..].chars().next().unwrap();
+        (ch, ch.len_utf8())
+    }
+
+    fn elem_before(&self, offset: usize) -> (char, usize) {
+        let ch = self.as_str()[..offset].chars().next_back().unwrap();
+        (ch, ch.len_utf8())
+    }
+}
+
+impl LeafCodec for ArcStr {
+    /// Writes the length in bytes followed by the UTF-8 bytes of the slice.
+    fn encode<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        binary::write_varint(out, self.len as u64)?;
+        out.write_all(self.as_bytes())
+    }
+
+    fn decode<R: Read>(input: &mut R) -> io::Result<ArcStr> {
+        let len = binary::read_varint(input)?;
+        let mut bytes = Vec::new(); // not allocated upfront, since the length may be corrupt
+        input.take(len).read_to_end(&mut bytes)?;
+        if bytes.len() as u64 != len {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        let s = String::from_utf8(bytes).map_err(|_| binary::invalid_data("invalid UTF-8"))?;
+        Ok(ArcStr::new(s))
+    }
+}
+
+impl<T> ArcSlice<T> {
+    pub fn new<S: Into<Arc<[T]>>>(s: S) -> ArcSlice<T> {
+        let data = s.into();
+        let len = data.len();
+        ArcSlice { data, start: 0, len }
+    }
+
+    pub fn as_slice(&self) -> &[T] {
+        &self.data[self.start..self.start + self.len]
+    }
+
+    /// Returns the sub-slice at `range` of this slice, sharing the same array.
+    ///
+    /// Panics if `range` is out of bounds.
+    pub fn slice(&self, range: Range<usize>) -> ArcSlice<T> {
+        let _ = &self.as_slice()[range.clone()]; // check bounds
+        ArcSlice { data: self.data.clone(), start: self.start + range.start, len: range.len() }
+    }
+}
+
+impl<T> From<Vec<T>> for ArcSlice<T> {
+    fn from(vec: Vec<T>) -> ArcSlice<T> {
+        ArcSlice::new(vec)
+    }
+}
+
+// Not derived, since that would require `T: Clone`
+impl<T> Clone for ArcSlice<T> {
+    fn clone(&self) -> Self {
+        ArcSlice { data: self.data.clone(), start: self.start, len: self.len }
+    }
+}
+
+impl<T> Deref for ArcSlice<T> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        self.as_slice()
+    }
+}
+
+impl<T: PartialEq> PartialEq for ArcSlice<T> {
+    fn eq(&self, other: &ArcSlice<T>) -> bool {
+        self.as_slice() == other.as_slice()
+    }
+}
+
+impl<T: Eq> Eq for ArcSlice<T> {}
+
+impl<T> Leaf for ArcSlice<T> {
+    type Info = usize;
+
+    fn compute_info(&self) -> usize {
+        self.len
+    }
+}
+
+impl<T> LeafSplit for ArcSlice<T> {
+    /// Panics if `at > self.len()`.
+    fn split_off(&mut self, at: usize) -> ArcSlice<T> {
+        let right = self.slice(at..self.len);
+        self.len = at;
+        right
+    }
+}
+
+impl<T> LeafMerge for ArcSlice<T> {
+    /// Merges only adjacent slices of the same array, so that no copying is needed.
+    fn try_merge(&mut self, other: &ArcSlice<T>) -> bool {
+        let mergeable = Arc::ptr_eq(&self.data, &other.data)
+                        && self.start + self.len == other.start;
+        if mergeable {
+            self.len += other.len;
+        }
+        mergeable
+    }
+}
+
+impl<T: Clone> LeafElems for ArcSlice<T> {
+    type Elem = T;
+
+    fn end_offset(&self) -> usize {
+        self.len
+    }
+
+    fn elem_at(&self, offset: usize) -> (T, usize) {
+        (self.as_slice()[offset].clone(), 1)
+    }
+
+    fn elem_before(&self, offset: usize) -> (T, usize) {
+        (self.as_slice()[offset - 1].clone(), 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ArcSlice, ArcStr};
+    use traits::LeafSplit;
+
+    use std::sync::Arc;
+
+    #[test]
+    fn split_shares() {
+        let mut left = ArcStr::new("héllo world");
+        let right = left.split_off(7);
+        assert_eq!((&*left, &*right), ("héllo ", "world"));
+        assert!(Arc::ptr_eq(&left.data, &right.data));
+        assert_eq!(&*right.slice(1..3), "or");
+
+        let mut left = ArcSlice::new(vec![1, 2, 3, 4]);
+        let right = left.split_off(1);
+        assert_eq!((&*left, &*right), (&[1][..], &[2, 3, 4][..]));
+        assert!(Arc::ptr_eq(&left.data, &right.data));
+    }
+}
@@ -0,0 +1,169 @@
+use traits::{Leaf, LeafElems, LeafMerge, LeafSplit};
+
+use arrayvec::{Array, ArrayVec};
+
+use std::fmt;
+use std::ops::Deref;
+
+/// A chunk of a sequence of items, stored inline with a fixed capacity. For example,
+/// `ArrayChunk<[u32; 64]>` holds up to 64 `u32`s.
+///
+/// The info of this leaf is its length, which can be used as a count for indexing.
+pub struct ArrayChunk<A: Array> {
+    items: ArrayVec<A>,
+}
+
+impl<A: Array> ArrayChunk<A> {
+    pub fn new() -> Self {
+        ArrayChunk { items: ArrayVec::new() }
+    }
+
+    /// The maximum number of items in a chunk.
+    pub fn capacity() -> usize {
+        A::capacity()
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.items.is_full()
+    }
+
+    /// Append `item` to the chunk. Returns it back if the chunk is full.
+    pub fn push(&mut self, item: A::Item) -> Option<A::Item> {
+        self.items.push(item)
+    }
+
+    /// Insert `item` at `index`. Returns it back if the chunk is full.
+    ///
+    /// Panics if `index` is out of bounds.
+    pub fn insert(&mut self, index: usize, item: A::Item) -> Option<A::Item> {
+        self.items.insert(index, item)
+    }
+
+    /// Remove the item at `index`, or returns `None` if `index` is out of bounds.
+    pub fn remove(&mut self, index: usize) -> Option<A::Item> {
+        self.items.remove(index)
+    }
+
+    pub fn as_slice(&self) -> &[A::Item] {
+        &self.items
+    }
+
+    pub fn as_mut_slice(&mut self) -> &mut [A::Item] {
+        &mut self.items
+    }
+
+    /// Collect `items` into full chunks (except the last one, which may be partially filled).
+    pub fn chunk_items<I: IntoIterator<Item=A::Item>>(items: I) -> Vec<ArrayChunk<A>> {
+        let mut chunks = Vec::new();
+        let mut chunk = ArrayChunk::new();
+        for item in items {
+            if let Some(item) = chunk.push(item) {
+                chunks.push(chunk);
+                chunk = ArrayChunk::new();
+                chunk.push(item);
+            }
+        }
+        if !chunk.is_empty() {
+            chunks.push(chunk);
+        }
+        chunks
+    }
+}
+
+impl<A: Array> Default for ArrayChunk<A> {
+    fn default() -> Self {
+        ArrayChunk::new()
+    }
+}
+
+impl<A: Array> Clone for ArrayChunk<A> where A::Item: Clone {
+    fn clone(&self) -> Self {
+        ArrayChunk { items: self.items.clone() }
+    }
+}
+
+impl<A: Array> fmt::Debug for ArrayChunk<A> where A::Item: fmt::Debug {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.items.iter()).finish()
+    }
+}
+
+impl<A: Array> PartialEq for ArrayChunk<A> where A::Item: PartialEq {
+    fn eq(&self, other: &ArrayChunk<A>) -> bool {
+        self.as_slice() == other.as_slice()
+    }
+}
+
+impl<A: Array> Eq for ArrayChunk<A> where A::Item: Eq {}
+
+impl<A: Array> Deref for ArrayChunk<A> {
+    type Target = [A::Item];
+
+    fn deref(&self) -> &[A::Item] {
+        &self.items
+    }
+}
+
+impl<A: Array> Leaf for ArrayChunk<A> where A::Item: Clone {
+    type Info = usize;
+
+    fn compute_info(&self) -> usize {
+        self.items.len()
+    }
+}
+
+impl<A: Array> LeafSplit for ArrayChunk<A> where A::Item: Clone {
+    /// Panics if `at > self.len()`.
+    fn split_off(&mut self, at: usize) -> ArrayChunk<A> {
+        assert!(at <= self.len(), "split index {} out of bounds (len: {})", at, self.len());
+        ArrayChunk { items: self.items.drain(at..).collect() }
+    }
+}
+
+impl<A: Array> LeafMerge for ArrayChunk<A> where A::Item: Clone {
+    /// Merges only if the items of both chunks fit in one chunk.
+    fn try_merge(&mut self, other: &ArrayChunk<A>) -> bool {
+        let mergeable = self.len() + other.len() <= Self::capacity();
+        if mergeable {
+            self.items.extend(other.items.iter().cloned());
+        }
+        mergeable
+    }
+}
+
+impl<A: Array> LeafElems for ArrayChunk<A> where A::Item: Clone {
+    type Elem = A::Item;
+
+    fn end_offset(&self) -> usize {
+        self.items.len()
+    }
+
+    fn elem_at(&self, offset: usize) -> (A::Item, usize) {
+        (self.items[offset].clone(), 1)
+    }
+
+    fn elem_before(&self, offset: usize) -> (A::Item, usize) {
+        (self.items[offset - 1].clone(), 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ArrayChunk;
+    use traits::{LeafMerge, LeafSplit};
+    use test_help::*;
+
+    #[test]
+    fn chunks() {
+        let node: NodeRc<_> = ArrayChunk::<[usize; 16]>::chunk_items(0..1000).into_iter().collect();
+        assert_eq!(node.info(), 1000);
+        assert!(node.iter_elements().eq(0..1000));
+
+        let mut chunk = ArrayChunk::<[usize; 16]>::chunk_items(0..10).pop().unwrap();
+        let right = chunk.split_off(4);
+        assert_eq!((&*chunk, &*right), (&[0, 1, 2, 3][..], &[4, 5, 6, 7, 8, 9][..]));
+        assert!(chunk.try_merge(&right));
+        assert!(!chunk.try_merge(&chunk.clone()));
+        assert_eq!(chunk.len(), 10);
+    }
+}
@@ -0,0 +1,139 @@
+use traits::{Leaf, LeafElems, LeafSplit};
+
+use std::ops::Deref;
+
+/// A leaf borrowing a slice (`str` or `[T]`) of an existing buffer, so that a buffer can be indexed
+/// without copying it into owned leaves. Splitting never copies either.
+///
+/// `Node` and the cursors need no lifetime of their own for this: a tree of `BorrowedLeaf<'a, _>`
+/// simply cannot outlive `'a`. (The only exception is `Pool33M`, which requires `'static` leaves.)
+/// Adjacent leaves are never merged, since two borrowed slices cannot be proven to belong to the
+/// same buffer; use `ArcStr` or `ArcSlice` when that is needed.
+///
+/// The info of this leaf is its length (in bytes, for `str`).
+#[derive(Debug, PartialEq, Eq)]
+pub struct BorrowedLeaf<'a, T: ?Sized + 'a>(pub &'a T);
+
+// Not derived, since that would require `T: Clone`
+impl<'a, T: ?Sized> Clone for BorrowedLeaf<'a, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'a, T: ?Sized> Copy for BorrowedLeaf<'a, T> {}
+
+impl<'a, T: ?Sized> Deref for BorrowedLeaf<'a, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.0
+    }
+}
+
+impl<'a, T: ?Sized> From<&'a T> for BorrowedLeaf<'a, T> {
+    fn from(slice: &'a T) -> Self {
+        BorrowedLeaf(slice)
+    }
+}
+
+impl<'a> Leaf for BorrowedLeaf<'a, str> {
+    type Info = usize;
+
+    fn compute_info(&self) -> usize {
+        self.0.len()
+    }
+}
+
+impl<'a> LeafSplit for BorrowedLeaf<'a, str> {
+    /// Panics if `at` is not a char boundary within this slice.
+    fn split_off(&mut self, at: usize) -> Self {
+        let (left, right) = self.0.split_at(at);
+        self.0 = left;
+        BorrowedLeaf(right)
+    }
+}
+
+impl<'a> LeafElems for BorrowedLeaf<'a, str> {
+    type Elem = char;
+
+    fn end_offset(&self) -> usize {
+        self.0.len()
+    }
+
+    fn elem_at(&self, offset: usize) -> (char, usize) {
+        let ch = self.0[offset..].chars().next().unwrap();
+        (ch, ch.len_utf8())
+    }
+
+    fn elem_before(&self, offset: usize) -> (char, usize) {
+        let ch = self.0[..offset].chars().next_back().unwrap();
+        (ch, ch.len_utf8())
+    }
+}
+
+impl<'a, T> Leaf for BorrowedLeaf<'a, [T]> {
+    type Info = usize;
+
+    fn compute_info(&self) -> usize {
+        self.0.len()
+    }
+}
+
+impl<'a, T> LeafSplit for BorrowedLeaf<'a, [T]> {
+    /// Panics if `at > self.len()`.
+    fn split_off(&mut self, at: usize) -> Self {
+        let (left, right) = self.0.split_at(at);
+        self.0 = left;
+        BorrowedLeaf(right)
+    }
+}
+
+impl<'a, T: Clone> LeafElems for BorrowedLeaf<'a, [T]> {
+    type Elem = T;
+
+    fn end_offset(&self) -> usize {
+        self.0.len()
+    }
+
+    fn elem_at(&self, offset: usize) -> (T, usize) {
+        (self.0[offset].clone(), 1)
+    }
+
+    fn elem_before(&self, offset: usize) -> (T, usize) {
+        (self.0[offset - 1].clone(), 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::BorrowedLeaf;
+    use cursor::{Cursor, CursorMut};
+    use traits::LeafSplit;
+
+    #[test]
+    fn borrowed_buffer() {
+        let buffer = String::from("one two three four five");
+        let words = buffer.split_inclusive(' ').map(BorrowedLeaf);
+        let mut cursor: CursorMut<_, usize> = words.collect();
+        cursor.goto_min(8);
+        let mut leaf = *cursor.leaf().unwrap();
+        assert_eq!(&*leaf, "three ");
+        let right = leaf.split_off(3);
+        assert_eq!((&*leaf, &*right), ("thr", "ee "));
+        assert_eq!(right.as_ptr(), buffer[11..].as_ptr()); // not copied
+        cursor.remove_leaf();
+        let root = cursor.into_root().unwrap();
+
+        let mut cursor = Cursor::<_, usize>::new(&root);
+        assert_eq!(cursor.goto_min(8).map(|leaf| leaf.0), Some("four "));
+        assert_eq!(root.info(), buffer.len() - "three ".len());
+
+        let bytes = buffer.as_bytes();
+        let mut cursor = CursorMut::<_, usize>::new();
+        for chunk in bytes.chunks(4) {
+            cursor.insert_leaf(BorrowedLeaf(chunk), true);
+        }
+        assert_eq!(cursor.into_root().unwrap().info(), bytes.len());
+    }
+}
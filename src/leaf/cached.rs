@@ -0,0 +1,125 @@
+use traits::{Leaf, LeafElems, LeafMerge, LeafSplit};
+
+use std::fmt;
+use std::ops::Deref;
+
+/// A leaf that keeps the info of the wrapped leaf along with it, so that `compute_info` is O(1).
+///
+/// This is useful for leaves whose info is expensive to compute (such as large string chunks, for
+/// which chars and newlines need to be counted), since the info of a leaf is computed whenever a
+/// node containing it is rebalanced. The wrapped leaf can only be mutated through `update`, which
+/// computes the info again afterwards.
+#[derive(Clone)]
+pub struct CachedLeaf<L: Leaf> {
+    value: L,
+    info: L::Info,
+}
+
+impl<L: Leaf> CachedLeaf<L> {
+    pub fn new(value: L) -> Self {
+        let info = value.compute_info();
+        CachedLeaf { value, info }
+    }
+
+    /// Mutate the wrapped leaf using `f`, and update the cached info.
+    pub fn update<F, R>(&mut self, f: F) -> R where F: FnOnce(&mut L) -> R {
+        let ret = f(&mut self.value);
+        self.info = self.value.compute_info();
+        ret
+    }
+
+    pub fn into_inner(self) -> L {
+        self.value
+    }
+}
+
+impl<L: Leaf> Deref for CachedLeaf<L> {
+    type Target = L;
+
+    fn deref(&self) -> &L {
+        &self.value
+    }
+}
+
+impl<L: Leaf> From<L> for CachedLeaf<L> {
+    fn from(value: L) -> Self {
+        CachedLeaf::new(value)
+    }
+}
+
+// Not derived, since that would require `L::Info: Debug`
+impl<L: Leaf + fmt::Debug> fmt::Debug for CachedLeaf<L> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("CachedLeaf").field(&self.value).finish()
+    }
+}
+
+impl<L: Leaf + PartialEq> PartialEq for CachedLeaf<L> {
+    fn eq(&self, other: &Self) -> bool {
+        self.value == other.value
+    }
+}
+
+impl<L: Leaf + Eq> Eq for CachedLeaf<L> {}
+
+impl<L: Leaf> Leaf for CachedLeaf<L> {
+    type Info = L::Info;
+
+    fn compute_info(&self) -> L::Info {
+        self.info
+    }
+}
+
+impl<L: LeafSplit> LeafSplit for CachedLeaf<L> {
+    fn split_off(&mut self, at: usize) -> Self {
+        CachedLeaf::new(self.update(|value| value.split_off(at)))
+    }
+}
+
+impl<L: LeafMerge> LeafMerge for CachedLeaf<L> {
+    fn try_merge(&mut self, other: &Self) -> bool {
+        // not using `update`, to skip computing the info when not merged
+        let merged = self.value.try_merge(&other.value);
+        if merged {
+            self.info = self.value.compute_info();
+        }
+        merged
+    }
+}
+
+impl<L: LeafElems> LeafElems for CachedLeaf<L> {
+    type Elem = L::Elem;
+
+    fn end_offset(&self) -> usize {
+        self.value.end_offset()
+    }
+
+    fn elem_at(&self, offset: usize) -> (L::Elem, usize) {
+        self.value.elem_at(offset)
+    }
+
+    fn elem_before(&self, offset: usize) -> (L::Elem, usize) {
+        self.value.elem_before(offset)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CachedLeaf;
+    use leaf::{StringChunk, TextInfo};
+    use traits::{Leaf, LeafMerge, LeafSplit};
+
+    #[test]
+    fn cached_info() {
+        let mut leaf = CachedLeaf::new(StringChunk::from("one\ntwo"));
+        assert_eq!(leaf.compute_info(), TextInfo::of("one\ntwo"));
+        leaf.update(|chunk| *chunk = StringChunk::from("one\ntwo\n"));
+        assert_eq!(leaf.compute_info(), TextInfo::of("one\ntwo\n"));
+
+        let rest = leaf.split_off(4);
+        assert_eq!(leaf.compute_info(), TextInfo::of("one\n"));
+        assert_eq!(rest.compute_info(), TextInfo::of("two\n"));
+        assert!(leaf.try_merge(&rest));
+        assert_eq!(leaf.compute_info(), TextInfo::of("one\ntwo\n"));
+    }
+}
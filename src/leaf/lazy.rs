@@ -0,0 +1,215 @@
+use node::{Node, NodesPtr};
+use traits::{Leaf, PathInfo, SubOrd};
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::ops::{Deref, Range};
+use std::sync::{Arc, OnceLock};
+
+/// An external storage of leaves, such as chunks of a file on disk.
+pub trait LeafStore {
+    type Leaf: Leaf;
+    /// The key of a leaf in the store.
+    type Id: Clone;
+
+    /// Read the leaf identified by `id` from the store.
+    fn load(&self, id: &Self::Id) -> Self::Leaf;
+}
+
+/// A leaf whose value is loaded from a `LeafStore` on first access.
+///
+/// The info of the leaf is kept with it, so that the tree can be built and navigated without
+/// loading any leaves. The value is cached in the leaf once loaded, until `unload` is called. Thus
+/// only the internal nodes and the leaves accessed (since they were last unloaded) are in memory.
+///
+/// The leaf dereferences to its value, so accessing the contents through a tree (such as with
+/// `Cursor::chunk_at` or `Node::chunks`) loads the leaves accessed. See also `Node::load_range` and
+/// `Node::unload_leaves` for loading and unloading leaves of a tree in bulk.
+pub struct LazyLeaf<S: LeafStore> {
+    store: Arc<S>,
+    id: S::Id,
+    info: <S::Leaf as Leaf>::Info,
+    value: OnceLock<S::Leaf>,
+}
+
+impl<S: LeafStore> LazyLeaf<S> {
+    /// A leaf of `store` identified by `id`, having `info`, that is not loaded yet.
+    pub fn new(store: Arc<S>, id: S::Id, info: <S::Leaf as Leaf>::Info) -> Self {
+        LazyLeaf { store, id, info, value: OnceLock::new() }
+    }
+
+    /// A leaf of `store` identified by `id`, that is already loaded as `value`.
+    pub fn loaded(store: Arc<S>, id: S::Id, value: S::Leaf) -> Self {
+        let info = value.compute_info();
+        LazyLeaf { store, id, info, value: OnceLock::from(value) }
+    }
+
+    pub fn id(&self) -> &S::Id {
+        &self.id
+    }
+
+    /// Returns the value of this leaf, loading it from the store if not loaded.
+    pub fn get(&self) -> &S::Leaf {
+        self.value.get_or_init(|| self.store.load(&self.id))
+    }
+
+    pub fn is_loaded(&self) -> bool {
+        self.value.get().is_some()
+    }
+
+    /// Drop the loaded value (if any) to free up memory. It is loaded again on next access.
+    pub fn unload(&mut self) {
+        self.value.take();
+    }
+}
+
+// Not derived, since that would require `S: Clone`
+impl<S: LeafStore> Clone for LazyLeaf<S> {
+    fn clone(&self) -> Self {
+        LazyLeaf {
+            store: self.store.clone(),
+            id: self.id.clone(),
+            info: self.info,
+            value: self.value.clone(),
+        }
+    }
+}
+
+impl<S: LeafStore> fmt::Debug for LazyLeaf<S> where S::Id: fmt::Debug {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "LazyLeaf {{ id: {:?}, loaded: {} }}", self.id, self.is_loaded())
+    }
+}
+
+impl<S: LeafStore> Deref for LazyLeaf<S> {
+    type Target = S::Leaf;
+
+    fn deref(&self) -> &S::Leaf {
+        self.get()
+    }
+}
+
+impl<S: LeafStore> Leaf for LazyLeaf<S> {
+    type Info = <S::Leaf as Leaf>::Info;
+
+    fn compute_info(&self) -> Self::Info {
+        self.info
+    }
+}
+
+impl<S: LeafStore, NP: NodesPtr<LazyLeaf<S>>> Node<LazyLeaf<S>, NP> {
+    /// Load the leaves overlapping `range` that are not loaded, such as those about to be
+    /// displayed, ahead of accessing them. Returns the number of leaves loaded.
+    ///
+    /// The loaded values are cached in the leaves, which are shared with any other trees sharing
+    /// the subtrees containing them.
+    pub fn load_range<PI, K>(&self, range: Range<K>) -> usize
+        where PI: PathInfo<<S::Leaf as Leaf>::Info>, K: SubOrd<PI>,
+    {
+        let mut loaded = 0;
+        self.load_range_rec(PI::identity(), &range, &mut loaded);
+        loaded
+    }
+
+    fn load_range_rec<PI, K>(&self, start: PI, range: &Range<K>, loaded: &mut usize)
+        where PI: PathInfo<<S::Leaf as Leaf>::Info>, K: SubOrd<PI>,
+    {
+        let end = start.extend(self.info());
+        if range.start.sub_cmp(&end) != Ordering::Less ||
+           range.end.sub_cmp(&start) != Ordering::Greater {
+            return; // not overlapping
+        }
+        match self.leaf() {
+            Some(leaf) => {
+                if !leaf.is_loaded() {
+                    leaf.get();
+                    *loaded += 1;
+                }
+            }
+            None => {
+                let mut start = start;
+                for child in self.children() {
+                    child.load_range_rec(start, range, loaded);
+                    start = start.extend(child.info());
+                }
+            }
+        }
+    }
+
+    /// Unload all loaded leaves of this tree to free up memory. Returns whether any leaf was
+    /// loaded.
+    ///
+    /// As with `mutate_leaves`, the nodes along the paths to loaded leaves are copied, so that
+    /// other trees sharing them are unaffected.
+    pub fn unload_leaves(&mut self) -> bool {
+        self.mutate_leaves(|leaf| {
+            let loaded = leaf.is_loaded();
+            leaf.unload();
+            loaded
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{LazyLeaf, LeafStore};
+    use cursor::Cursor;
+    use test_help::*;
+
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    struct Store {
+        loads: AtomicUsize,
+    }
+
+    impl LeafStore for Store {
+        type Leaf = ListLeaf;
+        type Id = usize;
+
+        fn load(&self, &id: &usize) -> ListLeaf {
+            self.loads.fetch_add(1, Ordering::SeqCst);
+            ListLeaf(id)
+        }
+    }
+
+    #[test]
+    fn load_on_access() {
+        let store = Arc::new(Store { loads: AtomicUsize::new(0) });
+        let info = |i| ListInfo { count: 1, sum: i };
+        let node: NodeRc<_> = (0..100).map(|i| LazyLeaf::new(store.clone(), i, info(i))).collect();
+        assert_eq!(node.info(), ListInfo { count: 100, sum: 99 * 100 / 2 });
+        assert_eq!(store.loads.load(Ordering::SeqCst), 0);
+
+        let mut cursor_mut = ::cursor::CursorMut::<_, ListPath>::from_node(node);
+        assert_eq!(cursor_mut.goto_min(ListIndex(42)).unwrap().get(), &ListLeaf(42));
+        assert_eq!(cursor_mut.leaf().unwrap().get(), &ListLeaf(42));
+        assert_eq!(store.loads.load(Ordering::SeqCst), 1);
+        cursor_mut.leaf_update(|leaf| leaf.unload());
+        assert!(!cursor_mut.leaf().unwrap().is_loaded());
+        assert_eq!(cursor_mut.leaf().unwrap().get(), &ListLeaf(42));
+        assert_eq!(store.loads.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn load_and_unload() {
+        let store = Arc::new(Store { loads: AtomicUsize::new(0) });
+        let info = |i| ListInfo { count: 1, sum: i };
+        let mut node: NodeRc<_> = (0..100).map(|i| LazyLeaf::new(store.clone(), i, info(i)))
+                                          .collect();
+        let (chunk, path_info) = Cursor::<_, ListPath>::new(&node).chunk_at(ListIndex(7)).unwrap();
+        assert_eq!((chunk, path_info.index), (&ListLeaf(7), 7));
+        assert_eq!(store.loads.load(Ordering::SeqCst), 1);
+
+        assert_eq!(node.load_range::<ListPath, _>(ListIndex(5)..ListIndex(20)), 14);
+        assert_eq!(node.load_range::<ListPath, _>(ListIndex(5)..ListIndex(20)), 0);
+        assert_eq!(store.loads.load(Ordering::SeqCst), 15);
+        assert_eq!(node.leaves().filter(|leaf| leaf.is_loaded()).count(), 15);
+
+        let snapshot = node.clone();
+        assert!(node.unload_leaves());
+        assert!(!node.unload_leaves());
+        assert_eq!(node.leaves().filter(|leaf| leaf.is_loaded()).count(), 0);
+        assert_eq!(snapshot.leaves().filter(|leaf| leaf.is_loaded()).count(), 15);
+    }
+}
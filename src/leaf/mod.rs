@@ -0,0 +1,17 @@
+//! Ready-made leaf types.
+
+mod arc;
+mod array;
+mod borrowed;
+mod cached;
+mod lazy;
+mod read_only;
+mod string;
+
+pub use self::arc::{ArcSlice, ArcStr};
+pub use self::array::ArrayChunk;
+pub use self::borrowed::BorrowedLeaf;
+pub use self::cached::CachedLeaf;
+pub use self::lazy::{LazyLeaf, LeafStore};
+pub use self::read_only::ReadOnly;
+pub use self::string::{ByteIndex, CharIndex, LineIndex, StringChunk, TextInfo};
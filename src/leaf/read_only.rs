@@ -0,0 +1,88 @@
+use traits::{Leaf, ReadOnlyLeaf};
+
+use std::ops::Deref;
+use std::sync::Arc;
+
+/// A leaf for values that are not `Clone`, such as those owning file handles or large unique
+/// buffers. The value is shared, so cloning the leaf (as copy-on-write mutation of the tree does)
+/// never clones the value.
+///
+/// The value can only be accessed by shared reference, so mutating it in place (even through a
+/// `CursorMut`) is rejected at compile time. A `CursorMut` can still replace or rearrange leaves,
+/// which only clones the `Arc`s, but trees of these leaves are meant to be built once (such as by
+/// collecting an iterator) and read using `Cursor` or `leaves`.
+pub struct ReadOnly<T> {
+    value: Arc<T>,
+}
+
+impl<T> ReadOnly<T> {
+    pub fn new(value: T) -> Self {
+        ReadOnly { value: Arc::new(value) }
+    }
+
+    /// Returns whether both leaves share the same value.
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.value, &other.value)
+    }
+}
+
+// Not derived, since that would require `T: Clone`
+impl<T> Clone for ReadOnly<T> {
+    fn clone(&self) -> Self {
+        ReadOnly { value: self.value.clone() }
+    }
+}
+
+impl<T> Deref for ReadOnly<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.value
+    }
+}
+
+impl<T> From<T> for ReadOnly<T> {
+    fn from(value: T) -> Self {
+        ReadOnly::new(value)
+    }
+}
+
+impl<T: ReadOnlyLeaf> Leaf for ReadOnly<T> {
+    type Info = T::Info;
+
+    fn compute_info(&self) -> T::Info {
+        self.value.compute_info()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ReadOnly;
+    use cursor::Cursor;
+    use test_help::NodeRc;
+    use traits::ReadOnlyLeaf;
+
+    // Not `Clone`
+    struct Buffer(Box<[u8]>);
+
+    impl ReadOnlyLeaf for Buffer {
+        type Info = usize;
+
+        fn compute_info(&self) -> usize {
+            self.0.len()
+        }
+    }
+
+    #[test]
+    fn non_clone_leaves() {
+        let buffers = (0..100).map(|i| Buffer(vec![i as u8; i].into_boxed_slice()));
+        let root: NodeRc<_> = buffers.map(ReadOnly::new).collect();
+        assert_eq!(root.info(), (0..100).sum::<usize>());
+
+        let mut cursor = Cursor::<_, usize>::new(&root);
+        let leaf = cursor.goto_min(10).unwrap();
+        assert_eq!(&*leaf.0, &[5; 5][..]);
+        let copy = root.clone();
+        assert!(copy.leaves().zip(root.leaves()).all(|(a, b)| a.ptr_eq(b)));
+    }
+}
@@ -0,0 +1,272 @@
+use traits::{metric_add, metric_sub, Count, Info, Leaf, LeafElems, LeafLimit, LeafMerge, LeafSplit,
+             PathInfo, SubOrd};
+
+use std::cmp::Ordering;
+use std::ops::Deref;
+
+/// A chunk of a string, for building ropes. Chunks are kept at most `MAX_LEN` bytes long by
+/// `split_text` and `try_merge`, which keeps edits within a chunk cheap.
+///
+/// The info of a chunk is a `TextInfo`, which can also be used as path info, so that positions can
+/// be looked up (and converted) in terms of bytes, chars or lines.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct StringChunk(String);
+
+/// The number of bytes, chars and newlines in a string.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct TextInfo {
+    pub bytes: usize,
+    pub chars: usize,
+    pub newlines: usize,
+}
+
+/// A byte offset in text, for seeking using `TextInfo` as path info.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ByteIndex(pub usize);
+
+/// A char offset in text, for seeking using `TextInfo` as path info.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CharIndex(pub usize);
+
+/// A line number (the number of newlines before a position) in text, for seeking using `TextInfo`
+/// as path info.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LineIndex(pub usize);
+
+impl StringChunk {
+    /// The maximum length of a chunk in bytes, as created by `split_text`.
+    pub const MAX_LEN: usize = 1024;
+
+    /// Create a chunk of `text`, regardless of its length.
+    pub fn new<S: Into<String>>(text: S) -> StringChunk {
+        StringChunk(text.into())
+    }
+
+    /// Split `text` into chunks of at most `MAX_LEN` bytes, on char boundaries.
+    pub fn split_text(mut text: &str) -> Vec<StringChunk> {
+        let mut chunks = Vec::with_capacity(text.len() / Self::MAX_LEN + 1);
+        while !text.is_empty() {
+            let mut at = text.len().min(Self::MAX_LEN);
+            while !text.is_char_boundary(at) {
+                at -= 1;
+            }
+            chunks.push(StringChunk::new(&text[..at]));
+            text = &text[at..];
+        }
+        chunks
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Returns the byte offset of the char at `char_offset` within this chunk (or the length of the
+    /// chunk if `char_offset` is the number of chars in it).
+    ///
+    /// Panics if `char_offset` is out of bounds.
+    pub fn char_to_byte(&self, char_offset: usize) -> usize {
+        let mut indices = self.0.char_indices().map(|(i, _)| i).chain(Some(self.0.len()));
+        indices.nth(char_offset).expect("char offset out of bounds")
+    }
+
+    /// Returns the number of chars before the byte offset `byte_offset` within this chunk.
+    ///
+    /// Panics if `byte_offset` is out of bounds, or not on a char boundary.
+    pub fn byte_to_char(&self, byte_offset: usize) -> usize {
+        self.0[..byte_offset].chars().count()
+    }
+}
+
+impl Deref for StringChunk {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl<'a> From<&'a str> for StringChunk {
+    fn from(text: &'a str) -> StringChunk {
+        StringChunk::new(text)
+    }
+}
+
+impl TextInfo {
+    /// Returns the info of `text`.
+    pub fn of(text: &str) -> TextInfo {
+        TextInfo {
+            bytes: text.len(),
+            chars: text.chars().count(),
+            newlines: text.bytes().filter(|&b| b == b'\n').count(),
+        }
+    }
+}
+
+impl Leaf for StringChunk {
+    type Info = TextInfo;
+
+    fn compute_info(&self) -> TextInfo {
+        TextInfo::of(&self.0)
+    }
+}
+
+impl LeafSplit for StringChunk {
+    /// Panics if `at` is not a char boundary within this chunk.
+    fn split_off(&mut self, at: usize) -> StringChunk {
+        StringChunk(self.0.split_off(at))
+    }
+}
+
+impl LeafMerge for StringChunk {
+    /// Merges only if the merged chunk would be at most `MAX_LEN` bytes long.
+    fn try_merge(&mut self, other: &StringChunk) -> bool {
+        let mergeable = self.0.len() + other.0.len() <= Self::MAX_LEN;
+        if mergeable {
+            self.0.push_str(&other.0);
+        }
+        mergeable
+    }
+}
+
+impl LeafLimit for StringChunk {
+    fn max_weight() -> usize {
+        Self::MAX_LEN
+    }
+
+    fn weight(info: &TextInfo) -> usize {
+        info.bytes
+    }
+
+    fn split_point(&self) -> usize {
+        let mut at = Self::MAX_LEN;
+        while !self.0.is_char_boundary(at) {
+            at -= 1;
+        }
+        at
+    }
+}
+
+impl LeafElems for StringChunk {
+    type Elem = char;
+
+    fn end_offset(&self) -> usize {
+        self.0.len()
+    }
+
+    fn elem_at(&self, offset: usize) -> (char, usize) {
+        let ch = self.0[offset..].chars().next().unwrap();
+        (ch, ch.len_utf8())
+    }
+
+    fn elem_before(&self, offset: usize) -> (char, usize) {
+        let ch = self.0[..offset].chars().next_back().unwrap();
+        (ch, ch.len_utf8())
+    }
+}
+
+impl Info for TextInfo {
+    fn gather(self, other: TextInfo) -> TextInfo {
+        TextInfo {
+            bytes: metric_add(self.bytes, other.bytes),
+            chars: metric_add(self.chars, other.chars),
+            newlines: metric_add(self.newlines, other.newlines),
+        }
+    }
+
+    fn paranoid_eq(&self, other: &TextInfo) -> Option<bool> {
+        Some(self == other)
+    }
+}
+
+/// Counts bytes.
+impl Count for TextInfo {
+    fn count(&self) -> usize {
+        self.bytes
+    }
+}
+
+impl PathInfo for TextInfo {
+    fn extend(self, prev: TextInfo) -> TextInfo {
+        self.gather(prev)
+    }
+
+    fn extend_inv(self, curr: TextInfo) -> TextInfo {
+        TextInfo {
+            bytes: metric_sub(self.bytes, curr.bytes),
+            chars: metric_sub(self.chars, curr.chars),
+            newlines: metric_sub(self.newlines, curr.newlines),
+        }
+    }
+
+    fn identity() -> TextInfo {
+        TextInfo::default()
+    }
+}
+
+impl SubOrd<TextInfo> for ByteIndex {
+    fn sub_cmp(&self, rhs: &TextInfo) -> Ordering {
+        self.0.cmp(&rhs.bytes)
+    }
+}
+
+impl SubOrd<TextInfo> for CharIndex {
+    fn sub_cmp(&self, rhs: &TextInfo) -> Ordering {
+        self.0.cmp(&rhs.chars)
+    }
+}
+
+impl SubOrd<TextInfo> for LineIndex {
+    fn sub_cmp(&self, rhs: &TextInfo) -> Ordering {
+        self.0.cmp(&rhs.newlines)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CharIndex, StringChunk, TextInfo};
+    use traits::LeafMerge;
+    use test_help::*;
+
+    #[test]
+    fn chunks() {
+        let text: String = (0..1000).map(|i| format!("{}: héllo\n", i)).collect();
+        let node: NodeRc<_> = StringChunk::split_text(&text).into_iter().collect();
+        assert!(node.leaves().all(|leaf| leaf.len() <= StringChunk::MAX_LEN));
+        assert_eq!(node.info(), TextInfo::of(&text));
+        assert_eq!(node.chunks().collect::<String>(), text);
+
+        // char offset to byte offset
+        let char_offset = text.chars().count() - 10;
+        let byte_offset = node.convert(CharIndex(char_offset), |path: TextInfo, leaf| {
+            path.bytes + leaf.char_to_byte(char_offset - path.chars)
+        });
+        assert_eq!(byte_offset, Some(text.char_indices().nth(char_offset).unwrap().0));
+
+        let mut chunk = StringChunk::new("a".repeat(1000));
+        assert!(!chunk.try_merge(&StringChunk::new("b".repeat(25))));
+        assert!(chunk.try_merge(&StringChunk::new("é")));
+        assert_eq!(chunk.byte_to_char(1002), 1001);
+    }
+
+    #[test]
+    fn range_info() {
+        let text: String = (0..1000).map(|i| format!("{}: héllo\n", i)).collect();
+        let node: NodeRc<_> = StringChunk::split_text(&text).into_iter().collect();
+        let bytes = |info: &TextInfo| info.bytes;
+        let boundary = |at: usize| (at..).find(|&at| text.is_char_boundary(at)).unwrap();
+        for &(start, end) in &[(0, text.len()), (4, 6), (100, 5000), (3001, 20000)] {
+            let (start, end) = (boundary(start), boundary(end.min(text.len())));
+            assert_eq!(node.range_info(start..end, bytes), Some(TextInfo::of(&text[start..end])));
+        }
+        assert_eq!(node.range_info(7..7, bytes), None);
+        assert_eq!(node.range_info(text.len()..text.len() + 5, bytes), None);
+    }
+
+    #[test]
+    #[cfg(feature = "checked")]
+    #[should_panic(expected = "metric underflow: 1 - 5")]
+    fn checked_extend_inv() {
+        use traits::PathInfo;
+        TextInfo::of("a").extend_inv(TextInfo::of("hello"));
+    }
+}
@@ -5,16 +5,57 @@
 //! [xi-rope]: https://github.com/google/xi-editor/tree/master/rust/rope
 extern crate arrayvec;
 extern crate mines;
+#[cfg(feature = "proptest")]
+extern crate proptest;
+#[cfg(feature = "rayon")]
+extern crate rayon;
+#[cfg(feature = "rkyv")]
+extern crate rkyv;
+#[cfg(feature = "serde")]
+extern crate serde;
 
 #[macro_use]
 mod macros;
 
+#[cfg(feature = "rkyv")]
+pub mod archive;
+pub mod binary;
+pub mod builder;
 pub mod cursor;
+pub mod delta;
+pub mod deque;
+pub mod diff;
+pub mod error;
+pub mod history;
+#[cfg(feature = "instrument")]
+pub mod instrument;
+pub mod intern;
+pub mod io;
+pub mod iter;
+pub mod leaf;
+pub mod marks;
+pub mod merkle;
 pub mod node;
+pub mod ops;
+pub mod overlay;
+#[cfg(feature = "rayon")]
+pub mod par;
+pub mod piece;
+pub mod shared;
+pub mod sorted;
+#[cfg(feature = "serde")]
+mod ser;
+pub mod stats;
+#[cfg(feature = "proptest")]
+pub mod strategy;
 pub mod traits;
+pub mod tree;
+pub mod versioned;
 
 #[cfg(test)]
 extern crate rand;
+#[cfg(all(test, feature = "serde"))]
+extern crate serde_json;
 
 #[cfg(test)]
 mod test_help;
@@ -1,7 +1,15 @@
 #![allow(unused_macros)]
 
+// Emit an `instrument::Event` (a variant with its fields), if the `instrument` feature is enabled.
+macro_rules! instrument {
+    ($variant:ident { $($field:ident: $value:expr),* }) => {
+        #[cfg(feature = "instrument")]
+        ::instrument::emit(::instrument::Event::$variant { $($field: $value),* });
+    }
+}
+
 macro_rules! def_nodes_ptr_rc {
-    ($wrap:tt, $rc:tt, $size:expr) => {
+    ($wrap:tt, $rc:tt, $rcmod:ident, $size:expr) => {
         #[derive(Clone)]
         pub struct $wrap<L: Leaf>($rc<ArrayVec<[Node<L, $wrap<L>>; $size]>>);
 
@@ -13,10 +21,25 @@ macro_rules! def_nodes_ptr_rc {
             }
 
             fn make_mut(this: &mut Self) -> &mut ArrayVec<Self::Array> {
+                if $rc::strong_count(&this.0) > 1 {
+                    instrument!(Clone { height: ::instrument::nodes_height(&this.0) });
+                }
                 $rc::make_mut(&mut this.0)
             }
         }
 
+        impl<L: Leaf> NodesWeak<L> for $wrap<L> {
+            type Weak = ::std::$rcmod::Weak<ArrayVec<[Node<L, $wrap<L>>; $size]>>;
+
+            fn downgrade(this: &Self) -> Self::Weak {
+                $rc::downgrade(&this.0)
+            }
+
+            fn upgrade(weak: &Self::Weak) -> Option<Self> {
+                weak.upgrade().map($wrap)
+            }
+        }
+
         impl<L: Leaf> Deref for $wrap<L> {
             type Target = [Node<L, $wrap<L>>];
 
@@ -54,6 +77,60 @@ macro_rules! def_nodes_ptr_box {
     }
 }
 
+macro_rules! def_nodes_ptr_pool {
+    ($wrap:tt, $size:expr, $limit:expr) => {
+        pub struct $wrap<L: Leaf + 'static>(ManuallyDrop<Rc<ArrayVec<[Node<L, $wrap<L>>; $size]>>>);
+
+        impl<L: Leaf + 'static> Clone for $wrap<L> {
+            fn clone(&self) -> Self {
+                $wrap(self.0.clone())
+            }
+        }
+
+        impl<L: Leaf + 'static> NodesPtr<L> for $wrap<L> {
+            type Array = [Node<L, $wrap<L>>; $size];
+
+            fn new(nodes: ArrayVec<Self::Array>) -> Self {
+                let rc = match pool::take::<Rc<ArrayVec<Self::Array>>>() {
+                    Some(mut rc) => {
+                        *Rc::get_mut(&mut rc).unwrap() = nodes;
+                        rc
+                    }
+                    None => Rc::new(nodes),
+                };
+                $wrap(ManuallyDrop::new(rc))
+            }
+
+            fn make_mut(this: &mut Self) -> &mut ArrayVec<Self::Array> {
+                if Rc::strong_count(&this.0) > 1 {
+                    instrument!(Clone { height: ::instrument::nodes_height(&this.0) });
+                }
+                Rc::make_mut(&mut this.0)
+            }
+        }
+
+        impl<L: Leaf + 'static> Drop for $wrap<L> {
+            fn drop(&mut self) {
+                let mut rc = unsafe { ManuallyDrop::take(&mut self.0) };
+                if let Some(nodes) = Rc::get_mut(&mut rc) {
+                    nodes.clear(); // may recursively return more arrays to the pool
+                } else {
+                    return;
+                }
+                pool::give(rc, $limit);
+            }
+        }
+
+        impl<L: Leaf + 'static> Deref for $wrap<L> {
+            type Target = [Node<L, $wrap<L>>];
+
+            fn deref(&self) -> &[Node<L, $wrap<L>>] {
+                &**self.0
+            }
+        }
+    }
+}
+
 macro_rules! def_cursor_conf {
     ($wrap:tt, $ptr:tt, $buf:expr) => {
         pub enum $wrap {}
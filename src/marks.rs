@@ -0,0 +1,206 @@
+//! Positions that are kept in sync with the edits of a sequence.
+//!
+//! Editors need to track many positions in a buffer (diagnostics, breakpoints, selections) that
+//! should move along with the text around them. Storing each mark as the gap from its previous mark
+//! makes an edit only affect the marks within the edited range and the one right after it.
+
+use cursor::CursorMut;
+use cursor::conf::{CMutConf, Rc33M};
+use node::{Node, NodesPtr};
+use traits::Leaf;
+
+/// A mark in `Marks`, stored relative to the previous mark. The info of a mark is its gap.
+#[derive(Clone, Debug)]
+pub struct Mark<T> {
+    gap: usize,
+    value: T,
+}
+
+impl<T: Clone> Leaf for Mark<T> {
+    type Info = usize;
+
+    fn compute_info(&self) -> usize {
+        self.gap
+    }
+}
+
+/// A sorted collection of positions with associated values.
+///
+/// Positions are in whatever unit the edits are reported in (chars, bytes, leaves, etc.).
+pub struct Marks<T: Clone, CONF = Rc33M>
+    where CONF: CMutConf<Mark<T>, usize>,
+{
+    root: Option<Node<Mark<T>, CONF::Ptr>>,
+    count: usize,
+}
+
+impl<T: Clone, CONF> Clone for Marks<T, CONF>
+    where CONF: CMutConf<Mark<T>, usize>,
+{
+    fn clone(&self) -> Self {
+        Marks {
+            root: self.root.clone(),
+            count: self.count,
+        }
+    }
+}
+
+impl<T: Clone, CONF> Default for Marks<T, CONF>
+    where CONF: CMutConf<Mark<T>, usize>,
+{
+    fn default() -> Self {
+        Marks::new()
+    }
+}
+
+impl<T: Clone, CONF> Marks<T, CONF>
+    where CONF: CMutConf<Mark<T>, usize>,
+{
+    pub fn new() -> Self {
+        Marks {
+            root: None,
+            count: 0,
+        }
+    }
+
+    /// The number of marks.
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /// Add a mark at `pos`. It is placed after all existing marks at `pos`.
+    ///
+    /// Time: O(log n)
+    pub fn insert(&mut self, pos: usize, value: T) {
+        self.count += 1;
+        let mut cursor = match self.root.take() {
+            Some(root) => CursorMut::<_, _, CONF>::from_node(root),
+            None => {
+                self.root = Some(Node::from_leaf(Mark { gap: pos, value }));
+                return;
+            }
+        };
+        if cursor.goto_max(pos).is_none() {
+            cursor.reset();
+            cursor.first_leaf();
+            let next_pos = cursor.leaf().unwrap().gap;
+            cursor.leaf_update(|mark| mark.gap = next_pos - pos);
+            cursor.insert_leaf(Mark { gap: pos, value }, false);
+        } else {
+            let prev_pos = cursor.path_info() + cursor.leaf().unwrap().gap;
+            if cursor.next_leaf().is_some() {
+                let next_pos = cursor.path_info() + cursor.leaf().unwrap().gap;
+                cursor.leaf_update(|mark| mark.gap = next_pos - pos);
+                cursor.insert_leaf(Mark { gap: pos - prev_pos, value }, false);
+            } else {
+                cursor.last_leaf();
+                cursor.insert_leaf(Mark { gap: pos - prev_pos, value }, true);
+            }
+        }
+        self.root = cursor.into_root();
+    }
+
+    /// Update the marks for an edit that replaced `removed` units at `at` with `inserted` units.
+    ///
+    /// Marks at or before `at` are not moved. Marks within the replaced range are moved to `at`,
+    /// and marks after it are shifted by `inserted - removed`.
+    ///
+    /// Time: O(log n + k log n), where k is the number of marks within the replaced range.
+    pub fn edit(&mut self, at: usize, removed: usize, inserted: usize) {
+        let mut cursor = match self.root.take() {
+            Some(root) => CursorMut::<_, _, CONF>::from_node(root),
+            None => return,
+        };
+        // find the first mark after `at`
+        let found = if cursor.goto_max(at).is_some() {
+            cursor.next_leaf().is_some()
+        } else {
+            cursor.reset();
+            cursor.first_leaf().is_some()
+        };
+        if found {
+            let (mut prev_pos, mut prev_new_pos) = (cursor.path_info(), cursor.path_info());
+            loop {
+                let pos = prev_pos + cursor.leaf().unwrap().gap;
+                let new_pos = if pos < at + removed { at } else { pos + inserted - removed };
+                cursor.leaf_update(|mark| mark.gap = new_pos - prev_new_pos);
+                if pos >= at + removed || cursor.next_leaf().is_none() {
+                    break;
+                }
+                prev_pos = pos;
+                prev_new_pos = new_pos;
+            }
+        }
+        self.root = cursor.into_root();
+    }
+
+    /// Returns all marks with their positions, in order.
+    pub fn positions(&self) -> Vec<(usize, &T)> {
+        fn visit<'a, T: Clone, NP>(node: &'a Node<Mark<T>, NP>,
+                                   pos: &mut usize,
+                                   marks: &mut Vec<(usize, &'a T)>)
+            where NP: NodesPtr<Mark<T>>,
+        {
+            match node.leaf() {
+                Some(mark) => {
+                    *pos += mark.gap;
+                    marks.push((*pos, &mark.value));
+                }
+                None => for child in node.children() { visit(child, pos, marks) },
+            }
+        }
+        let mut marks = Vec::with_capacity(self.count);
+        if let Some(ref root) = self.root {
+            visit(root, &mut 0, &mut marks);
+        }
+        marks
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Marks;
+
+    #[test]
+    fn insert_edit() {
+        let mut marks: Marks<char> = Marks::new();
+        marks.insert(10, 'b');
+        marks.insert(20, 'd');
+        marks.insert(5, 'a');
+        marks.insert(15, 'c');
+        marks.insert(20, 'e');
+        let positions = |marks: &Marks<char>| -> Vec<(usize, char)> {
+            marks.positions().into_iter().map(|(pos, &c)| (pos, c)).collect()
+        };
+        assert_eq!(positions(&marks), vec![(5, 'a'), (10, 'b'), (15, 'c'), (20, 'd'), (20, 'e')]);
+
+        marks.edit(10, 0, 3); // insert
+        assert_eq!(positions(&marks), vec![(5, 'a'), (10, 'b'), (18, 'c'), (23, 'd'), (23, 'e')]);
+        marks.edit(6, 13, 1); // replace
+        assert_eq!(positions(&marks), vec![(5, 'a'), (6, 'b'), (6, 'c'), (11, 'd'), (11, 'e')]);
+        marks.edit(0, 100, 0); // delete all
+        assert_eq!(positions(&marks), vec![(0, 'a'), (0, 'b'), (0, 'c'), (0, 'd'), (0, 'e')]);
+        assert_eq!(marks.len(), 5);
+    }
+
+    #[test]
+    fn many_marks() {
+        let mut marks: Marks<usize> = Marks::new();
+        for i in 0..500 {
+            marks.insert(i * 2, i);
+        }
+        marks.edit(500, 100, 0);
+        for (pos, &i) in marks.positions() {
+            let expected = match i * 2 {
+                p if p <= 500 => p,
+                p if p < 600 => 500,
+                p => p - 100,
+            };
+            assert_eq!(pos, expected);
+        }
+    }
+}
@@ -0,0 +1,140 @@
+//! Hashing of subtrees, memoized in the info of every node.
+//!
+//! Wrapping leaves in `Merkle` makes each node keep a hash of the sequence of leaves under it,
+//! computed from the hashes of its children. So the hash of a tree is available in O(1) time,
+//! and after an edit, only the hashes of the nodes along the edited paths are recomputed. This is
+//! useful as a cache key for subtrees, or for finding the subtrees that differ between replicas.
+//!
+//! The hash of a sequence of leaves is a polynomial hash of the (64-bit) hashes of its leaves,
+//! which can be gathered in any grouping. Thus, equal sequences of leaves have equal hashes
+//! regardless of how they are split across nodes. The hashes are not cryptographically secure,
+//! and use `DefaultHasher` for hashing leaves, so they should not be persisted across builds.
+
+use node::{Node, NodesPtr};
+use traits::{metric_add, metric_sub, Count, Info, Leaf, PathInfo};
+
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::ops::{Deref, DerefMut};
+
+const MODULUS: u64 = (1 << 61) - 1; // a Mersenne prime
+const BASE: u64 = 0x0b5a_d4ec_ed7f_3c25;
+
+fn mul_mod(a: u64, b: u64) -> u64 {
+    ((u128::from(a) * u128::from(b)) % u128::from(MODULUS)) as u64
+}
+
+/// A leaf whose info includes a hash of the leaf. See the module documentation for details.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Merkle<L>(pub L);
+
+/// The info of a `Merkle` leaf: the info of the wrapped leaf, along with a hash.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MerkleInfo<I> {
+    pub info: I,
+    hash: u64,
+    scale: u64, // BASE^n (mod MODULUS) where n is the number of leaves
+}
+
+impl<I> MerkleInfo<I> {
+    /// The hash of the leaves that this info was gathered from.
+    pub fn hash(&self) -> u64 {
+        self.hash
+    }
+}
+
+impl<L> Deref for Merkle<L> {
+    type Target = L;
+
+    fn deref(&self) -> &L {
+        &self.0
+    }
+}
+
+impl<L> DerefMut for Merkle<L> {
+    fn deref_mut(&mut self) -> &mut L {
+        &mut self.0
+    }
+}
+
+impl<L: Leaf + Hash> Leaf for Merkle<L> {
+    type Info = MerkleInfo<L::Info>;
+
+    fn compute_info(&self) -> Self::Info {
+        let mut hasher = DefaultHasher::new();
+        self.0.hash(&mut hasher);
+        MerkleInfo {
+            info: self.0.compute_info(),
+            hash: hasher.finish() % MODULUS,
+            scale: BASE,
+        }
+    }
+}
+
+impl<I: Info> Info for MerkleInfo<I> {
+    fn gather(self, other: Self) -> Self {
+        MerkleInfo {
+            info: self.info.gather(other.info),
+            hash: (mul_mod(self.hash, other.scale) + other.hash) % MODULUS,
+            scale: mul_mod(self.scale, other.scale),
+        }
+    }
+
+    fn paranoid_eq(&self, other: &Self) -> Option<bool> {
+        let hash_eq = self.hash == other.hash && self.scale == other.scale;
+        Some(hash_eq && self.info.paranoid_eq(&other.info) != Some(false))
+    }
+}
+
+impl<I: Count> Count for MerkleInfo<I> {
+    fn count(&self) -> usize {
+        self.info.count()
+    }
+}
+
+impl PathInfo<MerkleInfo<usize>> for usize {
+    #[inline]
+    fn extend(self, other: MerkleInfo<usize>) -> usize { metric_add(self, other.info) }
+
+    #[inline]
+    fn extend_inv(self, other: MerkleInfo<usize>) -> usize { metric_sub(self, other.info) }
+
+    #[inline]
+    fn identity() -> usize { 0 }
+}
+
+impl<L, NP> Node<Merkle<L>, NP>
+    where L: Leaf + Hash,
+          NP: NodesPtr<Merkle<L>>,
+{
+    /// The hash of the sequence of leaves in this tree. Trees with equal sequences of leaves have
+    /// equal hashes.
+    ///
+    /// Time: O(1)
+    pub fn structural_hash(&self) -> u64 {
+        self.info().hash
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Merkle;
+    use test_help::*;
+
+    #[test]
+    fn structural_hash() {
+        let node1: NodeRc<_> = (0..1000).map(|i| Merkle(ListLeaf(i))).collect();
+        let node2 = NodeRc::concat((0..10).map(|i| Merkle(ListLeaf(i))).collect(),
+                                   (10..1000).map(|i| Merkle(ListLeaf(i))).collect());
+        assert!(node1.children().len() != node2.children().len());
+        assert_eq!(node1.structural_hash(), node2.structural_hash());
+
+        let mut cursor_mut: CursorMutT<_> = CursorMutT::from_node(node1.clone());
+        cursor_mut.last_leaf();
+        cursor_mut.leaf_update(|leaf| leaf.0 = ListLeaf(0));
+        let node3 = cursor_mut.into_root().unwrap();
+        assert!(node1.structural_hash() != node3.structural_hash());
+        assert_eq!(node3.info().info, ListInfo { count: 1000, sum: 999 * 1000 / 2 - 999 });
+        assert_eq!(node1.children()[0].structural_hash(), node3.children()[0].structural_hash());
+    }
+}
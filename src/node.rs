@@ -1,11 +1,16 @@
-use traits::{Info, Leaf};
+use builder::TreeBuilder;
+use error::TreeError;
+use traits::{Count, Info, Leaf, LeafLimit, LeafSplit, PathInfo, SubOrd};
 
 use arrayvec::ArrayVec;
 use mines::boom;
 
 use std::cmp::{self, Ordering};
+use std::fmt;
+use std::hash::{Hash, Hasher};
 use std::iter::FromIterator;
 use std::mem;
+use std::ops::{Index, Range};
 
 mod links {
     use traits::Leaf;
@@ -15,6 +20,7 @@ mod links {
 
     use std::sync::Arc;
     use std::rc::Rc;
+    use std::mem::ManuallyDrop;
     use std::ops::Deref;
 
     pub trait NodesPtr<L: Leaf>: Clone + Deref<Target=[Node<L, Self>]> {
@@ -28,12 +34,55 @@ mod links {
         }
     }
 
-    def_nodes_ptr_rc!(Arc16, Arc, 16);
-    def_nodes_ptr_rc!(Rc16, Rc, 16);
+    /// A `NodesPtr` that can be weakly referenced. See `WeakNode`.
+    pub trait NodesWeak<L: Leaf>: NodesPtr<L> {
+        type Weak: Clone;
+
+        fn downgrade(this: &Self) -> Self::Weak;
+        fn upgrade(weak: &Self::Weak) -> Option<Self>;
+    }
+
+    def_nodes_ptr_rc!(Arc16, Arc, sync, 16);
+    def_nodes_ptr_rc!(Rc16, Rc, rc, 16);
     def_nodes_ptr_box!(Box16, 16);
+
+    // Like `Rc16`, but arrays of children that are no longer used are kept in a thread-local pool
+    // for reuse (up to a limit), instead of being deallocated. This reduces allocator traffic when
+    // nodes are frequently created and destroyed, as with rapid insertions and removals.
+    def_nodes_ptr_pool!(Pool16, 16, 1024);
+
+    mod pool {
+        use std::any::{Any, TypeId};
+        use std::cell::RefCell;
+        use std::collections::HashMap;
+
+        // Maps the type of pooled objects to a `Vec` of them.
+        thread_local!(static POOL: RefCell<HashMap<TypeId, Box<dyn Any>>> = RefCell::new(HashMap::new()));
+
+        pub fn take<T: Any>() -> Option<T> {
+            POOL.try_with(|pool| {
+                pool.borrow_mut()
+                    .get_mut(&TypeId::of::<T>())
+                    .and_then(|list| list.downcast_mut::<Vec<T>>().unwrap().pop())
+            }).unwrap_or(None)
+        }
+
+        pub fn give<T: Any>(item: T, limit: usize) {
+            let _ = POOL.try_with(move |pool| {
+                let mut pool = pool.borrow_mut();
+                let list = pool.entry(TypeId::of::<T>())
+                               .or_insert_with(|| Box::new(Vec::<T>::new()))
+                               .downcast_mut::<Vec<T>>()
+                               .unwrap();
+                if list.len() < limit {
+                    list.push(item);
+                }
+            });
+        }
+    }
 }
 
-pub use self::links::{NodesPtr, Arc16, Rc16, Box16};
+pub use self::links::{NodesPtr, NodesWeak, Arc16, Rc16, Box16, Pool16};
 
 /// The basic building block of a tree.
 ///
@@ -55,7 +104,8 @@ pub enum Node<L: Leaf, NP> {
 #[derive(Clone)]
 pub struct InternalVal<L: Leaf, NP> {
     info: L::Info,
-    height: usize, // > 0
+    height: u8, // > 0; kept small (along with `stale`) to reduce the size of `Node`
+    stale: bool, // whether info needs to be recomputed (see `CursorMut::defer_info`)
     nodes: NP,
 }
 
@@ -70,6 +120,115 @@ pub struct LeafVal<L: Leaf> {
 #[derive(Clone)]
 pub struct NeverVal(());
 
+/// The rest of a tree along with the leaves removed from it. See `Node::remove_where`.
+pub type Removed<L, NP> = (Option<Node<L, NP>>, Vec<L>);
+
+/// The indices of the children taken to reach a node from the root; the root itself has an empty
+/// path. See `Cursor::tree_path` and `Node::get_by_path`.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct TreePath(pub Vec<usize>);
+
+/// An edit of the leaves of a tree, located in terms of a component `K` of a path info, as in
+/// `Node::remove_where`. See `Node::apply_edits`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Edit<L, K> {
+    /// Insert the leaves before the first leaf starting at or after the position.
+    Insert(K, Vec<L>),
+    /// Remove the leaves starting within the range.
+    Delete(Range<K>),
+    /// Remove the leaves starting within the range, and insert the leaves in their place.
+    Replace(Range<K>, Vec<L>),
+}
+
+impl<L, K> Edit<L, K> {
+    fn start(&self) -> &K {
+        match *self {
+            Edit::Insert(ref start, _) => start,
+            Edit::Delete(ref range) | Edit::Replace(ref range, _) => &range.start,
+        }
+    }
+
+    fn end(&self) -> Option<&K> {
+        match *self {
+            Edit::Insert(..) => None,
+            Edit::Delete(ref range) | Edit::Replace(ref range, _) => Some(&range.end),
+        }
+    }
+
+    fn leaves(&self) -> &[L] {
+        match *self {
+            Edit::Insert(_, ref leaves) | Edit::Replace(_, ref leaves) => leaves,
+            Edit::Delete(_) => &[],
+        }
+    }
+}
+
+// The progress of applying edits in `Node::apply_edits`.
+struct EditState<'a, L: 'a, K: 'a> {
+    edits: &'a [Edit<L, K>],
+    next: usize, // the index of the next edit to be started
+    active: Option<usize>, // the index of the last edit started, which may still remove leaves
+}
+
+/// The left, middle and right parts of a tree split around a range. See `Node::split3`.
+pub type Split3<L, NP> = (Option<Node<L, NP>>, Option<Node<L, NP>>, Option<Node<L, NP>>);
+
+/// A weak reference to a tree, which does not keep its nodes alive. See `Node::downgrade`.
+pub struct WeakNode<L: Leaf, NP: NodesWeak<L> = Arc16<L>> {
+    repr: WeakRepr<L, NP>,
+}
+
+enum WeakRepr<L: Leaf, NP: NodesWeak<L>> {
+    Internal { info: L::Info, height: u8, stale: bool, nodes: NP::Weak },
+    Leaf(Node<L, NP>), // a leaf node does not hold any allocation, so it is kept as is
+}
+
+impl<L: Leaf, NP: NodesWeak<L>> Clone for WeakNode<L, NP> {
+    fn clone(&self) -> Self {
+        let repr = match self.repr {
+            WeakRepr::Internal { info, height, stale, ref nodes } =>
+                WeakRepr::Internal { info, height, stale, nodes: nodes.clone() },
+            WeakRepr::Leaf(ref node) => WeakRepr::Leaf(node.clone()),
+        };
+        WeakNode { repr }
+    }
+}
+
+impl<L: Leaf, NP: NodesWeak<L>> Node<L, NP> {
+    /// Returns a weak reference to this tree, which can be upgraded back to this tree as long as
+    /// its root node is kept alive by some other tree (such as a later version of it).
+    ///
+    /// Useful for caching old versions of a tree opportunistically, without preventing them from
+    /// being freed when they are discarded.
+    pub fn downgrade(&self) -> WeakNode<L, NP> {
+        let repr = match *self {
+            Node::Internal(ref int) => WeakRepr::Internal {
+                info: int.info,
+                height: int.height,
+                stale: int.stale,
+                nodes: NP::downgrade(&int.nodes),
+            },
+            Node::Leaf(_) => WeakRepr::Leaf(self.clone()),
+            Node::Never(_) => unsafe { boom("Never!") },
+        };
+        WeakNode { repr }
+    }
+}
+
+impl<L: Leaf, NP: NodesWeak<L>> WeakNode<L, NP> {
+    /// Returns the tree, or `None` if it has been freed.
+    pub fn upgrade(&self) -> Option<Node<L, NP>> {
+        match self.repr {
+            WeakRepr::Internal { info, height, stale, ref nodes } => {
+                NP::upgrade(nodes).map(|nodes| {
+                    Node::Internal(InternalVal { info, height, stale, nodes })
+                })
+            }
+            WeakRepr::Leaf(ref node) => Some(node.clone()),
+        }
+    }
+}
+
 impl<L: Leaf, NP: NodesPtr<L>> Node<L, NP> {
     #[inline]
     pub fn from_leaf(leaf: L) -> Node<L, NP> {
@@ -114,7 +273,7 @@ impl<L: Leaf, NP: NodesPtr<L>> Node<L, NP> {
 
     pub fn height(&self) -> usize {
         match *self {
-            Node::Internal(ref int) => int.height,
+            Node::Internal(ref int) => int.height as usize,
             Node::Leaf(_) => 0,
             Node::Never(_) => unsafe { boom("Never!") },
         }
@@ -148,7 +307,48 @@ impl<L: Leaf, NP: NodesPtr<L>> Node<L, NP> {
         }
     }
 
+    /// Returns the first leaf of this tree, by walking down its left edge.
+    ///
+    /// Time: O(log n)
+    pub fn first_leaf(&self) -> &L {
+        let mut node = self;
+        loop {
+            match node.children().first() {
+                Some(child) => node = child,
+                None => return node.leaf().unwrap(),
+            }
+        }
+    }
+
+    /// Returns the last leaf of this tree, by walking down its right edge.
+    ///
+    /// Time: O(log n)
+    pub fn last_leaf(&self) -> &L {
+        let mut node = self;
+        loop {
+            match node.children().last() {
+                Some(child) => node = child,
+                None => return node.leaf().unwrap(),
+            }
+        }
+    }
+
+    /// Returns whether both nodes are internal nodes sharing the same children, which means one
+    /// is a (copy-on-write) clone of the other. Leaf nodes are never considered the same.
+    pub fn ptr_eq(&self, other: &Node<L, NP>) -> bool {
+        match (self, other) {
+            (Node::Internal(int1), Node::Internal(int2)) =>
+                int1.nodes.as_ptr() == int2.nodes.as_ptr(),
+            _ => false,
+        }
+    }
+
     /// Concatenates two nodes of possibly different heights into a single balanced node.
+    ///
+    /// The shorter node is grafted onto the edge of the taller one, so only the nodes along that
+    /// edge, down to the height of the shorter node, are rebuilt.
+    ///
+    /// Time: O(1 + height difference)
     pub fn concat(node1: Node<L, NP>, node2: Node<L, NP>) -> Node<L, NP> {
         let (node1, maybe_node2) = Node::maybe_concat(node1, node2);
         if let Some(node2) = maybe_node2 {
@@ -158,6 +358,482 @@ impl<L: Leaf, NP: NodesPtr<L>> Node<L, NP> {
         }
     }
 
+    /// Append `leaf` to this tree. If the nodes along the right edge overflow (including the
+    /// root), they are split, so the tree may grow taller. The nodes shared with other trees are
+    /// left intact, so a clone of the tree taken beforehand still has the old leaves.
+    ///
+    /// Time: O(log n). For appending many leaves, convert this tree into a `TreeBuilder` using
+    /// `TreeBuilder::from_node`, which keeps the edges open and appends in amortized O(1) time.
+    pub fn push_back(&mut self, leaf: L) {
+        let node = self.never_take();
+        *self = Node::concat(node, Node::from_leaf(leaf));
+    }
+
+    /// Prepend `leaf` to this tree. See `push_back` for details.
+    pub fn push_front(&mut self, leaf: L) {
+        let node = self.never_take();
+        *self = Node::concat(Node::from_leaf(leaf), node);
+    }
+
+    /// Same as `push_back`, but `leaf` is first split into leaves within its size limit (see
+    /// `LeafLimit`).
+    pub fn push_back_limited(&mut self, leaf: L) where L: LeafLimit {
+        for part in leaf.split_to_limit() {
+            self.push_back(part);
+        }
+    }
+
+    /// Returns a tree consisting of `n` copies of the leaves of this tree (`None` if `n` is zero).
+    ///
+    /// The tree is built by repeated doubling, so the copies share their nodes, and only O(log n)
+    /// distinct nodes are created (per height). This makes it cheap to create huge uniform trees,
+    /// such as a buffer of blank lines, which get unshared as they are edited.
+    ///
+    /// Time: O(log^2 n)
+    pub fn repeat(&self, mut n: usize) -> Option<Node<L, NP>> {
+        let mut result: Option<Node<L, NP>> = None;
+        let mut power = self.clone(); // 2^k copies
+        while n > 0 {
+            if n & 1 == 1 {
+                result = Some(match result {
+                    Some(result) => Node::concat(result, power.clone()),
+                    None => power.clone(),
+                });
+            }
+            n >>= 1;
+            if n > 0 {
+                power = Node::concat(power.clone(), power);
+            }
+        }
+        result
+    }
+
+    /// Returns a tree with the leaves of this tree in reverse order. The leaves themselves are
+    /// cloned as is, and the info of each node is recomputed in the new order.
+    ///
+    /// Time: O(n)
+    pub fn reverse(&self) -> Node<L, NP> {
+        match *self {
+            Node::Internal(ref int) => {
+                let nodes = int.nodes.iter().rev().map(Node::reverse).collect();
+                Node::from_children(NP::new(nodes))
+            }
+            Node::Leaf(_) => self.clone(),
+            Node::Never(_) => unsafe { boom("Never!") },
+        }
+    }
+
+    /// Remove the leaves for which `pred` returns `true` among those starting within `range` (in
+    /// terms of a component of the path info `PI`). Returns the rest of the tree (or `None` if all
+    /// leaves were removed), along with the removed leaves in order.
+    ///
+    /// This is done in a single pass, where the subtrees outside `range` are kept as is, and the
+    /// remaining parts are joined together as they are collected. So it is much faster than
+    /// removing the leaves one at a time, when many leaves are removed.
+    ///
+    /// Time: O(m log n), where m is the number of leaves within `range`.
+    pub fn remove_where<PI, K, F>(self, range: Range<K>, mut pred: F) -> Removed<L, NP>
+        where PI: PathInfo<L::Info>, K: SubOrd<PI>, F: FnMut(&L) -> bool,
+    {
+        let mut removed = Vec::new();
+        let rest = self.remove_where_rec(PI::identity(), &range, &mut pred, &mut removed);
+        (rest, removed)
+    }
+
+    // Same as `remove_where`, for this subtree starting at `path_info`.
+    fn remove_where_rec<PI, K, F>(self, path_info: PI, range: &Range<K>, pred: &mut F,
+                                  removed: &mut Vec<L>) -> Option<Node<L, NP>>
+        where PI: PathInfo<L::Info>, K: SubOrd<PI>, F: FnMut(&L) -> bool,
+    {
+        let end = path_info.extend(self.info());
+        if range.end.sub_cmp(&path_info) != Ordering::Greater
+            || range.start.sub_cmp(&end) == Ordering::Greater {
+            return Some(self); // every leaf starts outside `range`
+        }
+        match self.into_children() {
+            Ok(mut nodes) => {
+                let mut rest = None;
+                let mut path_info = path_info;
+                for child in NP::make_mut(&mut nodes).drain(..) {
+                    let child_end = path_info.extend(child.info());
+                    let kept = child.remove_where_rec(path_info, range, pred, removed);
+                    rest = concat_opt(rest, kept);
+                    path_info = child_end;
+                }
+                rest
+            }
+            Err(node) => {
+                let in_range = range.start.sub_cmp(&path_info) != Ordering::Greater;
+                if in_range && pred(node.leaf().unwrap()) {
+                    removed.push(node.into_leaf().ok().unwrap());
+                    None
+                } else {
+                    Some(node)
+                }
+            }
+        }
+    }
+
+    /// Apply `edits`, which should be sorted by position and non-overlapping, in a single pass
+    /// over this tree. The positions of the edits refer to this tree before any of them are
+    /// applied. Returns the edited tree, or `None` if no leaves are left.
+    ///
+    /// The subtrees untouched by the edits are kept as is, and the subtrees entirely removed are
+    /// not visited. So this is much cheaper than applying many edits one at a time, which would
+    /// also require adjusting the positions of the later edits after each one.
+    ///
+    /// Time: O(k log n + m), where k is the number of edits, and m the number of leaves inserted
+    /// or removed.
+    pub fn apply_edits<PI, K>(self, edits: &[Edit<L, K>]) -> Option<Node<L, NP>>
+        where PI: PathInfo<L::Info>, K: SubOrd<PI>,
+    {
+        let mut state = EditState { edits, next: 0, active: None };
+        let mut out = None;
+        self.apply_edits_rec(PI::identity(), &mut state, &mut out);
+        while state.next < edits.len() {
+            Self::start_edit(&mut state, &mut out); // inserting at the end
+        }
+        out
+    }
+
+    // Same as `apply_edits`, for this subtree starting at `path_info`, appending the result
+    // to `out`.
+    fn apply_edits_rec<PI, K>(self, path_info: PI, state: &mut EditState<L, K>,
+                              out: &mut Option<Node<L, NP>>)
+        where PI: PathInfo<L::Info>, K: SubOrd<PI>,
+    {
+        let end = path_info.extend(self.info());
+        let active_end = state.active.and_then(|index| state.edits[index].end());
+        let next_after = match state.edits.get(state.next) {
+            Some(edit) => edit.start().sub_cmp(&end) == Ordering::Greater,
+            None => true,
+        };
+        if next_after {
+            match active_end {
+                Some(key) if key.sub_cmp(&end) == Ordering::Greater => return, // all removed
+                Some(key) if key.sub_cmp(&path_info) == Ordering::Greater => (),
+                _ => { // untouched
+                    *out = concat_opt(out.take(), Some(self));
+                    return;
+                }
+            }
+        }
+        match self.into_children() {
+            Ok(mut nodes) => {
+                let mut path_info = path_info;
+                for child in NP::make_mut(&mut nodes).drain(..) {
+                    let child_end = path_info.extend(child.info());
+                    child.apply_edits_rec(path_info, state, out);
+                    path_info = child_end;
+                }
+            }
+            Err(node) => {
+                loop {
+                    match state.edits.get(state.next) {
+                        Some(edit) if edit.start().sub_cmp(&path_info) != Ordering::Greater => (),
+                        _ => break,
+                    }
+                    Self::start_edit(state, out);
+                }
+                let active_end = state.active.and_then(|index| state.edits[index].end());
+                match active_end {
+                    Some(key) if key.sub_cmp(&path_info) == Ordering::Greater => (), // removed
+                    _ => *out = concat_opt(out.take(), Some(node)),
+                }
+            }
+        }
+    }
+
+    // Insert the leaves of the next edit, and make it the active edit.
+    fn start_edit<K>(state: &mut EditState<L, K>, out: &mut Option<Node<L, NP>>) {
+        let leaves = state.edits[state.next].leaves();
+        if !leaves.is_empty() {
+            *out = concat_opt(out.take(), Some(leaves.iter().cloned().collect()));
+        }
+        state.active = Some(state.next);
+        state.next += 1;
+    }
+
+    /// Split this tree into three: the leaves starting before `range`, those starting within
+    /// `range`, and those starting after it (in terms of a component of the path info `PI`). Each
+    /// part is `None` if it has no leaves.
+    ///
+    /// This is done in a single pass, which is cheaper than splitting the tree twice. The subtrees
+    /// entirely within a part are kept as is.
+    ///
+    /// Time: O(log n)
+    pub fn split3<PI, K>(self, range: Range<K>) -> Split3<L, NP>
+        where PI: PathInfo<L::Info>, K: SubOrd<PI>,
+    {
+        let [left, middle, right] = self.split3_rec(PI::identity(), &range);
+        (left, middle, right)
+    }
+
+    // Same as `split3`, for this subtree starting at `path_info`.
+    fn split3_rec<PI, K>(self, path_info: PI, range: &Range<K>) -> [Option<Node<L, NP>>; 3]
+        where PI: PathInfo<L::Info>, K: SubOrd<PI>,
+    {
+        // the part containing a leaf starting at `path_info`
+        let part = |path_info: &PI| {
+            if range.start.sub_cmp(path_info) == Ordering::Greater {
+                0
+            } else if range.end.sub_cmp(path_info) == Ordering::Greater {
+                1
+            } else {
+                2
+            }
+        };
+        let first = part(&path_info);
+        let mut parts = [None, None, None];
+        if first == part(&path_info.extend(self.info())) {
+            parts[first] = Some(self); // every leaf goes to the same part
+            return parts;
+        }
+        match self.into_children() {
+            Ok(mut nodes) => {
+                let mut path_info = path_info;
+                for child in NP::make_mut(&mut nodes).drain(..) {
+                    let child_end = path_info.extend(child.info());
+                    let child_parts = child.split3_rec(path_info, range);
+                    for (part, child_part) in parts.iter_mut().zip(child_parts) {
+                        *part = concat_opt(part.take(), child_part);
+                    }
+                    path_info = child_end;
+                }
+            }
+            Err(leaf) => parts[first] = Some(leaf),
+        }
+        parts
+    }
+
+    /// Returns the node reached by following `path` from this node, or `None` if an index in
+    /// `path` is out of bounds.
+    ///
+    /// Time: O(depth)
+    pub fn get_by_path(&self, path: &TreePath) -> Option<&Node<L, NP>> {
+        let mut node = self;
+        for &index in &path.0 {
+            node = node.children().get(index)?;
+        }
+        Some(node)
+    }
+
+    /// Replace the node reached by following `path` with `subtree`, which must have the same
+    /// height, and update the info of its ancestors. Returns the replaced node.
+    ///
+    /// The tree is balanced afterwards only if `subtree` has enough children to not be the root
+    /// (as with nodes taken from another tree); otherwise, see `Node::compact`.
+    ///
+    /// Time: O(depth)
+    pub fn replace_by_path(&mut self, path: &TreePath, subtree: Node<L, NP>)
+        -> Result<Node<L, NP>, TreeError>
+    {
+        self.replace_by_path_rec(&path.0, subtree)
+    }
+
+    // Same as `replace_by_path`, for the remaining `indices` of the path.
+    fn replace_by_path_rec(&mut self, indices: &[usize], subtree: Node<L, NP>)
+        -> Result<Node<L, NP>, TreeError>
+    {
+        let (&index, rest) = match indices.split_first() {
+            Some(split) => split,
+            None => {
+                if self.height() != subtree.height() {
+                    return Err(TreeError::HeightMismatch {
+                        expected: self.height(), found: subtree.height(),
+                    });
+                }
+                return Ok(mem::replace(self, subtree));
+            }
+        };
+        match *self {
+            Node::Internal(ref mut int) => {
+                let len = int.nodes.len();
+                if index >= len {
+                    return Err(TreeError::OutOfBounds { index, len });
+                }
+                let child = &mut NP::make_mut(&mut int.nodes)[index];
+                let replaced = child.replace_by_path_rec(rest, subtree)?;
+                let (info, _, stale) = InternalVal::summarize(&int.nodes);
+                int.info = info;
+                int.stale = stale;
+                Ok(replaced)
+            }
+            Node::Leaf(_) => Err(TreeError::OutOfBounds { index, len: 0 }),
+            Node::Never(_) => unsafe { boom("Never!") },
+        }
+    }
+
+    /// Split this tree into at most `k` parts of about equal weight, by an additive metric of the
+    /// info (as in `select`). Leaves are never split, so a part may be empty (and left out) if
+    /// there are fewer leaves than `k`, or if some leaves are much heavier than the rest.
+    ///
+    /// The `i`th part has the leaves starting at or after `i/k` of the total weight, and before
+    /// `(i+1)/k` of it. The subtrees entirely within a part are kept as is, making this suitable for
+    /// partitioning work among threads or shards.
+    ///
+    /// Time: O(k log n)
+    ///
+    /// Panics if `k` is zero.
+    pub fn split_even<F>(self, k: usize, metric: F) -> Vec<Node<L, NP>>
+        where F: Fn(&L::Info) -> usize,
+    {
+        assert!(k > 0, "cannot split into zero parts");
+        let total = cmp::max(metric(&self.info()), 1);
+        let part = |offset: usize| cmp::min(offset * k / total, k - 1);
+        let mut parts: Vec<_> = (0..k).map(|_| None).collect();
+        self.split_even_rec(0, &part, &metric, &mut parts);
+        parts.into_iter().flatten().collect()
+    }
+
+    // Same as `split_even`, for this subtree starting at `offset`, with `part` giving the part
+    // containing a leaf starting at an offset.
+    fn split_even_rec<P, F>(self, offset: usize, part: &P, metric: &F,
+                            parts: &mut [Option<Node<L, NP>>])
+        where P: Fn(usize) -> usize, F: Fn(&L::Info) -> usize,
+    {
+        let first = part(offset);
+        if first == part(offset + metric(&self.info())) {
+            parts[first] = concat_opt(parts[first].take(), Some(self)); // all in the same part
+            return;
+        }
+        match self.into_children() {
+            Ok(mut nodes) => {
+                let mut offset = offset;
+                for child in NP::make_mut(&mut nodes).drain(..) {
+                    let units = metric(&child.info());
+                    child.split_even_rec(offset, part, metric, parts);
+                    offset += units;
+                }
+            }
+            Err(node) => parts[first] = concat_opt(parts[first].take(), Some(node)),
+        }
+    }
+
+    /// Recompute the info of the leaves starting within `range` (in terms of a component of the
+    /// path info `PI`), and of their ancestors. This is needed after the leaves were mutated
+    /// externally, such as through interior mutability, which the tree cannot detect. `range` is
+    /// located using the info from before the recomputation.
+    ///
+    /// Time: O(m + log n), where m is the number of leaves within `range`.
+    pub fn recompute_info_range<PI, K>(&mut self, range: Range<K>)
+        where PI: PathInfo<L::Info>, K: SubOrd<PI>,
+    {
+        self.recompute_info_rec(PI::identity(), &range);
+    }
+
+    // Same as `recompute_info_range`, for this subtree starting at `path_info`.
+    fn recompute_info_rec<PI, K>(&mut self, path_info: PI, range: &Range<K>)
+        where PI: PathInfo<L::Info>, K: SubOrd<PI>,
+    {
+        let end = path_info.extend(self.info());
+        if range.end.sub_cmp(&path_info) != Ordering::Greater
+            || range.start.sub_cmp(&end) == Ordering::Greater {
+            return; // every leaf starts outside `range`
+        }
+        match *self {
+            Node::Internal(ref mut int) => {
+                let mut path_info = path_info;
+                for child in NP::make_mut(&mut int.nodes).iter_mut() {
+                    let child_end = path_info.extend(child.info());
+                    child.recompute_info_rec(path_info, range);
+                    path_info = child_end;
+                }
+                let (info, _, stale) = InternalVal::summarize(&int.nodes);
+                int.info = info;
+                int.stale = stale;
+            }
+            Node::Leaf(ref mut leaf) => {
+                if range.start.sub_cmp(&path_info) != Ordering::Greater {
+                    leaf.info = leaf.val.compute_info();
+                }
+            }
+            Node::Never(_) => unsafe { boom("Never!") },
+        }
+    }
+
+    /// Apply `f` to every leaf, which should return whether it changed the leaf. Returns whether
+    /// any leaf was changed.
+    ///
+    /// Only the nodes along the paths to changed leaves are copied and have their info recomputed;
+    /// all other subtrees are kept as is, and so remain shared with any other trees. Since `f` is
+    /// given a copy of each leaf, leaves should be cheap to clone.
+    ///
+    /// Time: O(n)
+    pub fn mutate_leaves<F>(&mut self, mut f: F) -> bool where F: FnMut(&mut L) -> bool {
+        match self.mutated(&mut f) {
+            Some(node) => {
+                *self = node;
+                true
+            }
+            None => false,
+        }
+    }
+
+    // Returns a copy of this subtree with `f` applied to its leaves, or `None` if no leaf was
+    // changed. See `mutate_leaves`.
+    fn mutated<F>(&self, f: &mut F) -> Option<Node<L, NP>> where F: FnMut(&mut L) -> bool {
+        match *self {
+            Node::Internal(ref int) => {
+                let mut nodes = None;
+                for (i, child) in int.nodes.iter().enumerate() {
+                    if let Some(child) = child.mutated(f) {
+                        let nodes = nodes.get_or_insert_with(|| int.nodes.clone());
+                        NP::make_mut(nodes)[i] = child;
+                    }
+                }
+                nodes.map(Node::from_children)
+            }
+            Node::Leaf(ref leaf) => {
+                let mut val = leaf.val.clone();
+                if f(&mut val) {
+                    Some(Node::from_leaf(val))
+                } else {
+                    None
+                }
+            }
+            Node::Never(_) => unsafe { boom("Never!") },
+        }
+    }
+
+    /// Remove the last leaf of this tree. Returns the rest of the tree (or `None` if it was the
+    /// only leaf), along with the leaf.
+    ///
+    /// Only the nodes along the right edge are rebuilt (and rebalanced), so the rest of the tree is
+    /// shared with the original.
+    ///
+    /// Time: O(log n)
+    pub fn pop_back(self) -> (Option<Node<L, NP>>, L) {
+        match self.into_children() {
+            Ok(mut nodes) => {
+                let (rest, leaf) = NP::make_mut(&mut nodes).pop().unwrap().pop_back();
+                (concat_opt(Node::from_children_opt(nodes), rest), leaf)
+            }
+            Err(node) => (None, node.into_leaf().ok().unwrap()),
+        }
+    }
+
+    /// Remove the first leaf of this tree. See `pop_back` for details.
+    pub fn pop_front(self) -> (Option<Node<L, NP>>, L) {
+        match self.into_children() {
+            Ok(mut nodes) => {
+                let (rest, leaf) = NP::make_mut(&mut nodes).remove(0).unwrap().pop_front();
+                (concat_opt(rest, Node::from_children_opt(nodes)), leaf)
+            }
+            Err(node) => (None, node.into_leaf().ok().unwrap()),
+        }
+    }
+
+    // Same as `from_children`, except that a single node is returned as is, and no nodes results
+    // in `None`.
+    fn from_children_opt(mut nodes: NP) -> Option<Node<L, NP>> {
+        match nodes.len() {
+            0 => None,
+            1 => NP::make_mut(&mut nodes).pop(),
+            _ => Some(Node::from_children(nodes)),
+        }
+    }
+
     /// Concatenates two nodes of possibly different heights into a single balanced node if the
     /// resulting height does not exceed the maximum height among the original nodes. Otherwise,
     /// splits them into two nodes of equal height.
@@ -179,11 +855,14 @@ impl<L: Leaf, NP: NodesPtr<L>> Node<L, NP> {
                         insert_maybe_split(children2, 0, node1)
                             .map(|split_children| Node::from_children(split_children))
                     } else {
-                        let newnode2 = Node::concat(node1, children2.remove(0).unwrap());
+                        // graft node1 onto the left spine; only the nodes along the spine down
+                        // to height h1 are rebuilt
+                        let newnode2 = Node::concat(node1, children2[0].never_take());
                         if newnode2.height() == h2 - 1 {
-                            insert_maybe_split(children2, 0, newnode2)
-                                .map(|split_children| Node::from_children(split_children))
+                            children2[0] = newnode2; // replace in-place
+                            None
                         } else {
+                            children2.remove(0);
                             debug_assert_eq!(newnode2.height(), h2);
                             let mut newchildren = newnode2.into_children_must();
                             let merged = {
@@ -221,12 +900,13 @@ impl<L: Leaf, NP: NodesPtr<L>> Node<L, NP> {
                         insert_maybe_split(children1, len1, node2)
                             .map(|split_children| Node::from_children(split_children))
                     } else {
-                        let newnode1 = Node::concat(children1.pop().unwrap(), node2);
-                        let len1 = len1 - 1;
+                        // graft node2 onto the right spine (see above)
+                        let newnode1 = Node::concat(children1[len1 - 1].never_take(), node2);
                         if newnode1.height() == h1 - 1 {
-                            insert_maybe_split(children1, len1, newnode1)
-                                .map(|split_children| Node::from_children(split_children))
+                            children1[len1 - 1] = newnode1; // replace in-place
+                            None
                         } else {
+                            children1.pop();
                             debug_assert_eq!(newnode1.height(), h1);
                             let mut newchildren = newnode1.into_children_must();
                             let merged = {
@@ -247,23 +927,21 @@ impl<L: Leaf, NP: NodesPtr<L>> Node<L, NP> {
     }
 }
 
-/// This implementation is for testing and benchmarking purposes. This panics if the iterator is
-/// empty. Use `CursorMut::collect` which not only avoids panicking, but is also more efficient.
+// Concatenate two possibly empty trees.
+pub(crate) fn concat_opt<L: Leaf, NP: NodesPtr<L>>(node1: Option<Node<L, NP>>,
+                                                   node2: Option<Node<L, NP>>)
+    -> Option<Node<L, NP>>
+{
+    match (node1, node2) {
+        (Some(node1), Some(node2)) => Some(Node::concat(node1, node2)),
+        (node1, node2) => node1.or(node2),
+    }
+}
+
+/// Panics if the iterator is empty. Use `TreeBuilder` or `CursorMut::collect` to avoid that.
 impl<L: Leaf, NP: NodesPtr<L>> FromIterator<L> for Node<L, NP> {
     fn from_iter<I: IntoIterator<Item=L>>(iter: I) -> Self {
-        let mut iter = iter.into_iter().map(Node::from_leaf);
-        let mut root = iter.next().expect("Iterator should not be empty.");
-
-        loop {
-            let nodes: ArrayVec<_> = iter.by_ref().take(NP::max_size()).collect();
-            if nodes.len() > 0 {
-                let node = Node::from_children(NP::new(nodes));
-                root = Node::concat(root, node);
-            } else {
-                break;
-            }
-        }
-        root
+        TreeBuilder::from_iter(iter).build().expect("Iterator should not be empty.")
     }
 }
 
@@ -286,6 +964,9 @@ fn balance_maybe_merge<L: Leaf, NP: NodesPtr<L>>(
 ) -> bool {
     let (len1, len2) = (children1.len(), children2.len());
     if len1 + len2 <= NP::max_size() {
+        instrument!(Merge {
+            height: children1.iter().chain(&*children2).next().map_or(1, |node| node.height() + 1)
+        });
         children1.extend(children2.drain(..));
         debug_assert_eq!(children1.len(), len1 + len2);
         true
@@ -321,6 +1002,7 @@ pub(crate) fn insert_maybe_split<L: Leaf, NP: NodesPtr<L>>(
         debug_assert!(_res.is_none());
         None
     } else {
+        instrument!(Split { height: nodes[0].height() + 1 });
         let extra = nodes.insert(idx, newnode).unwrap(); // like unwrap_err
         let n_left = balanced_split::<L, NP>(NP::max_size() + 1).0;
         let mut right: ArrayVec<_> = nodes.drain(n_left..).collect();
@@ -336,19 +1018,21 @@ pub enum TraverseError {
 }
 
 impl<L: Leaf, NP: NodesPtr<L>> InternalVal<L, NP> {
-    fn summarize(nodes: &NP) -> (L::Info, usize) {
-        let height = nodes[0].height() + 1;
+    fn summarize(nodes: &NP) -> (L::Info, u8, bool) {
+        let height = nodes[0].height() as u8 + 1;
         let mut info = nodes[0].info();
+        let mut stale = nodes[0].is_stale();
         for child in &nodes[1..] {
-            assert_eq!(height, child.height() + 1);
+            assert_eq!(height as usize, child.height() + 1);
             info = info.gather(child.info());
+            stale |= child.is_stale();
         }
-        (info, height)
+        (info, height, stale)
     }
 
     pub(crate) fn from_children(nodes: NP) -> Self {
-        let (info, height) = Self::summarize(&nodes);
-        InternalVal { info, height, nodes }
+        let (info, height, stale) = Self::summarize(&nodes);
+        InternalVal { info, height, stale, nodes }
     }
 
     pub(crate) fn info(&self) -> L::Info {
@@ -367,9 +1051,14 @@ impl<L: Leaf, NP: NodesPtr<L>> InternalVal<L, NP> {
         };
         if merged {
             self.info = merged_info;
+            self.stale |= other.stale;
         } else {
-            self.info = Self::summarize(&self.nodes).0;
-            other.info = Self::summarize(&other.nodes).0;
+            let (info, _, stale) = Self::summarize(&self.nodes);
+            self.info = info;
+            self.stale = stale;
+            let (info, _, stale) = Self::summarize(&other.nodes);
+            other.info = info;
+            other.stale = stale;
         }
         merged
     }
@@ -385,6 +1074,34 @@ impl<L: Leaf, NP: NodesPtr<L>> Node<L, NP> {
         }
     }
 
+    // Same as `from_children`, except that `info` is used as the info of `nodes` as is. If `stale`
+    // is true, `info` is assumed to be outdated, to be recomputed by `refresh_stale` later.
+    pub(crate) fn from_children_info(nodes: NP, info: L::Info, stale: bool) -> Node<L, NP> {
+        let height = nodes[0].height() as u8 + 1;
+        Node::Internal(InternalVal { info, height, stale, nodes })
+    }
+
+    pub(crate) fn is_stale(&self) -> bool {
+        match *self {
+            Node::Internal(ref int) => int.stale,
+            _ => false,
+        }
+    }
+
+    // Recompute the info of all stale nodes in this subtree. Only stale nodes are visited.
+    pub(crate) fn refresh_stale(&mut self) {
+        if let Node::Internal(ref mut int) = *self {
+            if int.stale {
+                for child in NP::make_mut(&mut int.nodes).iter_mut() {
+                    child.refresh_stale();
+                }
+                let (info, _, _) = InternalVal::summarize(&int.nodes);
+                int.info = info;
+                int.stale = false;
+            }
+        }
+    }
+
     pub(crate) fn internal_mut_must(&mut self) -> &mut InternalVal<L, NP> {
         match *self {
             Node::Internal(ref mut int) => int,
@@ -440,6 +1157,406 @@ impl<L: Leaf, NP: NodesPtr<L>> Node<L, NP> {
     }
 }
 
+impl<L: Leaf, NP: NodesPtr<L>> Node<L, NP> where L::Info: Count {
+    /// Returns the leaf containing the unit at `index` (as counted by `Count`), or `None` if
+    /// `index` is out of bounds. If each leaf counts as one unit, this is the `index`th leaf.
+    ///
+    /// Time: O(log n)
+    pub fn get(&self, index: usize) -> Option<&L> {
+        self.select(index, Count::count).map(|(leaf, _)| leaf)
+    }
+
+    /// Same as `get`, but returns an error describing the bounds if `index` is out of bounds.
+    pub fn try_get(&self, index: usize) -> Result<&L, TreeError> {
+        self.get(index).ok_or(TreeError::OutOfBounds { index, len: self.info().count() })
+    }
+}
+
+impl<L: Leaf, NP: NodesPtr<L>> Node<L, NP> {
+    /// Returns the leaf containing the position `key` (in terms of one of the components of the
+    /// path info `PI`), along with the path info at the start of that leaf. Returns `None` if
+    /// `key` is beyond the end of the tree.
+    ///
+    /// A position at the boundary of two leaves is contained in the latter, except at the end of
+    /// the tree, where it is contained in the last leaf.
+    ///
+    /// Time: O(log n)
+    pub fn locate<PI, K>(&self, key: K) -> Option<(PI, &L)>
+        where PI: PathInfo<L::Info>, K: SubOrd<PI>,
+    {
+        let mut path_info = PI::identity();
+        if key.sub_cmp(&path_info.extend(self.info())) == Ordering::Greater {
+            return None;
+        }
+        let mut node = self;
+        loop {
+            match *node {
+                Node::Internal(ref int) => {
+                    let last = int.nodes.len() - 1;
+                    for (i, child) in int.nodes.iter().enumerate() {
+                        let end = path_info.extend(child.info());
+                        if i == last || key.sub_cmp(&end) == Ordering::Less {
+                            node = child;
+                            break;
+                        }
+                        path_info = end;
+                    }
+                }
+                Node::Leaf(ref leaf) => return Some((path_info, &leaf.val)),
+                Node::Never(_) => unsafe { boom("Never!") },
+            }
+        }
+    }
+
+    /// Returns the leaf containing the `k`th unit (counting from zero) of an additive metric of the
+    /// info, such as the number of bytes or newlines, along with the number of units of the metric
+    /// before it within the leaf. Returns `None` if `k` is not less than the total.
+    ///
+    /// The metric must be additive, i.e. the units of an internal node must be the sum of those of
+    /// its children. Otherwise, `None` may be returned (and debug builds panic).
+    ///
+    /// Time: O(log n)
+    pub fn select<F>(&self, mut k: usize, metric: F) -> Option<(&L, usize)>
+        where F: Fn(&L::Info) -> usize,
+    {
+        if k >= metric(&self.info()) {
+            return None;
+        }
+        let mut node = self;
+        loop {
+            match *node {
+                Node::Internal(ref int) => {
+                    let child = int.nodes.iter().find(|child| {
+                        let units = metric(&child.info());
+                        if k < units {
+                            return true;
+                        }
+                        k -= units;
+                        false
+                    });
+                    debug_assert!(child.is_some(), "select: the metric is not additive");
+                    node = child?;
+                }
+                Node::Leaf(ref leaf) => return Some((&leaf.val, k)),
+                Node::Never(_) => unsafe { boom("Never!") },
+            }
+        }
+    }
+
+    /// Returns the first leaf boundary (as the path info at it) for which `pred` returns `false`,
+    /// like `slice::partition_point`. The boundaries include the start and the end of the tree,
+    /// and the end is returned if `pred` holds for all of them.
+    ///
+    /// `pred` should hold for a prefix of the boundaries and not for the rest. Then only O(log n)
+    /// boundaries need to be checked, since the boundaries within a subtree are skipped when
+    /// `pred` holds at its end.
+    ///
+    /// Time: O(log n)
+    pub fn partition_point<PI, F>(&self, mut pred: F) -> PI
+        where PI: PathInfo<L::Info>, F: FnMut(PI) -> bool,
+    {
+        let mut path_info = PI::identity();
+        if !pred(path_info) {
+            return path_info;
+        }
+        let mut node = self;
+        while !node.is_leaf() {
+            let mut found = false;
+            for child in node.children() {
+                let end = path_info.extend(child.info());
+                if !pred(end) {
+                    node = child;
+                    found = true;
+                    break;
+                }
+                path_info = end;
+            }
+            if !found {
+                return path_info; // `pred` holds at the end
+            }
+        }
+        path_info.extend(node.info())
+    }
+
+    /// Returns the first leaf for which `pred` holds at its end (the path info including the leaf),
+    /// along with the path info at its start. Returns `None` if `pred` does not hold at the end of
+    /// the tree.
+    ///
+    /// This generalizes locating a leaf by a `SubOrd` key to computed conditions, such as the first
+    /// line at which the rendered width exceeds the viewport. `pred` should not hold for a prefix
+    /// of the leaves and hold for the rest, so that subtrees ending before the flip are skipped.
+    ///
+    /// Time: O(log n)
+    pub fn find_boundary<PI, F>(&self, mut pred: F) -> Option<(PI, &L)>
+        where PI: PathInfo<L::Info>, F: FnMut(PI) -> bool,
+    {
+        let mut path_info = PI::identity();
+        if !pred(path_info.extend(self.info())) {
+            return None;
+        }
+        let mut node = self;
+        loop {
+            match *node {
+                Node::Internal(ref int) => {
+                    let mut found = None;
+                    for child in int.nodes.iter() {
+                        let end = path_info.extend(child.info());
+                        if pred(end) {
+                            found = Some(child);
+                            break;
+                        }
+                        path_info = end;
+                    }
+                    node = found?; // only if `pred` is not monotone
+                }
+                Node::Leaf(ref leaf) => return Some((path_info, &leaf.val)),
+                Node::Never(_) => unsafe { boom("Never!") },
+            }
+        }
+    }
+
+    /// Convert the position `key` in one metric to a position in another, in a single descent.
+    /// `f` is called with the path info at the start of the leaf containing `key` (see `locate`)
+    /// and the leaf, and should complete the conversion within the leaf (if needed).
+    ///
+    /// For example, with a path info having byte and line offsets, the line containing a byte
+    /// offset is the line offset of the leaf plus the number of newlines in the leaf before it.
+    pub fn convert<PI, K, T, F>(&self, key: K, f: F) -> Option<T>
+        where PI: PathInfo<L::Info>, K: SubOrd<PI>, F: FnOnce(PI, &L) -> T,
+    {
+        self.locate(key).map(|(path_info, leaf)| f(path_info, leaf))
+    }
+}
+
+impl<L: LeafSplit, NP: NodesPtr<L>> Node<L, NP> {
+    /// Returns the info gathered from the units within `range` of an additive metric of the info
+    /// (as in `select`), or `None` if no units are within `range`. The units of the metric should
+    /// be the offsets used by `LeafSplit::split_off`, such as bytes for text.
+    ///
+    /// The leaves at the boundaries of `range` are not rounded to: a copy of each is split, so that
+    /// only its part within `range` contributes. For example, this gives the number of lines and
+    /// chars over an arbitrary byte range.
+    ///
+    /// Time: O(log n + s), where s is the size of the leaves at the boundaries.
+    pub fn range_info<F>(&self, range: Range<usize>, metric: F) -> Option<L::Info>
+        where F: Fn(&L::Info) -> usize,
+    {
+        let mut info = None;
+        self.range_info_rec(&range, &metric, &mut info);
+        info
+    }
+
+    // Same as `range_info`, with `range` relative to the start of this subtree, gathering the
+    // info into `acc`.
+    fn range_info_rec<F>(&self, range: &Range<usize>, metric: &F, acc: &mut Option<L::Info>)
+        where F: Fn(&L::Info) -> usize,
+    {
+        let units = metric(&self.info());
+        if range.start >= cmp::min(range.end, units) {
+            return; // no units within `range`
+        }
+        let info = if range.start == 0 && range.end >= units {
+            self.info()
+        } else {
+            match *self {
+                Node::Internal(ref int) => {
+                    let mut offset = 0;
+                    for child in int.nodes.iter() {
+                        if offset >= range.end {
+                            break;
+                        }
+                        let child_range = range.start.saturating_sub(offset)..range.end - offset;
+                        child.range_info_rec(&child_range, metric, acc);
+                        offset += metric(&child.info());
+                    }
+                    return;
+                }
+                Node::Leaf(ref leaf) => {
+                    let mut val = leaf.val.clone();
+                    if range.end < units {
+                        val.split_off(range.end);
+                    }
+                    if range.start > 0 {
+                        val = val.split_off(range.start);
+                    }
+                    val.compute_info()
+                }
+                Node::Never(_) => unsafe { boom("Never!") },
+            }
+        };
+        *acc = Some(match acc.take() {
+            Some(acc) => acc.gather(info),
+            None => info,
+        });
+    }
+}
+
+/// Same as `Node::get`, but panics if `index` is out of bounds.
+impl<L: Leaf, NP: NodesPtr<L>> Index<usize> for Node<L, NP> where L::Info: Count {
+    type Output = L;
+
+    fn index(&self, index: usize) -> &L {
+        match self.get(index) {
+            Some(leaf) => leaf,
+            None => panic!("index {} out of bounds (count: {})", index, self.info().count()),
+        }
+    }
+}
+
+impl<L: Leaf, NP: NodesPtr<L>> Node<L, NP> {
+    /// Returns an object that displays this tree, one node per line, indented by depth. Each line
+    /// shows the height and the number of children of a node, along with its info.
+    pub fn display_tree(&self) -> TreeDisplay<'_, L, NP> {
+        TreeDisplay { root: self }
+    }
+
+    // Write the tree at this node (at `depth`), with leaves written using `write_leaf`.
+    fn write_tree<F>(&self, f: &mut fmt::Formatter, depth: usize, write_leaf: &F) -> fmt::Result
+        where L::Info: fmt::Debug, F: Fn(&mut fmt::Formatter, &L) -> fmt::Result,
+    {
+        write!(f, "{:indent$}", "", indent = depth * 2)?;
+        match *self {
+            Node::Internal(ref int) => {
+                writeln!(f, "[h={} n={}] {:?}", int.height, int.nodes.len(), int.info)?;
+                for child in int.nodes.iter() {
+                    child.write_tree(f, depth + 1, write_leaf)?;
+                }
+                Ok(())
+            }
+            Node::Leaf(ref leaf) => {
+                write!(f, "{:?}", leaf.info)?;
+                write_leaf(f, &leaf.val)?;
+                writeln!(f)
+            }
+            Node::Never(_) => writeln!(f, "!"),
+        }
+    }
+}
+
+/// Displays a tree as described in `Node::display_tree`.
+pub struct TreeDisplay<'a, L: Leaf + 'a, NP: NodesPtr<L> + 'a> {
+    root: &'a Node<L, NP>,
+}
+
+impl<'a, L, NP> fmt::Display for TreeDisplay<'a, L, NP>
+    where L: Leaf, L::Info: fmt::Debug, NP: NodesPtr<L>,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.root.write_tree(f, 0, &|_, _| Ok(()))
+    }
+}
+
+/// Same as `display_tree`, but with leaves also written after their info.
+impl<L, NP> fmt::Debug for Node<L, NP>
+    where L: Leaf + fmt::Debug, L::Info: fmt::Debug, NP: NodesPtr<L>,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.write_tree(f, 0, &|f, leaf| write!(f, " {:?}", leaf))
+    }
+}
+
+// Compare the leaves of two trees in order using `cmp`, until it returns anything other than
+// `Some(Equal)`. Subtrees shared by both trees at the same position are skipped without
+// comparing their leaves. A tree that is a prefix of another is less than that.
+fn cmp_leaves<L, NP, F>(node1: &Node<L, NP>, node2: &Node<L, NP>, mut cmp: F) -> Option<Ordering>
+    where L: Leaf, NP: NodesPtr<L>, F: FnMut(&L, &L) -> Option<Ordering>,
+{
+    // the nodes yet to be compared, in reverse order
+    let mut stack1 = vec![node1];
+    let mut stack2 = vec![node2];
+    loop {
+        let (n1, n2) = match (stack1.last(), stack2.last()) {
+            (Some(&n1), Some(&n2)) => (n1, n2),
+            (Some(_), None) => return Some(Ordering::Greater),
+            (None, Some(_)) => return Some(Ordering::Less),
+            (None, None) => return Some(Ordering::Equal),
+        };
+        if n1.ptr_eq(n2) {
+            stack1.pop();
+            stack2.pop();
+            continue;
+        }
+        match (n1.leaf(), n2.leaf()) {
+            (Some(leaf1), Some(leaf2)) => {
+                match cmp(leaf1, leaf2) {
+                    Some(Ordering::Equal) => (),
+                    ordering => return ordering,
+                }
+                stack1.pop();
+                stack2.pop();
+            }
+            // expand the taller node, to reach shared subtrees of the same height
+            _ if n1.height() >= n2.height() => {
+                stack1.pop();
+                stack1.extend(n1.children().iter().rev());
+            }
+            _ => {
+                stack2.pop();
+                stack2.extend(n2.children().iter().rev());
+            }
+        }
+    }
+}
+
+/// Trees are equal if their sequences of leaves are equal.
+impl<L: Leaf + PartialEq, NP: NodesPtr<L>> PartialEq for Node<L, NP> {
+    fn eq(&self, other: &Self) -> bool {
+        let cmp = |leaf1: &L, leaf2: &L| if leaf1 == leaf2 { Some(Ordering::Equal) } else { None };
+        cmp_leaves(self, other, cmp) == Some(Ordering::Equal)
+    }
+}
+
+impl<L: Leaf + Eq, NP: NodesPtr<L>> Eq for Node<L, NP> {}
+
+/// Hashes the sequence of leaves (consistent with `PartialEq`), so that trees with the same leaves
+/// hash equally regardless of their shape.
+impl<L: Leaf + Hash, NP: NodesPtr<L>> Hash for Node<L, NP> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        let mut len = 0;
+        for leaf in self.leaves() {
+            leaf.hash(state);
+            len += 1;
+        }
+        state.write_usize(len);
+    }
+}
+
+/// Trees are ordered lexicographically by their sequences of leaves.
+impl<L: Leaf + PartialOrd, NP: NodesPtr<L>> PartialOrd for Node<L, NP> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        cmp_leaves(self, other, L::partial_cmp)
+    }
+}
+
+impl<L: Leaf + Ord, NP: NodesPtr<L>> Ord for Node<L, NP> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        cmp_leaves(self, other, |leaf1, leaf2| Some(leaf1.cmp(leaf2))).unwrap()
+    }
+}
+
+/// A tree can be a leaf of an outer tree, with the info gathered from its leaves as its info. This
+/// allows two-level structures, such as a tree of documents, with aggregate metrics over all of
+/// them.
+impl<L: Leaf, NP: NodesPtr<L>> Leaf for Node<L, NP> {
+    type Info = L::Info;
+
+    fn compute_info(&self) -> L::Info {
+        self.info()
+    }
+}
+
+impl<L: Leaf, NP: NodesPtr<L>, NP2: NodesPtr<Node<L, NP>>> Node<Node<L, NP>, NP2> {
+    /// Concatenate the inner trees (the leaves of this tree) in order into a single tree. The inner
+    /// trees are shared with this tree, not copied.
+    ///
+    /// Time: O(m log n), where m is the number of inner trees.
+    pub fn flatten(&self) -> Node<L, NP> {
+        let mut inner = self.leaves().cloned();
+        let first = inner.next().unwrap();
+        inner.fold(first, Node::concat)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use ::test_help::*;
@@ -459,6 +1576,43 @@ mod tests {
         assert_eq!(node.height(), 2);
     }
 
+    #[test]
+    fn index() {
+        let node: NodeRc<_> = (0..300).map(ListLeaf).collect();
+        for i in 0..300 {
+            assert_eq!(node[i], ListLeaf(i));
+        }
+        assert_eq!(node.get(300), None);
+    }
+
+    #[test]
+    fn compare() {
+        let node1: NodeRc<_> = (0..300).map(ListLeaf).collect();
+        let node2 = NodeRc::concat((0..10).map(ListLeaf).collect(),
+                                   (10..300).map(ListLeaf).collect());
+        assert_eq!(node1, node2);
+        assert_eq!(node1, node1.clone());
+        let mut cursor_mut = CursorMutT::from_node(node1.clone());
+        cursor_mut.last_leaf();
+        cursor_mut.leaf_update(|leaf| leaf.0 = 1000);
+        let node3 = cursor_mut.into_root().unwrap();
+        assert!(node1 != node3);
+        assert!(node1 < node3 && node2 < node3);
+        assert!(NodeRc::from_leaf(ListLeaf(0)) < node1);
+        assert!(NodeRc::from_leaf(ListLeaf(1)) > node1);
+    }
+
+    #[test]
+    fn display_tree() {
+        let node: NodeRc<_> = (0..17).map(ListLeaf).collect();
+        let lines: Vec<_> = node.display_tree().to_string().lines().map(String::from).collect();
+        assert_eq!(lines.len(), 1 + 2 + 17);
+        assert_eq!(lines[0], "[h=2 n=2] ListInfo { count: 17, sum: 136 }");
+        assert!(lines[1].starts_with("  [h=1 n="));
+        assert_eq!(lines[2], "    ListInfo { count: 1, sum: 0 }");
+        assert!(format!("{:?}", node).contains("    ListInfo { count: 1, sum: 0 } ListLeaf(0)\n"));
+    }
+
     #[test]
     fn concat() {
         use super::{NodesPtr, Rc16};
@@ -474,5 +1628,415 @@ mod tests {
         }
     }
 
+    #[test]
+    fn pooled() {
+        use cursor::CursorMut;
+        use cursor::conf::Pool33M;
+        for round in 0..4 {
+            let mut cursor_mut: CursorMut<_, (), Pool33M> = (0..500).map(ListLeaf).collect();
+            cursor_mut.reset();
+            for _ in 0..round * 100 {
+                cursor_mut.remove_leaf();
+            }
+            let root = cursor_mut.into_root().unwrap();
+            assert_eq!(root.info(), ListInfo { count: 500 - round * 100,
+                                               sum: (round * 100..500).sum() });
+        }
+    }
+
+    #[test]
+    fn node_size() {
+        use std::mem::size_of;
+        use super::{LeafVal, NeverVal, Rc16};
+        use traits::Leaf;
+
+        // the layout of `Node` with the height of internal nodes stored as `usize`
+        #[allow(dead_code)]
+        enum UsizeHeight<L: Leaf, NP> {
+            Internal { info: L::Info, height: usize, stale: bool, nodes: NP },
+            Leaf(LeafVal<L>),
+            Never(NeverVal),
+        }
+        fn sizes<L: Leaf>() -> (usize, usize) {
+            (size_of::<NodeRc<L>>(), size_of::<UsizeHeight<L, Rc16<L>>>())
+        }
+
+        let (node, usize_height) = sizes::<ListLeaf>();
+        assert!(node < usize_height, "{} >= {}", node, usize_height);
+        // may be bound by the size of leaf nodes, which are unaffected
+        let (node, usize_height) = sizes::<::piece::Piece>();
+        assert!(node <= usize_height, "{} > {}", node, usize_height);
+    }
+
+    #[test]
+    fn concat_uneven() {
+        let huge: NodeRc<_> = (1..5000).map(ListLeaf).collect();
+        let small = NodeRc::from_leaf(ListLeaf(0));
+        let node = NodeRc::concat(small.clone(), huge.clone());
+        assert_balanced(&node);
+        assert_eq!(node.info(), ListInfo { count: 5000, sum: 4999 * 5000 / 2 });
+        assert_eq!(CursorT::new(&node).first_leaf(), Some(&ListLeaf(0)));
+        // all but the left spine is shared with `huge`
+        let shared = node.children()[1..].iter().zip(&huge.children()[1..])
+                         .all(|(n1, n2)| n1.ptr_eq(n2));
+        assert!(shared);
+
+        let node = NodeRc::concat(huge, small);
+        assert_balanced(&node);
+        assert_eq!(CursorT::new(&node).last_leaf(), Some(&ListLeaf(0)));
+
+        for len in 1..300 {
+            let left: NodeRc<_> = (0..len).map(ListLeaf).collect();
+            let right: NodeRc<_> = (len..300).chain(Some(300)).map(ListLeaf).collect();
+            let node = NodeRc::concat(left, right);
+            assert_balanced(&node);
+            let leaves: Vec<_> = CursorT::new(&node).into_iter().map(|l| l.0).collect();
+            assert_eq!(leaves, (0..301).collect::<Vec<_>>());
+        }
+    }
+
+    #[test]
+    fn downgrade() {
+        let node: NodeRc<_> = (0..300).map(ListLeaf).collect();
+        let weak = node.downgrade();
+        let leaf = NodeRc::from_leaf(ListLeaf(1)).downgrade();
+        assert!(weak.upgrade().unwrap().ptr_eq(&node));
+        let copy = node.clone();
+        drop(node);
+        assert!(weak.upgrade().is_some()); // still kept alive by `copy`
+        drop(copy);
+        assert!(weak.upgrade().is_none());
+        assert_eq!(leaf.upgrade().unwrap().leaf(), Some(&ListLeaf(1)));
+    }
+
+    #[test]
+    fn convert() {
+        let node: NodeRc<_> = (0..100).map(ListLeaf).collect();
+        // leaf `i` spans the runs `i*(i-1)/2..i*(i+1)/2`
+        let index_of = |run| node.convert(ListRun(run), |path: ListPath, leaf| {
+            assert!(path.run <= run && run <= path.run + leaf.0);
+            path.index
+        });
+        assert_eq!(index_of(0), Some(1));
+        assert_eq!(index_of(45), Some(10));
+        assert_eq!(index_of(54), Some(10));
+        assert_eq!(index_of(55), Some(11));
+        assert_eq!(index_of(4950), Some(99));
+        assert_eq!(index_of(4951), None);
+        let path = ListPath { index: 50, run: 1225 };
+        assert_eq!(node.locate(ListIndex(50)), Some((path, &ListLeaf(50))));
+    }
+
+    #[test]
+    fn pop() {
+        let mut node: NodeRc<_> = (0..300).map(ListLeaf).collect();
+        let original = node.clone();
+        for i in 0..150 {
+            let (rest, leaf) = node.pop_back();
+            assert_eq!(leaf, ListLeaf(299 - i));
+            let (rest, leaf) = rest.unwrap().pop_front();
+            assert_eq!(leaf, ListLeaf(i));
+            node = match rest {
+                Some(rest) => rest,
+                None => break,
+            };
+            assert_balanced(&node);
+            assert!(node.leaves().map(|leaf| leaf.0).eq(i + 1..299 - i));
+        }
+        assert!(original.leaves().map(|leaf| leaf.0).eq(0..300));
+    }
+
+    #[test]
+    fn push() {
+        let mut node = NodeRc::from_leaf(ListLeaf(0));
+        let mut versions = vec![node.clone()];
+        for i in 1..300 {
+            if i % 2 == 0 {
+                node.push_back(ListLeaf(i));
+            } else {
+                node.push_front(ListLeaf(i));
+            }
+            assert_balanced(&node);
+            versions.push(node.clone());
+        }
+        assert_eq!(node.height(), 3);
+        let expected = |n| (1..n).rev().filter(|i| i % 2 == 1).chain((0..n).filter(|i| i % 2 == 0));
+        for (n, version) in versions.iter().enumerate() {
+            assert!(version.leaves().map(|leaf| leaf.0).eq(expected(n + 1)));
+        }
+    }
+
+    #[test]
+    fn repeat() {
+        use std::collections::HashSet;
+
+        let node: NodeRc<_> = (0..3).map(ListLeaf).collect();
+        assert!(node.repeat(0).is_none());
+        let repeated = node.repeat(1_000_000).unwrap();
+        assert_eq!(repeated.info(), ListInfo { count: 3_000_000, sum: 3_000_000 });
+        assert_balanced(&repeated);
+
+        fn distinct(node: &NodeRc<ListLeaf>, ids: &mut HashSet<*const NodeRc<ListLeaf>>) {
+            if !node.is_leaf() && ids.insert(node.children().as_ptr()) {
+                node.children().iter().for_each(|child| distinct(child, ids));
+            }
+        }
+        let mut ids = HashSet::new();
+        distinct(&repeated, &mut ids);
+        assert!(ids.len() < 200);
+        let small = node.repeat(5).unwrap();
+        assert!(small.leaves().map(|leaf| leaf.0).eq((0..15).map(|i| i % 3)));
+    }
+
+    #[test]
+    fn reverse() {
+        let node: NodeRc<_> = (0..300).map(ListLeaf).collect();
+        let reversed = node.reverse();
+        assert_balanced(&reversed);
+        assert_eq!(reversed.info(), node.info());
+        assert!(reversed.leaves().map(|leaf| leaf.0).eq((0..300).rev()));
+        assert_eq!(reversed.reverse(), node);
+    }
+
+    #[test]
+    fn remove_where() {
+        let node: NodeRc<_> = (0..1000).map(ListLeaf).collect();
+        let (rest, removed) = node.clone().remove_where(ListIndex(100)..ListIndex(900),
+                                                        |leaf| leaf.0 % 3 != 0);
+        let rest = rest.unwrap();
+        assert_balanced(&rest);
+        let kept = |i: &usize| *i < 100 || *i >= 900 || i.is_multiple_of(3);
+        assert!(rest.leaves().map(|leaf| leaf.0).eq((0..1000).filter(kept)));
+        assert!(removed.into_iter().map(|leaf| leaf.0).eq((0..1000).filter(|i| !kept(i))));
+        // the subtrees outside the range are kept as is
+        assert!(rest.children()[0].children()[0].ptr_eq(&node.children()[0].children()[0]));
+
+        let (rest, removed) = node.remove_where(ListIndex(0)..ListIndex(1000), |_| true);
+        assert!(rest.is_none());
+        assert_eq!(removed.len(), 1000);
+    }
+
+    #[test]
+    fn apply_edits() {
+        use super::Edit;
+
+        let node: NodeRc<_> = (0..1000).map(ListLeaf).collect();
+        let leaves = |range: ::std::ops::Range<usize>| range.map(ListLeaf).collect::<Vec<_>>();
+        let edits = [
+            Edit::Insert(ListIndex(0), leaves(1000..1002)),
+            Edit::Replace(ListIndex(10)..ListIndex(20), leaves(2000..2005)),
+            Edit::Delete(ListIndex(100)..ListIndex(900)),
+            Edit::Insert(ListIndex(950), leaves(3000..3001)),
+            Edit::Delete(ListIndex(990)..ListIndex(995)),
+            Edit::Insert(ListIndex(1000), leaves(4000..4003)),
+        ];
+        let edited = node.clone().apply_edits(&edits).unwrap();
+        assert_balanced(&edited);
+        let expected = (1000..1002).chain(0..10).chain(2000..2005).chain(20..100)
+                                   .chain(900..950).chain(3000..3001).chain(950..990)
+                                   .chain(995..1000).chain(4000..4003);
+        assert!(edited.leaves().map(|leaf| leaf.0).eq(expected));
+        assert!(edited.children()[0].children()[0].children()[0].is_leaf());
+
+        let all = [Edit::Delete(ListIndex(0)..ListIndex(1000))];
+        assert!(node.apply_edits(&all).is_none());
+    }
+
+    #[test]
+    fn split3() {
+        let node: NodeRc<_> = (0..1000).map(ListLeaf).collect();
+        let (left, middle, right) = node.clone().split3(ListIndex(100)..ListIndex(300));
+        let (left, middle, right) = (left.unwrap(), middle.unwrap(), right.unwrap());
+        for (part, range) in [(&left, 0..100), (&middle, 100..300), (&right, 300..1000)] {
+            assert_balanced(part);
+            assert!(part.leaves().map(|leaf| leaf.0).eq(range));
+        }
+        assert!(right.children().last().unwrap().ptr_eq(node.children().last().unwrap()));
+
+        let (left, middle, right) = node.split3(ListIndex(0)..ListIndex(0));
+        assert!(left.is_none() && middle.is_none());
+        assert_eq!(right.unwrap().info().count, 1000);
+    }
+
+    #[test]
+    fn first_last_leaf() {
+        let node: NodeRc<_> = (0..1000).map(ListLeaf).collect();
+        assert_eq!((node.first_leaf(), node.last_leaf()), (&ListLeaf(0), &ListLeaf(999)));
+        let leaf = NodeRc::from_leaf(ListLeaf(7));
+        assert_eq!((leaf.first_leaf(), leaf.last_leaf()), (&ListLeaf(7), &ListLeaf(7)));
+    }
+
+    #[test]
+    fn hash() {
+        use std::collections::hash_map::DefaultHasher;
+        use std::hash::{Hash, Hasher};
+
+        let hash = |node: &NodeRc<ListLeaf>| {
+            let mut hasher = DefaultHasher::new();
+            node.hash(&mut hasher);
+            hasher.finish()
+        };
+        let node1: NodeRc<_> = (0..500).map(ListLeaf).collect();
+        let mut node2 = NodeRc::from_leaf(ListLeaf(0));
+        for i in 1..500 {
+            node2.push_back(ListLeaf(i));
+        }
+        assert!(node1 == node2 && hash(&node1) == hash(&node2));
+        node2.push_back(ListLeaf(500));
+        assert_ne!(hash(&node1), hash(&node2));
+    }
+
+    #[test]
+    fn partition_point() {
+        let node: NodeRc<_> = (0..300).map(ListLeaf).collect();
+        let path = node.partition_point(|path: ListPath| path.run < 1000);
+        assert_eq!(path, ListPath { index: 46, run: 1035 });
+        assert_eq!(node.partition_point(|path: ListPath| path.index < 1000).index, 300);
+        assert_eq!(node.partition_point(|_: ListPath| false).index, 0);
+        let mut calls = 0;
+        node.partition_point(|path: ListPath| { calls += 1; path.index <= 150 });
+        assert!(calls < 3 * 16);
+    }
+
+    #[test]
+    fn find_boundary() {
+        let node: NodeRc<_> = (0..300).map(ListLeaf).collect();
+        let (path, leaf) = node.find_boundary(|path: ListPath| path.run > 1040).unwrap();
+        assert_eq!((path, leaf), (ListPath { index: 46, run: 1035 }, &ListLeaf(46)));
+        let (path, leaf) = node.find_boundary(|path: ListPath| path.index >= 1).unwrap();
+        assert_eq!((path.index, leaf), (0, &ListLeaf(0)));
+        assert!(node.find_boundary(|path: ListPath| path.index > 300).is_none());
+    }
+
+    #[test]
+    fn select() {
+        let node: NodeRc<_> = (0..300).map(ListLeaf).collect();
+        let run = |info: &ListInfo| info.sum;
+        assert_eq!(node.select(0, run), Some((&ListLeaf(1), 0)));
+        assert_eq!(node.select(1040, run), Some((&ListLeaf(46), 5)));
+        assert_eq!(node.select(299 * 300 / 2 - 1, run), Some((&ListLeaf(299), 298)));
+        assert_eq!(node.select(299 * 300 / 2, run), None);
+    }
+
+    #[test]
+    #[cfg_attr(debug_assertions, should_panic(expected = "not additive"))]
+    fn select_non_additive() {
+        let node: NodeRc<_> = (0..300).map(ListLeaf).collect();
+        assert_eq!(node.select(0, |info: &ListInfo| (info.count == 300) as usize), None);
+    }
+
+    #[test]
+    fn tree_path() {
+        use super::TreePath;
+        use cursor::Cursor;
+        use error::TreeError;
+
+        let mut node: NodeRc<_> = (0..1000).map(ListLeaf).collect();
+        let (path, subtree) = {
+            let mut cursor = Cursor::<_, ListPath>::new(&node);
+            cursor.goto_min(ListIndex(300));
+            cursor.ascend();
+            (cursor.tree_path(), cursor.current().clone())
+        };
+        assert!(node.get_by_path(&path).unwrap().leaves().eq(subtree.leaves()));
+        assert!(node.get_by_path(&TreePath(vec![99])).is_none());
+
+        let other: NodeRc<_> = (1000..2000).map(ListLeaf).collect();
+        let replacement = other.get_by_path(&path).unwrap().clone();
+        let added = replacement.info().sum - subtree.info().sum;
+        let replaced = node.replace_by_path(&path, replacement).unwrap();
+        assert!(replaced.leaves().eq(subtree.leaves()));
+        assert_eq!(node.info().sum, (0..1000).sum::<usize>() + added);
+        assert_balanced(&node);
+        assert_eq!(node.replace_by_path(&path, NodeRc::from_leaf(ListLeaf(0))).unwrap_err(),
+                   TreeError::HeightMismatch { expected: 1, found: 0 });
+    }
+
+    #[test]
+    fn mutate_leaves() {
+        let original: NodeRc<_> = (0..1000).map(ListLeaf).collect();
+        let mut node = original.clone();
+        assert!(!node.mutate_leaves(|_| false));
+        assert_eq!(node.children().as_ptr(), original.children().as_ptr());
+
+        assert!(node.mutate_leaves(|leaf| {
+            let changed = leaf.0 == 5;
+            if changed {
+                leaf.0 = 1005;
+            }
+            changed
+        }));
+        assert_eq!(node.info().sum, (0..1000).sum::<usize>() + 1000);
+        assert_eq!(node.get(5), Some(&ListLeaf(1005)));
+        assert_balanced(&node);
+        let shared = node.children().iter().zip(original.children())
+                         .filter(|&(a, b)| a.children().as_ptr() == b.children().as_ptr());
+        assert_eq!(shared.count(), original.children().len() - 1);
+    }
+
+    #[test]
+    fn split_even() {
+        let node: NodeRc<_> = (0..1000).map(ListLeaf).collect();
+        let parts = node.clone().split_even(7, |info| info.count);
+        assert_eq!(parts.len(), 7);
+        for part in &parts {
+            assert_balanced(part);
+            assert!(part.info().count >= 1000 / 7 && part.info().count <= 1000 / 7 + 1);
+        }
+        assert!(parts.iter().flat_map(|part| part.leaves()).eq(node.leaves()));
+
+        let parts = node.clone().split_even(4, |info| info.sum);
+        let quarter = (0..1000).sum::<usize>() / 4;
+        assert!(parts.iter().all(|part| part.info().sum.abs_diff(quarter) < 1000));
+        assert_eq!(NodeRc::from_leaf(ListLeaf(0)).split_even(3, |info| info.sum).len(), 1);
+    }
+
+    #[test]
+    fn nested() {
+        let docs: Vec<NodeRc<_>> = (0..10).map(|i| (i * 100..i * 100 + 100).map(ListLeaf).collect())
+                                          .collect();
+        let mut outer: NodeRc<_> = docs.iter().cloned().collect();
+        assert_eq!(outer.info(), ListInfo { count: 1000, sum: (0..1000).sum() });
+        assert_eq!(outer.get(250).map(|doc| doc.first_leaf()), Some(&ListLeaf(200)));
+
+        outer.push_back((1000..1005).map(ListLeaf).collect());
+        let flat = outer.flatten();
+        assert_balanced(&flat);
+        assert!(flat.leaves().map(|leaf| leaf.0).eq(0..1005));
+        assert_eq!(flat.info(), outer.info());
+    }
+
+    #[test]
+    fn recompute_info_range() {
+        use cursor::CursorMut;
+        use std::cell::Cell;
+        use std::rc::Rc;
+        use traits::Leaf;
+
+        #[derive(Clone)]
+        struct CellLeaf(Rc<Cell<usize>>);
+
+        impl Leaf for CellLeaf {
+            type Info = usize;
+            fn compute_info(&self) -> usize { self.0.get() }
+        }
+
+        let cells: Vec<_> = (0..100).map(|_| Rc::new(Cell::new(1))).collect();
+        let mut node: NodeRc<_> = cells.iter().cloned().map(CellLeaf).collect();
+        for cell in &cells[10..20] {
+            cell.set(2);
+        }
+        assert_eq!(node.info(), 100);
+        node.recompute_info_range::<usize, _>(10..20);
+        assert_eq!(node.info(), 110);
+
+        cells[50].set(5);
+        let mut cursor = CursorMut::<_, usize>::from_node(node);
+        cursor.goto_min(60);
+        cursor.refresh_leaf_info();
+        assert_eq!(cursor.leaf().unwrap().0.get(), 5);
+        assert_eq!(cursor.into_root().unwrap().info(), 114);
+    }
+
     // TODO more tests
 }
@@ -0,0 +1,267 @@
+//! A log of tree operations that can be recorded and replayed, for fuzzing and bug reports.
+//!
+//! A sequence of `Op`s is applied by a `Replay` both to a tree (through `CursorMut`) and to a
+//! `Vec` of leaves as an oracle, asserting that the two have the same leaves after every op. Any
+//! sequence of ops is valid (indices wrap around), so fuzzers can generate them freely, and a
+//! failing sequence can be written out with `write_ops` as a minimal reproduction.
+//!
+//! Indices are positions of leaves as counted by `Count`, so each leaf should count as one unit.
+//!
+//! The binary format of an op is a tag byte (`0` to `4` in the order of the variants of `Op`),
+//! followed by the index (if any) as a LEB128 varint, and the leaf (if any) per `LeafCodec`.
+
+use binary::{invalid_data, read_varint, write_varint, LeafCodec};
+use cursor::CursorMut;
+use cursor::conf::{CMutConf, Rc33M};
+use node::Node;
+use traits::{Count, Leaf};
+
+use std::fmt::Debug;
+use std::io::{self, Read, Write};
+
+const TAG_INSERT: u8 = 0;
+const TAG_REMOVE: u8 = 1;
+const TAG_SPLIT: u8 = 2;
+const TAG_CONCAT: u8 = 3;
+const TAG_SEEK: u8 = 4;
+
+/// An operation on a tree. The tree being operated on is the "main" tree, and trees split off
+/// from it are kept on a stack until concatenated back.
+///
+/// Indices are taken modulo the number of valid positions, and ops on an empty tree (other than
+/// `Insert`) are no-ops.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Op<L> {
+    /// Insert the leaf at the index, in `0..=len`.
+    Insert(usize, L),
+    /// Remove the leaf at the index, in `0..len`.
+    Remove(usize),
+    /// Split the tree at the index, in `0..=len`, pushing the right part onto the stack.
+    Split(usize),
+    /// Pop a tree from the stack (if any) and append it to the tree.
+    Concat,
+    /// Move the cursor to the leaf at the index, in `0..len`, and check the leaf.
+    Seek(usize),
+}
+
+impl<L: LeafCodec> Op<L> {
+    pub fn encode<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        match *self {
+            Op::Insert(index, ref leaf) => {
+                out.write_all(&[TAG_INSERT])?;
+                write_varint(out, index as u64)?;
+                leaf.encode(out)
+            }
+            Op::Remove(index) => {
+                out.write_all(&[TAG_REMOVE])?;
+                write_varint(out, index as u64)
+            }
+            Op::Split(index) => {
+                out.write_all(&[TAG_SPLIT])?;
+                write_varint(out, index as u64)
+            }
+            Op::Concat => out.write_all(&[TAG_CONCAT]),
+            Op::Seek(index) => {
+                out.write_all(&[TAG_SEEK])?;
+                write_varint(out, index as u64)
+            }
+        }
+    }
+
+    /// Read an op from `input`. Returns `None` if `input` is at its end.
+    pub fn decode<R: Read>(input: &mut R) -> io::Result<Option<Self>> {
+        let mut tag = [0];
+        if input.read(&mut tag)? == 0 {
+            return Ok(None);
+        }
+        let op = match tag[0] {
+            TAG_INSERT => {
+                let index = read_varint(input)? as usize;
+                Op::Insert(index, L::decode(input)?)
+            }
+            TAG_REMOVE => Op::Remove(read_varint(input)? as usize),
+            TAG_SPLIT => Op::Split(read_varint(input)? as usize),
+            TAG_CONCAT => Op::Concat,
+            TAG_SEEK => Op::Seek(read_varint(input)? as usize),
+            _ => return Err(invalid_data("invalid op tag")),
+        };
+        Ok(Some(op))
+    }
+}
+
+/// Write `ops` in the binary format described in the module documentation.
+pub fn write_ops<W: Write, L: LeafCodec>(mut out: W, ops: &[Op<L>]) -> io::Result<()> {
+    for op in ops {
+        op.encode(&mut out)?;
+    }
+    out.flush()
+}
+
+/// Read all ops written by `write_ops`, until the end of `input`.
+pub fn read_ops<R: Read, L: LeafCodec>(mut input: R) -> io::Result<Vec<Op<L>>> {
+    let mut ops = Vec::new();
+    while let Some(op) = Op::decode(&mut input)? {
+        ops.push(op);
+    }
+    Ok(ops)
+}
+
+// A tree split off from the main tree, along with its oracle.
+type Spare<L, NP> = (Option<Node<L, NP>>, Vec<L>);
+
+/// Applies ops to a tree and to an oracle, checking that they agree. See the module documentation.
+pub struct Replay<L, CONF = Rc33M>
+    where L: Leaf,
+          CONF: CMutConf<L, ()>,
+{
+    cursor: CursorMut<L, (), CONF>,
+    oracle: Vec<L>,
+    stack: Vec<Spare<L, CONF::Ptr>>,
+    applied: usize,
+}
+
+impl<L, CONF> Replay<L, CONF>
+    where L: Leaf + PartialEq + Debug,
+          L::Info: Count,
+          CONF: CMutConf<L, ()>,
+{
+    /// Start with an empty tree.
+    pub fn new() -> Self {
+        Replay { cursor: CursorMut::new(), oracle: Vec::new(), stack: Vec::new(), applied: 0 }
+    }
+
+    /// Apply each of `ops` to an empty tree, checking after each one. Returns the final tree.
+    pub fn run<'a, I>(ops: I) -> Option<Node<L, CONF::Ptr>>
+        where I: IntoIterator<Item=&'a Op<L>>, L: 'a,
+    {
+        let mut replay = Self::new();
+        for op in ops {
+            replay.apply(op);
+        }
+        replay.into_root()
+    }
+
+    /// Apply `op` to both the tree and the oracle.
+    ///
+    /// Panics (mentioning the number of ops applied before) if they disagree afterwards.
+    pub fn apply(&mut self, op: &Op<L>) {
+        let len = self.oracle.len();
+        match *op {
+            Op::Insert(index, ref leaf) => {
+                let index = index % (len + 1);
+                if index < len {
+                    self.seek(index);
+                    self.cursor.insert_leaf(leaf.clone(), false);
+                } else {
+                    self.cursor.reset();
+                    self.cursor.last_leaf();
+                    self.cursor.insert_leaf(leaf.clone(), true);
+                }
+                self.oracle.insert(index, leaf.clone());
+            }
+            Op::Remove(_) | Op::Seek(_) if len == 0 => (),
+            Op::Remove(index) => {
+                self.seek(index % len);
+                self.cursor.remove_node();
+                self.oracle.remove(index % len);
+            }
+            Op::Split(index) => {
+                let index = index % (len + 1);
+                let right = if index < len {
+                    self.seek(index);
+                    self.cursor.split_off()
+                } else {
+                    None
+                };
+                let right_oracle = self.oracle.split_off(index);
+                self.check_leaves(right.as_ref(), &right_oracle, "split-off");
+                self.stack.push((right, right_oracle));
+            }
+            Op::Concat => if let Some((right, right_oracle)) = self.stack.pop() {
+                self.cursor.reset();
+                if let Some(right) = right {
+                    self.cursor.insert(right, true);
+                }
+                self.oracle.extend(right_oracle);
+            },
+            Op::Seek(index) => {
+                let index = index % len;
+                self.seek(index);
+                assert!(self.cursor.leaf() == Some(&self.oracle[index]),
+                        "after {} ops, {:?} found {:?}, expected {:?}",
+                        self.applied, op, self.cursor.leaf(), self.oracle[index]);
+            }
+        }
+        self.cursor.reset();
+        let root = self.cursor.current();
+        self.check_leaves(root, &self.oracle, "tree");
+        self.applied += 1;
+    }
+
+    /// Returns the main tree (dropping the stack of split-off trees).
+    pub fn into_root(self) -> Option<Node<L, CONF::Ptr>> {
+        self.cursor.into_root()
+    }
+
+    // Move the cursor to the leaf at `index` (which must be in bounds).
+    fn seek(&mut self, mut index: usize) {
+        self.cursor.reset();
+        while self.cursor.descend_first().is_some() {
+            loop {
+                let count = self.cursor.current().unwrap().info().count();
+                if index < count {
+                    break;
+                }
+                index -= count;
+                self.cursor.right_sibling().expect("index out of bounds");
+            }
+        }
+    }
+
+    fn check_leaves(&self, node: Option<&Node<L, CONF::Ptr>>, oracle: &[L], what: &str) {
+        let equal = match node {
+            Some(node) => node.leaves().eq(oracle.iter()),
+            None => oracle.is_empty(),
+        };
+        if !equal {
+            let leaves: Vec<_> = node.into_iter().flat_map(|node| node.leaves()).collect();
+            panic!("after {} ops, the {} has leaves {:?}, expected {:?}",
+                   self.applied + 1, what, leaves, oracle);
+        }
+    }
+}
+
+impl<L, CONF> Default for Replay<L, CONF>
+    where L: Leaf + PartialEq + Debug,
+          L::Info: Count,
+          CONF: CMutConf<L, ()>,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{read_ops, write_ops, Op, Replay};
+    use test_help::*;
+
+    #[test]
+    fn replay() {
+        let ops: Vec<_> = (0..2000).map(|i| match rand_usize(8) {
+            0..=2 => Op::Insert(rand_usize(1000), ListLeaf(i)),
+            3 => Op::Remove(rand_usize(1000)),
+            4 => Op::Split(rand_usize(1000)),
+            5 => Op::Concat,
+            _ => Op::Seek(rand_usize(1000)),
+        }).collect();
+        let mut bytes = Vec::new();
+        write_ops(&mut bytes, &ops).unwrap();
+        let read = read_ops(&bytes[..]).unwrap();
+        assert_eq!(read, ops);
+        let root = Replay::<_>::run(&read);
+        if let Some(root) = root {
+            assert_balanced(&root);
+        }
+    }
+}
@@ -0,0 +1,109 @@
+//! Secondary info over existing trees.
+//!
+//! An `Overlay` computes an additional info over the leaves of a tree, without rebuilding the tree
+//! with a larger `Leaf::Info`. The info of each internal node is cached by the identity of the
+//! node, so that after a tree is edited, only the nodes that were replaced (those along the edited
+//! paths) are computed again. This is useful for metrics needed only for a while, such as the
+//! number of search matches.
+//!
+//! Entries are keyed by pointer, and a weak reference to each node is kept along with its info.
+//! Since a weak reference keeps the allocation of a node from being reused, an entry is valid for
+//! as long as its node can be upgraded. Entries of freed nodes are removed by `prune`.
+
+use node::{Arc16, Node, NodesWeak, WeakNode};
+use traits::{Info, Leaf};
+
+use std::collections::HashMap;
+
+type NodeId<L, NP> = *const Node<L, NP>;
+
+/// A cache of an additional info `I` over trees. See the module documentation.
+pub struct Overlay<L, I, F, NP = Arc16<L>>
+    where L: Leaf, NP: NodesWeak<L>,
+{
+    leaf_info: F,
+    cache: HashMap<NodeId<L, NP>, (WeakNode<L, NP>, I)>,
+}
+
+impl<L, I, F, NP> Overlay<L, I, F, NP>
+    where L: Leaf, I: Info, F: FnMut(&L) -> I, NP: NodesWeak<L>,
+{
+    /// Create an empty overlay that computes the info of each leaf using `leaf_info`.
+    pub fn new(leaf_info: F) -> Self {
+        Overlay { leaf_info, cache: HashMap::new() }
+    }
+
+    /// Returns the info gathered from the leaves of `node`, computing it for the internal nodes
+    /// of `node` that are not already cached.
+    pub fn info(&mut self, node: &Node<L, NP>) -> I {
+        match node.leaf() {
+            Some(leaf) => (self.leaf_info)(leaf),
+            None => {
+                let id = node.children().as_ptr();
+                if let Some(&(ref weak, info)) = self.cache.get(&id) {
+                    if weak.upgrade().is_some() {
+                        return info;
+                    }
+                }
+                let mut children = node.children().iter();
+                let first = self.info(children.next().unwrap());
+                let info = children.fold(first, |info, child| info.gather(self.info(child)));
+                self.cache.insert(id, (node.downgrade(), info));
+                info
+            }
+        }
+    }
+
+    /// The number of cached entries, including those of freed nodes.
+    pub fn len(&self) -> usize {
+        self.cache.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cache.is_empty()
+    }
+
+    /// Remove the entries of nodes that have been freed.
+    pub fn prune(&mut self) {
+        self.cache.retain(|_, &mut (ref weak, _)| weak.upgrade().is_some());
+    }
+
+    /// Remove all entries.
+    pub fn clear(&mut self) {
+        self.cache.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Overlay;
+    use test_help::*;
+
+    use std::cell::Cell;
+
+    #[test]
+    fn cached_info() {
+        let computed = Cell::new(0);
+        let mut evens = Overlay::new(|leaf: &ListLeaf| {
+            computed.set(computed.get() + 1);
+            leaf.0.is_multiple_of(2) as usize
+        });
+        let node: NodeRc<_> = (0..1000).map(ListLeaf).collect();
+        assert_eq!(evens.info(&node), 500);
+        assert_eq!(computed.get(), 1000);
+        assert_eq!(evens.info(&node), 500);
+        assert_eq!(computed.get(), 1000);
+
+        let cached = evens.len();
+        let mut edited = node.clone();
+        edited.push_back(ListLeaf(1000));
+        assert_eq!(evens.info(&edited), 501);
+        assert!(computed.get() < 1000 + 2 * node.height() * 16);
+        assert!(evens.len() > cached);
+
+        drop(node);
+        evens.prune();
+        assert!(evens.len() <= cached);
+        assert_eq!(evens.info(&edited), 501);
+    }
+}
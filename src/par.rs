@@ -0,0 +1,263 @@
+//! Parallel processing of leaves using rayon (requires the `rayon` feature).
+//!
+//! The work is split along the structure of the tree: the children of each internal node are
+//! processed in parallel, and nodes right above the leaves are processed sequentially.
+
+use diff::{count_leaves, diff, Change};
+use node::{Node, NodesPtr};
+use traits::Leaf;
+
+use rayon::iter::plumbing::{bridge_unindexed, Folder, UnindexedConsumer, UnindexedProducer};
+use rayon::prelude::*;
+
+use arrayvec::ArrayVec;
+
+use std::slice;
+
+/// A parallel iterator over the leaves of a tree. See `Node::par_leaves`.
+pub struct ParLeaves<'a, L: Leaf + 'a, NP: NodesPtr<L> + 'a> {
+    root: &'a Node<L, NP>,
+}
+
+// Produces the leaves of a sequence of sibling nodes (or of a single node).
+struct LeavesProducer<'a, L: Leaf + 'a, NP: NodesPtr<L> + 'a> {
+    nodes: &'a [Node<L, NP>],
+}
+
+impl<L, NP> Node<L, NP>
+    where L: Leaf + Send + Sync,
+          L::Info: Send + Sync,
+          NP: NodesPtr<L> + Send + Sync,
+{
+    /// Fold all leaves in order, in parallel.
+    ///
+    /// The leaves of disjoint parts of the tree are folded using `fold`, starting with a value
+    /// returned by `identity`, and the results of adjacent parts are then combined in order using
+    /// `combine`. For the result to be deterministic, `combine` must be associative, and
+    /// `identity()` must be its identity element (similar to `Info::gather`).
+    pub fn par_fold<T, ID, F, C>(&self, identity: ID, fold: F, combine: C) -> T
+        where T: Send,
+              ID: Fn() -> T + Sync,
+              F: Fn(T, &L) -> T + Sync,
+              C: Fn(T, T) -> T + Sync,
+    {
+        self.par_fold_ref(&identity, &fold, &combine)
+    }
+
+    /// Returns a tree of the same shape, with each leaf replaced by `f(leaf)`. Leaves are mapped in
+    /// parallel.
+    pub fn par_map_leaves<F>(&self, f: F) -> Node<L, NP>
+        where F: Fn(&L) -> L + Sync,
+    {
+        self.par_map_ref(&f)
+    }
+
+    /// Returns a parallel iterator over the leaves of this tree. The work is split at the
+    /// boundaries of internal nodes, and the leaves of each node right above the leaves are
+    /// iterated sequentially.
+    pub fn par_leaves(&self) -> ParLeaves<'_, L, NP> {
+        ParLeaves { root: self }
+    }
+
+    fn par_fold_ref<T, ID, F, C>(&self, identity: &ID, fold: &F, combine: &C) -> T
+        where T: Send,
+              ID: Fn() -> T + Sync,
+              F: Fn(T, &L) -> T + Sync,
+              C: Fn(T, T) -> T + Sync,
+    {
+        match self.height() {
+            0 => fold(identity(), self.leaf().unwrap()),
+            1 => self.children().iter()
+                     .fold(identity(), |acc, child| fold(acc, child.leaf().unwrap())),
+            _ => self.children().par_iter()
+                     .map(|child| child.par_fold_ref(identity, fold, combine))
+                     .reduce(identity, combine),
+        }
+    }
+
+    fn par_map_ref<F>(&self, f: &F) -> Node<L, NP>
+        where F: Fn(&L) -> L + Sync,
+    {
+        match self.height() {
+            0 => Node::from_leaf(f(self.leaf().unwrap())),
+            1 => {
+                let leaves = self.children().iter().map(|child| f(child.leaf().unwrap()));
+                Node::from_children(NP::new(leaves.map(Node::from_leaf).collect()))
+            }
+            _ => {
+                let nodes: Vec<_> = self.children().par_iter()
+                                        .map(|child| child.par_map_ref(f))
+                                        .collect();
+                Node::from_children(NP::new(nodes.into_iter().collect::<ArrayVec<_>>()))
+            }
+        }
+    }
+}
+
+impl<'a, L, NP> ParallelIterator for ParLeaves<'a, L, NP>
+    where L: Leaf + Send + Sync,
+          L::Info: Send + Sync,
+          NP: NodesPtr<L> + Send + Sync,
+{
+    type Item = &'a L;
+
+    fn drive_unindexed<C>(self, consumer: C) -> C::Result where C: UnindexedConsumer<&'a L> {
+        bridge_unindexed(LeavesProducer { nodes: slice::from_ref(self.root) }, consumer)
+    }
+}
+
+impl<'a, L, NP> UnindexedProducer for LeavesProducer<'a, L, NP>
+    where L: Leaf + Send + Sync,
+          L::Info: Send + Sync,
+          NP: NodesPtr<L> + Send + Sync,
+{
+    type Item = &'a L;
+
+    fn split(self) -> (Self, Option<Self>) {
+        let nodes = match self.nodes {
+            [node] if node.height() >= 2 => node.children(),
+            [_] => return (self, None),
+            nodes => nodes,
+        };
+        let (left, right) = nodes.split_at(nodes.len() / 2);
+        (LeavesProducer { nodes: left }, Some(LeavesProducer { nodes: right }))
+    }
+
+    fn fold_with<F>(self, mut folder: F) -> F where F: Folder<&'a L> {
+        for node in self.nodes {
+            folder = folder.consume_iter(node.leaves());
+            if folder.full() {
+                break;
+            }
+        }
+        folder
+    }
+}
+
+/// Same as `diff::diff`, but diffs independent parts of the trees in parallel.
+///
+/// When two internal nodes have the same number of children, and the children shared between
+/// them (if any) are at the same positions, the children are diffed pairwise in parallel (skipping
+/// the shared ones). Other nodes are diffed sequentially. Since changes are not tracked across
+/// the boundaries of the paired children, the changes may be less minimal than those of `diff`.
+pub fn par_diff<L, NP>(old: &Node<L, NP>, new: &Node<L, NP>) -> Vec<Change>
+    where L: Leaf + PartialEq + Send + Sync,
+          L::Info: Send + Sync,
+          NP: NodesPtr<L> + Send + Sync,
+{
+    par_diff_counted(old, new).0
+}
+
+// Returns the changes, along with the number of leaves in `old` and `new`.
+fn par_diff_counted<L, NP>(old: &Node<L, NP>, new: &Node<L, NP>) -> (Vec<Change>, usize, usize)
+    where L: Leaf + PartialEq + Send + Sync,
+          L::Info: Send + Sync,
+          NP: NodesPtr<L> + Send + Sync,
+{
+    if old.ptr_eq(new) {
+        let count = count_leaves(old);
+        return (Vec::new(), count, count);
+    }
+    let (old_children, new_children) = (old.children(), new.children());
+    let aligned = old.height() == new.height() && old_children.len() == new_children.len()
+        && !old_children.is_empty()
+        && new_children.iter().enumerate().all(|(i, new_child)| {
+            old_children.iter().enumerate()
+                        .all(|(j, old_child)| i == j || !old_child.ptr_eq(new_child))
+        });
+    if !aligned {
+        return (diff(old, new), count_leaves(old), count_leaves(new));
+    }
+
+    let parts: Vec<_> = old_children.par_iter().zip(new_children)
+                                    .map(|(old_child, new_child)| {
+                                        par_diff_counted(old_child, new_child)
+                                    })
+                                    .collect();
+    let mut changes: Vec<Change> = Vec::new();
+    let (mut old_offset, mut new_offset) = (0, 0);
+    for (part, old_count, new_count) in parts {
+        for change in part {
+            let change = Change {
+                old: old_offset + change.old.start..old_offset + change.old.end,
+                new: new_offset + change.new.start..new_offset + change.new.end,
+            };
+            match changes.last_mut() {
+                // merge with a change ending right where this one starts (in the previous child)
+                Some(last) if last.old.end == change.old.start
+                              && last.new.end == change.new.start => {
+                    last.old.end = change.old.end;
+                    last.new.end = change.new.end;
+                }
+                _ => changes.push(change),
+            }
+        }
+        old_offset += old_count;
+        new_offset += new_count;
+    }
+    (changes, old_offset, new_offset)
+}
+
+#[cfg(test)]
+mod tests {
+    use node::{Arc16, Node};
+    use test_help::*;
+
+    #[test]
+    fn par_fold_map() {
+        let node: Node<_, Arc16<_>> = (0..10000).map(ListLeaf).collect();
+        let sum = node.par_fold(|| 0, |acc, leaf| acc + leaf.0, |a, b| a + b);
+        assert_eq!(sum, 9999 * 10000 / 2);
+        let first_ten = node.par_fold(Vec::new, |mut acc, leaf| {
+            if acc.len() < 10 { acc.push(leaf.0) }
+            acc
+        }, |mut a, b| {
+            a.extend(b.into_iter().take(10 - a.len()));
+            a
+        });
+        assert_eq!(first_ten, (0..10).collect::<Vec<_>>());
+
+        let doubled = node.par_map_leaves(|leaf| ListLeaf(leaf.0 * 2));
+        assert_eq!(doubled.info(), ListInfo { count: 10000, sum: 9999 * 10000 });
+        assert_eq!(doubled.height(), node.height());
+    }
+
+    #[test]
+    fn par_leaves() {
+        use rayon::prelude::*;
+
+        let node: Node<_, Arc16<_>> = (0..10000).map(ListLeaf).collect();
+        assert_eq!(node.par_leaves().map(|leaf| leaf.0).sum::<usize>(), 9999 * 10000 / 2);
+        let leaves: Vec<_> = node.par_leaves().map(|leaf| leaf.0).collect();
+        assert_eq!(leaves, (0..10000).collect::<Vec<_>>());
+        assert_eq!(node.par_leaves().find_first(|leaf| leaf.0 % 1000 == 999), Some(&ListLeaf(999)));
+    }
+
+    #[test]
+    fn par_diff() {
+        use cursor::CursorMut;
+        use cursor::conf::Arc33M;
+        use diff::{diff, Change};
+
+        let base: Node<_, Arc16<_>> = (0..10000).map(ListLeaf).collect();
+        let mut cursor_mut = CursorMut::<_, ListPath, Arc33M>::from_node(base.clone());
+        for &i in &[100, 5000, 5001, 9999] {
+            cursor_mut.goto_min(ListIndex(i));
+            cursor_mut.leaf_update(|leaf| leaf.0 = 0);
+        }
+        let updated = cursor_mut.clone().into_root().unwrap();
+        let changes = super::par_diff(&base, &updated);
+        assert_eq!(changes, diff(&base, &updated));
+        assert_eq!(changes[1], Change { old: 5000..5002, new: 5000..5002 });
+
+        cursor_mut.goto_min(ListIndex(3000));
+        cursor_mut.remove_leaf();
+        let removed = cursor_mut.into_root().unwrap();
+        let mut leaves: Vec<_> = base.leaves().cloned().collect();
+        for change in super::par_diff(&base, &removed).into_iter().rev() {
+            let new = removed.leaves().skip(change.new.start).take(change.new.len()).cloned();
+            leaves.splice(change.old, new);
+        }
+        assert!(leaves.iter().eq(removed.leaves()));
+    }
+}
@@ -0,0 +1,313 @@
+//! A piece table for editing text, backed by a `Node` of pieces.
+//!
+//! The text is never copied around: the original text and all inserted text live in two
+//! append-only buffers, and the tree only stores `(buffer, offset, len)` spans over them. Since
+//! the buffers only ever grow, a root obtained through `PieceTable::snapshot` remains valid for
+//! the lifetime of the table, which makes cheap undo and versioning possible.
+
+use cursor::CursorMut;
+use cursor::conf::{CMutConf, PtrMark, Rc33M};
+use node::{concat_opt, Node, NodesPtr};
+use traits::Leaf;
+
+use std::cmp;
+use std::fmt;
+use std::ops::Range;
+
+/// The buffer a `Piece` refers to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Buffer {
+    Original,
+    Added,
+}
+
+/// A span of text within one of the buffers of a `PieceTable`.
+///
+/// The info of a piece is the number of chars it spans.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Piece {
+    buffer: Buffer,
+    start: usize, // in bytes
+    len: usize, // in bytes
+    chars: usize,
+}
+
+impl Leaf for Piece {
+    type Info = usize;
+
+    fn compute_info(&self) -> usize {
+        self.chars
+    }
+}
+
+impl Piece {
+    pub fn buffer(&self) -> Buffer {
+        self.buffer
+    }
+
+    /// The byte range of this piece within its buffer.
+    pub fn byte_range(&self) -> Range<usize> {
+        self.start..self.start + self.len
+    }
+
+    // Split this piece after `chars` number of chars. `text` must be the text of this piece.
+    fn split(&self, text: &str, chars: usize) -> (Piece, Piece) {
+        debug_assert!(0 < chars && chars < self.chars);
+        let offset = text.char_indices().nth(chars).unwrap().0;
+        let left = Piece { len: offset, chars, ..*self };
+        let right = Piece {
+            start: self.start + offset,
+            len: self.len - offset,
+            chars: self.chars - chars,
+            ..*self
+        };
+        (left, right)
+    }
+}
+
+type PieceNode<CONF> = Node<Piece, <CONF as PtrMark<Piece>>::Ptr>;
+
+/// A text buffer supporting insertion and deletion by char position.
+///
+/// All operations take O(log n) time where n is the number of pieces, plus the time to copy the
+/// inserted text into the append buffer.
+pub struct PieceTable<CONF = Rc33M>
+    where CONF: CMutConf<Piece, usize>,
+{
+    original: String,
+    added: String,
+    root: Option<Node<Piece, CONF::Ptr>>,
+}
+
+impl<CONF> Clone for PieceTable<CONF>
+    where CONF: CMutConf<Piece, usize>,
+{
+    fn clone(&self) -> Self {
+        PieceTable {
+            original: self.original.clone(),
+            added: self.added.clone(),
+            root: self.root.clone(),
+        }
+    }
+}
+
+impl<CONF> PieceTable<CONF>
+    where CONF: CMutConf<Piece, usize>,
+{
+    /// Create a new piece table with `text` as its original content.
+    pub fn new(text: String) -> Self {
+        let root = if !text.is_empty() {
+            Some(Node::from_leaf(Piece {
+                buffer: Buffer::Original,
+                start: 0,
+                len: text.len(),
+                chars: text.chars().count(),
+            }))
+        } else {
+            None
+        };
+        PieceTable {
+            original: text,
+            added: String::new(),
+            root,
+        }
+    }
+
+    /// The number of chars in the table.
+    pub fn len(&self) -> usize {
+        self.root.as_ref().map_or(0, |root| root.info())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.root.is_none()
+    }
+
+    /// Returns the text spanned by `piece`.
+    pub fn piece_text(&self, piece: &Piece) -> &str {
+        let buffer = match piece.buffer {
+            Buffer::Original => &self.original,
+            Buffer::Added => &self.added,
+        };
+        &buffer[piece.byte_range()]
+    }
+
+    /// Insert `text` before the char at position `pos`, or at the end if `pos >= self.len()`.
+    pub fn insert(&mut self, pos: usize, text: &str) {
+        let pos = cmp::min(pos, self.len());
+        if text.is_empty() {
+            return;
+        }
+        let piece = Piece {
+            buffer: Buffer::Added,
+            start: self.added.len(),
+            len: text.len(),
+            chars: text.chars().count(),
+        };
+        self.added.push_str(text);
+
+        let (left, right) = match self.root.take() {
+            Some(root) => self.split(root, pos),
+            None => (None, None),
+        };
+        self.root = concat_opt(concat_opt(left, Some(Node::from_leaf(piece))), right);
+    }
+
+    /// Delete the chars in `range`. The part of `range` beyond the end is ignored.
+    ///
+    /// Panics if `range.start > range.end`.
+    pub fn delete(&mut self, range: Range<usize>) {
+        assert!(range.start <= range.end, "delete range is decreasing");
+        let end = cmp::min(range.end, self.len());
+        let range = cmp::min(range.start, end)..end;
+        if range.start == range.end {
+            return;
+        }
+        let root = self.root.take().unwrap(); // range is non-empty
+        let (left, rest) = self.split(root, range.start);
+        let (_, right) = self.split(rest.unwrap(), range.end - range.start);
+        self.root = concat_opt(left, right);
+    }
+
+    /// Returns the root node of the tree of pieces, which can be used to restore the table to its
+    /// current content later on using `restore`.
+    pub fn snapshot(&self) -> Option<Node<Piece, CONF::Ptr>> {
+        self.root.clone()
+    }
+
+    /// Restore the content of the table to that of a snapshot taken earlier from this table.
+    pub fn restore(&mut self, snapshot: Option<Node<Piece, CONF::Ptr>>) {
+        self.root = snapshot;
+    }
+
+    // Split `root` into two at char position `pos`, splitting a piece if necessary.
+    fn split(&self, root: PieceNode<CONF>, pos: usize)
+        -> (Option<PieceNode<CONF>>, Option<PieceNode<CONF>>)
+    {
+        if pos == 0 {
+            return (None, Some(root));
+        } else if pos >= root.info() {
+            return (Some(root), None);
+        }
+
+        let mut cursor = CursorMut::<_, _, CONF>::from_node(root);
+        let at_boundary = cursor.goto_min(pos).is_some() && cursor.path_info() == pos;
+        if !at_boundary {
+            // the piece containing `pos` is the one right after the last piece ending before it
+            cursor.reset();
+            if cursor.goto_max(pos).is_some() {
+                cursor.next_leaf();
+            } else {
+                cursor.reset();
+                cursor.first_leaf();
+            }
+            let offset = pos - cursor.path_info();
+            let (left, right) = {
+                let piece = cursor.leaf().unwrap();
+                piece.split(self.piece_text(piece), offset)
+            };
+            cursor.leaf_update(|piece| *piece = left);
+            cursor.insert_leaf(right, true);
+            cursor.reset();
+            let _res = cursor.goto_min(pos);
+            debug_assert!(_res.is_some() && cursor.path_info() == pos);
+        }
+        let right = cursor.split_off();
+        (cursor.into_root(), right)
+    }
+
+    // Calls `f` on each piece in order.
+    fn for_each_piece<F>(&self, mut f: F) where F: FnMut(&Piece) {
+        fn visit<L: Leaf, NP: NodesPtr<L>, F: FnMut(&L)>(node: &Node<L, NP>, f: &mut F) {
+            match node.leaf() {
+                Some(leaf) => f(leaf),
+                None => for child in node.children() { visit(child, f) },
+            }
+        }
+        if let Some(ref root) = self.root {
+            visit(root, &mut f);
+        }
+    }
+}
+
+impl<CONF> fmt::Display for PieceTable<CONF>
+    where CONF: CMutConf<Piece, usize>,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut res = Ok(());
+        self.for_each_piece(|piece| {
+            if res.is_ok() {
+                res = f.write_str(self.piece_text(piece));
+            }
+        });
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PieceTable;
+    use test_help::*;
+
+    #[test]
+    fn insert_delete() {
+        let mut table: PieceTable = PieceTable::new(String::from("hello world"));
+        table.insert(5, ",");
+        table.insert(12, "!");
+        table.insert(0, "¡");
+        assert_eq!(table.to_string(), "¡hello, world!");
+        assert_eq!(table.len(), 14);
+        table.delete(1..7);
+        assert_eq!(table.to_string(), "¡ world!");
+        table.delete(0..8);
+        assert!(table.is_empty());
+        table.insert(0, "ünïcödé");
+        table.insert(3, "-");
+        assert_eq!(table.to_string(), "ünï-cödé");
+
+        table.insert(100, "!"); // clamped to the end
+        table.delete(7..100);
+        table.delete(100..200);
+        assert_eq!(table.to_string(), "ünï-cöd");
+    }
+
+    #[test]
+    #[should_panic(expected = "delete range is decreasing")]
+    fn delete_decreasing() {
+        let mut table: PieceTable = PieceTable::new(String::from("hello world"));
+        let start = 5;
+        table.delete(start..start - 3);
+    }
+
+    #[test]
+    fn random_edits() {
+        let mut table: PieceTable = PieceTable::new(String::from("0123456789"));
+        let mut oracle: Vec<char> = "0123456789".chars().collect();
+        for i in 0..512 {
+            let pos = rand_usize(oracle.len() + 1);
+            if i % 3 == 2 && pos < oracle.len() {
+                let end = pos + rand_usize(oracle.len() - pos) + 1;
+                table.delete(pos..end);
+                oracle.drain(pos..end);
+            } else {
+                let text = format!("<{}é>", i);
+                table.insert(pos, &text);
+                for (j, c) in text.chars().enumerate() {
+                    oracle.insert(pos + j, c);
+                }
+            }
+            assert_eq!(table.len(), oracle.len());
+        }
+        assert_eq!(table.to_string(), oracle.into_iter().collect::<String>());
+    }
+
+    #[test]
+    fn snapshot_restore() {
+        let mut table: PieceTable = PieceTable::new(String::from("abc"));
+        let snapshot = table.snapshot();
+        table.insert(1, "xyz");
+        table.delete(0..2);
+        assert_eq!(table.to_string(), "yzbc");
+        table.restore(snapshot);
+        assert_eq!(table.to_string(), "abc");
+    }
+}
@@ -0,0 +1,92 @@
+//! Serialization of trees using serde (requires the `serde` feature).
+//!
+//! A `Node` is serialized as the sequence of its leaves, and deserialized by rebuilding a balanced
+//! tree out of them using `TreeBuilder`, so the format does not depend on the shape of the tree.
+
+use builder::TreeBuilder;
+use diff::count_leaves;
+use node::{Node, NodesPtr};
+use traits::Leaf;
+
+use serde::de::{Deserialize, Deserializer, Error, SeqAccess, Visitor};
+use serde::ser::{Serialize, SerializeSeq, Serializer};
+
+use std::fmt;
+use std::marker::PhantomData;
+
+impl<L, NP> Serialize for Node<L, NP>
+    where L: Leaf + Serialize,
+          NP: NodesPtr<L>,
+{
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        fn visit<L, NP, S>(node: &Node<L, NP>, seq: &mut S) -> Result<(), S::Error>
+            where L: Leaf + Serialize, NP: NodesPtr<L>, S: SerializeSeq,
+        {
+            match node.leaf() {
+                Some(leaf) => seq.serialize_element(leaf),
+                None => {
+                    for child in node.children() {
+                        visit(child, seq)?;
+                    }
+                    Ok(())
+                }
+            }
+        }
+
+        let mut seq = serializer.serialize_seq(Some(count_leaves(self)))?;
+        visit(self, &mut seq)?;
+        seq.end()
+    }
+}
+
+impl<'de, L, NP> Deserialize<'de> for Node<L, NP>
+    where L: Leaf + Deserialize<'de>,
+          NP: NodesPtr<L>,
+{
+    /// Fails if the sequence of leaves is empty. Use `Option<Node>` for possibly empty trees.
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct LeavesVisitor<L, NP>(PhantomData<(L, NP)>);
+
+        impl<'de, L, NP> Visitor<'de> for LeavesVisitor<L, NP>
+            where L: Leaf + Deserialize<'de>,
+                  NP: NodesPtr<L>,
+        {
+            type Value = Node<L, NP>;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a non-empty sequence of leaves")
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+                let mut builder = TreeBuilder::new();
+                while let Some(leaf) = seq.next_element()? {
+                    builder.push(leaf);
+                }
+                builder.build().ok_or_else(|| A::Error::invalid_length(0, &self))
+            }
+        }
+
+        deserializer.deserialize_seq(LeavesVisitor(PhantomData))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use test_help::*;
+
+    use serde_json;
+
+    #[test]
+    fn round_trip() {
+        let node: NodeRc<_> = (0..100).map(ListLeaf).collect();
+        let json = serde_json::to_string(&node).unwrap();
+        assert!(json.starts_with("[0,1,2,"));
+        let node: NodeRc<ListLeaf> = serde_json::from_str(&json).unwrap();
+        assert_balanced(&node);
+        assert_eq!(node.info(), ListInfo { count: 100, sum: 99 * 100 / 2 });
+
+        assert!(serde_json::from_str::<NodeRc<ListLeaf>>("[]").is_err());
+        let empty: Option<NodeRc<ListLeaf>> = serde_json::from_str("null").unwrap();
+        assert!(empty.is_none());
+    }
+}
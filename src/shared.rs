@@ -0,0 +1,117 @@
+//! A handle for sharing a tree among threads, with one writer publishing new versions.
+
+use node::{Arc16, Node, NodesPtr};
+use traits::Leaf;
+
+use std::mem;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, MutexGuard, RwLock};
+
+/// A tree shared among threads. Readers take snapshots of the current root, while a writer
+/// publishes new roots, typically derived from a snapshot using `CursorMut`.
+///
+/// Since trees are persistent, a snapshot stays consistent regardless of later publishes, and
+/// taking one only clones the root. The root is only locked while being cloned or replaced, so
+/// readers never wait for a writer's edits, and vice versa.
+pub struct SharedTree<L: Leaf, NP: NodesPtr<L> = Arc16<L>> {
+    root: RwLock<Option<Node<L, NP>>>,
+    writer: Mutex<()>, // serializes `update`s
+    generation: AtomicU64,
+}
+
+impl<L: Leaf, NP: NodesPtr<L>> SharedTree<L, NP> {
+    pub fn new(root: Option<Node<L, NP>>) -> Self {
+        SharedTree {
+            root: RwLock::new(root),
+            writer: Mutex::new(()),
+            generation: AtomicU64::new(0),
+        }
+    }
+
+    /// Returns the current root, or `None` if the tree is empty.
+    pub fn snapshot(&self) -> Option<Node<L, NP>> {
+        // the root is always valid, since it is only ever replaced as a whole
+        self.root.read().unwrap_or_else(|err| err.into_inner()).clone()
+    }
+
+    /// Replace the root with `root`, and return the previous root.
+    ///
+    /// Note: Use `update` instead when `root` is derived from a snapshot, so that concurrent
+    /// updates are not lost.
+    pub fn publish(&self, root: Option<Node<L, NP>>) -> Option<Node<L, NP>> {
+        let mut guard = self.root.write().unwrap_or_else(|err| err.into_inner());
+        self.generation.fetch_add(1, Ordering::Release);
+        mem::replace(&mut *guard, root)
+    }
+
+    /// The number of roots published so far. Caches of values derived from snapshots can use it
+    /// to detect that the tree may have changed, without comparing the trees.
+    pub fn generation(&self) -> u64 {
+        self.generation.load(Ordering::Acquire)
+    }
+
+    /// Publish the root returned by `f` when called with a snapshot of the current root. Updates
+    /// are serialized, so the root is not replaced by anyone else while `f` is running (except by
+    /// `publish`). Readers can take snapshots meanwhile, and see the update once it is published.
+    pub fn update<F>(&self, f: F) where F: FnOnce(Option<Node<L, NP>>) -> Option<Node<L, NP>> {
+        let _writer = self.lock_writer();
+        let root = f(self.snapshot());
+        self.publish(root);
+    }
+
+    /// Returns the current root, consuming the handle.
+    pub fn into_root(self) -> Option<Node<L, NP>> {
+        self.root.into_inner().unwrap_or_else(|err| err.into_inner())
+    }
+
+    fn lock_writer(&self) -> MutexGuard<'_, ()> {
+        self.writer.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SharedTree;
+    use cursor::CursorMut;
+    use cursor::conf::Arc33M;
+    use test_help::*;
+
+    use std::sync::Arc;
+    use std::thread;
+
+    #[test]
+    fn snapshots() {
+        let shared = Arc::new(SharedTree::new(Some((0..1000).map(ListLeaf).collect())));
+        let readers: Vec<_> = (0..4).map(|_| {
+            let shared = shared.clone();
+            thread::spawn(move || {
+                for _ in 0..100 {
+                    let root = shared.snapshot().unwrap();
+                    // every leaf was incremented by the same number of updates
+                    let first = root.leaves().next().unwrap().0;
+                    assert!(root.leaves().enumerate().all(|(i, leaf)| leaf.0 == first + i));
+                }
+            })
+        }).collect();
+        for _ in 0..10 {
+            shared.update(|root| {
+                let mut cursor_mut = CursorMut::<_, (), Arc33M>::from_node(root.unwrap());
+                cursor_mut.defer_info(true);
+                cursor_mut.first_leaf();
+                loop {
+                    cursor_mut.leaf_update(|leaf| leaf.0 += 1);
+                    if cursor_mut.next_leaf().is_none() {
+                        break;
+                    }
+                }
+                cursor_mut.into_root()
+            });
+        }
+        for reader in readers {
+            reader.join().unwrap();
+        }
+        assert_eq!(shared.generation(), 10);
+        let root = Arc::try_unwrap(shared).ok().unwrap().into_root().unwrap();
+        assert_eq!(root.info(), ListInfo { count: 1000, sum: 999 * 1000 / 2 + 10 * 1000 });
+    }
+}
@@ -0,0 +1,277 @@
+//! Operations on trees whose leaves are sorted by key (see `KeyRange`).
+//!
+//! These work on whole subtrees wherever the key ranges of the inputs do not interleave, so that
+//! such subtrees are reused as is, rather than being rebuilt leaf by leaf.
+//!
+//! Each leaf should hold a single key (see `KeyRange`), which is checked with debug assertions.
+
+use node::{concat_opt, Node, NodesPtr};
+use traits::{KeyRange, Leaf};
+
+type Split<L, NP> = (Option<Node<L, NP>>, Option<Node<L, NP>>);
+
+/// Merge two sorted trees into one sorted tree. Leaves with equal keys are kept, with the leaves of
+/// `a` placed before those of `b`.
+///
+/// Time: O(k log n), where k is the number of alternations between the inputs in the merged order.
+pub fn merge_sorted<L, NP>(a: Node<L, NP>, b: Node<L, NP>) -> Node<L, NP>
+    where L: Leaf, L::Info: KeyRange, NP: NodesPtr<L>,
+{
+    debug_assert_single_key(&a);
+    debug_assert_single_key(&b);
+    let (a_info, b_info) = (a.info(), b.info());
+    if a_info.max_key() <= b_info.min_key() {
+        return Node::concat(a, b);
+    } else if b_info.max_key() < a_info.min_key() {
+        return Node::concat(b, a);
+    }
+    // split the taller tree into its children, and merge each of them with the part of the other
+    // tree within its key range
+    let (outer, inner, outer_first) = if a.height() >= b.height() {
+        (a, b, true)
+    } else {
+        (b, a, false)
+    };
+    let children = outer.children();
+    let mut merged = None;
+    let mut rest = Some(inner);
+    for (i, child) in children.iter().enumerate() {
+        let part = match (rest.take(), children.get(i + 1)) {
+            (Some(inner), Some(next)) => {
+                // on equal keys, the leaves of `a` go first
+                let (left, right) = split_by_key(inner, &next.info().min_key(), !outer_first);
+                rest = right;
+                left
+            }
+            (inner, _) => inner,
+        };
+        let node = match part {
+            Some(part) => merge_pair(child, part, outer_first),
+            None => child.clone(),
+        };
+        merged = concat_opt(merged, Some(node));
+    }
+    merged.unwrap()
+}
+
+// Merge `child` of the outer tree with a part of the inner tree.
+fn merge_pair<L, NP>(child: &Node<L, NP>, part: Node<L, NP>, outer_first: bool) -> Node<L, NP>
+    where L: Leaf, L::Info: KeyRange, NP: NodesPtr<L>,
+{
+    if outer_first {
+        merge_sorted(child.clone(), part)
+    } else {
+        merge_sorted(part, child.clone())
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum SetOp {
+    Union,
+    Intersection,
+    Difference,
+}
+
+/// Returns the union of two sets, represented as trees sorted by unique keys. Of two leaves with
+/// the same key, the one in `a` is kept.
+///
+/// Subtrees shared between `a` and `b` (such as when they are versions of the same set) are
+/// skipped without being compared, so the time taken is proportional to their differences.
+pub fn union<L, NP>(a: Node<L, NP>, b: Node<L, NP>) -> Node<L, NP>
+    where L: Leaf, L::Info: KeyRange, NP: NodesPtr<L>,
+{
+    set_op(a, b, SetOp::Union).unwrap()
+}
+
+/// Returns the leaves of `a` whose keys are also in `b`, or `None` if there are none. See `union`.
+pub fn intersection<L, NP>(a: Node<L, NP>, b: Node<L, NP>) -> Option<Node<L, NP>>
+    where L: Leaf, L::Info: KeyRange, NP: NodesPtr<L>,
+{
+    set_op(a, b, SetOp::Intersection)
+}
+
+/// Returns the leaves of `a` whose keys are not in `b`, or `None` if there are none. See `union`.
+pub fn difference<L, NP>(a: Node<L, NP>, b: Node<L, NP>) -> Option<Node<L, NP>>
+    where L: Leaf, L::Info: KeyRange, NP: NodesPtr<L>,
+{
+    set_op(a, b, SetOp::Difference)
+}
+
+fn set_op<L, NP>(a: Node<L, NP>, b: Node<L, NP>, op: SetOp) -> Option<Node<L, NP>>
+    where L: Leaf, L::Info: KeyRange, NP: NodesPtr<L>,
+{
+    if a.ptr_eq(&b) {
+        return if op == SetOp::Difference { None } else { Some(a) };
+    }
+    debug_assert_single_key(&a);
+    debug_assert_single_key(&b);
+    let (a_info, b_info) = (a.info(), b.info());
+    let disjoint = if a_info.max_key() < b_info.min_key() {
+        Some(true)
+    } else if b_info.max_key() < a_info.min_key() {
+        Some(false)
+    } else {
+        None
+    };
+    match (disjoint, op) {
+        (Some(_), SetOp::Intersection) => return None,
+        (Some(_), SetOp::Difference) => return Some(a),
+        (Some(true), SetOp::Union) => return Some(Node::concat(a, b)),
+        (Some(false), SetOp::Union) => return Some(Node::concat(b, a)),
+        (None, _) if a.is_leaf() && b.is_leaf() => { // with the same key
+            return if op == SetOp::Difference { None } else { Some(a) };
+        }
+        (None, _) => (),
+    }
+    // split the taller tree into its children, and combine each of them with the part of the other
+    // tree within its key range
+    let outer_is_a = a.height() >= b.height();
+    let (outer, inner) = if outer_is_a { (a, b) } else { (b, a) };
+    let children = outer.children();
+    let mut result = None;
+    let mut rest = Some(inner);
+    for (i, child) in children.iter().enumerate() {
+        let part = match (rest.take(), children.get(i + 1)) {
+            (Some(inner), Some(next)) => {
+                let (left, right) = split_by_key(inner, &next.info().min_key(), false);
+                rest = right;
+                left
+            }
+            (inner, _) => inner,
+        };
+        let node = match (part, op, outer_is_a) {
+            (Some(part), _, true) => set_op(child.clone(), part, op),
+            (Some(part), _, false) => set_op(part, child.clone(), op),
+            (None, SetOp::Union, _) | (None, SetOp::Difference, true) => Some(child.clone()),
+            (None, _, _) => None,
+        };
+        result = concat_opt(result, node);
+    }
+    result
+}
+
+/// Split a sorted tree into the leaves with keys less than `key` (or equal to it, if `inclusive`)
+/// and the rest.
+///
+/// Time: O(log n)
+pub fn split_by_key<L, NP>(node: Node<L, NP>, key: &<L::Info as KeyRange>::Key, inclusive: bool)
+    -> Split<L, NP>
+    where L: Leaf, L::Info: KeyRange, NP: NodesPtr<L>,
+{
+    debug_assert_single_key(&node);
+    let goes_left = |bound: <L::Info as KeyRange>::Key| {
+        if inclusive { bound <= *key } else { bound < *key }
+    };
+    let info = node.info();
+    if goes_left(info.max_key()) {
+        return (Some(node), None);
+    } else if !goes_left(info.min_key()) {
+        return (None, Some(node));
+    }
+    // the split falls within one of the children (which is not a leaf)
+    let children = node.children();
+    let index = children.iter().position(|child| !goes_left(child.info().max_key())).unwrap();
+    let (left, right) = split_by_key(children[index].clone(), key, inclusive);
+    let left = children[..index].iter().rev().cloned()
+                                .fold(left, |left, child| concat_opt(Some(child), left));
+    let right = children[index + 1..].iter().cloned()
+                                     .fold(right, |right, child| concat_opt(right, Some(child)));
+    (left, right)
+}
+
+// Check that `node` is not a leaf covering a range of keys, which the operations above would
+// have to split.
+fn debug_assert_single_key<L, NP>(node: &Node<L, NP>)
+    where L: Leaf, L::Info: KeyRange, NP: NodesPtr<L>,
+{
+    if node.is_leaf() {
+        let info = node.info();
+        debug_assert!(info.min_key() == info.max_key(),
+                      "sorted: a leaf holds more than a single key");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{difference, intersection, merge_sorted, split_by_key, union};
+    use test_help::*;
+    use traits::Leaf;
+
+    use std::collections::BTreeSet;
+
+    #[test]
+    fn merge() {
+        let evens: NodeRc<_> = (0..500).map(|i| SetLeaf('a', 2 * i)).collect();
+        let odds: NodeRc<_> = (0..300).map(|i| SetLeaf('a', 2 * i + 1)).collect();
+        let tail: NodeRc<_> = (0..300).map(|i| SetLeaf('b', i)).collect();
+        for (a, b) in [(evens.clone(), odds.clone()), (odds.clone(), evens.clone()),
+                           (tail.clone(), evens.clone()), (evens.clone(), evens.clone())] {
+            let mut expected: Vec<_> = a.leaves().chain(b.leaves()).cloned().collect();
+            expected.sort();
+            let merged = merge_sorted(a, b);
+            assert_balanced(&merged);
+            assert!(merged.leaves().cloned().eq(expected));
+        }
+        let merged = merge_sorted(evens.clone(), tail.clone());
+        assert!(merged.children().last().unwrap().ptr_eq(tail.children().last().unwrap()));
+    }
+
+    #[test]
+    fn split() {
+        let node: NodeRc<_> = (0..500).map(|i| SetLeaf('a', i / 2)).collect();
+        let (left, right) = split_by_key(node.clone(), &SetLeaf('a', 100), false);
+        assert_eq!(left.unwrap().leaves().count(), 200);
+        assert_eq!(right.unwrap().leaves().count(), 300);
+        let (left, right) = split_by_key(node, &SetLeaf('a', 100), true);
+        assert_eq!(left.unwrap().leaves().count(), 202);
+        assert_eq!(right.unwrap().leaves().count(), 298);
+    }
+
+    #[test]
+    fn set_ops() {
+        let set = |keys: &[usize]| -> NodeRc<_> { keys.iter().map(|&i| SetLeaf('a', i)).collect() };
+        let keys = |node: Option<NodeRc<SetLeaf>>| -> BTreeSet<usize> {
+            node.map_or(BTreeSet::new(), |node| node.leaves().map(|leaf| leaf.1).collect())
+        };
+        let k1: Vec<_> = (0..600).step_by(2).collect();
+        let k2: Vec<_> = (0..900).step_by(3).collect();
+        let (s1, s2) = (k1.iter().cloned().collect::<BTreeSet<_>>(), k2.iter().cloned().collect());
+        let (a, b) = (set(&k1), set(&k2));
+        let expected: BTreeSet<_> = s1.union(&s2).cloned().collect();
+        assert_eq!(keys(Some(union(a.clone(), b.clone()))), expected);
+        let expected: BTreeSet<_> = s1.intersection(&s2).cloned().collect();
+        assert_eq!(keys(intersection(a.clone(), b.clone())), expected);
+        let expected: BTreeSet<_> = s1.difference(&s2).cloned().collect();
+        assert_eq!(keys(difference(a.clone(), b.clone())), expected);
+        let expected: BTreeSet<_> = s2.difference(&s1).cloned().collect();
+        assert_eq!(keys(difference(b.clone(), a.clone())), expected);
+
+        // versions of the same set
+        let mut cursor_mut = ::cursor::CursorMut::<_, ()>::from_node(a.clone());
+        cursor_mut.find_min(MinLeaf(SetLeaf('a', 300)));
+        cursor_mut.remove_leaf();
+        let a2 = cursor_mut.into_root().unwrap();
+        let expected: BTreeSet<_> = Some(300).into_iter().collect();
+        assert_eq!(keys(difference(a.clone(), a2.clone())), expected);
+        assert!(union(a.clone(), a.clone()).ptr_eq(&a));
+        assert_balanced(&union(a, a2));
+    }
+    #[cfg(debug_assertions)]
+    #[test]
+    #[should_panic(expected = "more than a single key")]
+    fn multi_key_leaves() {
+        #[derive(Clone)]
+        struct Range(usize, usize); // a leaf covering the keys from `.0` to `.1`
+
+        impl Leaf for Range {
+            type Info = SetInfo;
+            fn compute_info(&self) -> SetInfo {
+                SetInfo { min: SetLeaf('a', self.0), max: SetLeaf('a', self.1) }
+            }
+        }
+
+        let a: NodeRc<_> = (0..100).map(|i| Range(10 * i, 10 * i + 9)).collect();
+        let b: NodeRc<_> = (0..100).map(|i| Range(10 * i + 5, 10 * i + 5)).collect();
+        merge_sorted(a, b);
+    }
+}
@@ -0,0 +1,175 @@
+//! Statistics about the shape of trees (useful for detecting fragmentation) and their sharing.
+
+use node::{Node, NodesPtr};
+use traits::Leaf;
+
+use std::collections::HashSet;
+use std::mem;
+
+/// The result of `Node::census`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Census {
+    /// The number of nodes at each height. `nodes[0]` is the number of leaves.
+    pub nodes: Vec<usize>,
+    /// The number of internal nodes having `n` children, for each `n`.
+    pub children: Vec<usize>,
+    /// The number of leaves by size: `leaf_sizes[0]` counts leaves of size 0, and `leaf_sizes[i]`
+    /// counts leaves of size in `2^(i-1)..2^i`.
+    pub leaf_sizes: Vec<usize>,
+    /// The maximum number of children of an internal node.
+    pub max_children: usize,
+}
+
+impl Census {
+    /// The height of the tree.
+    pub fn height(&self) -> usize {
+        self.nodes.len() - 1
+    }
+
+    /// The average number of children of internal nodes, as a fraction of the maximum. Returns
+    /// 1.0 if there are no internal nodes.
+    pub fn fill_factor(&self) -> f64 {
+        let internal: usize = self.children.iter().sum();
+        if internal == 0 {
+            return 1.0;
+        }
+        let total: usize = self.children.iter().enumerate().map(|(n, count)| n * count).sum();
+        total as f64 / (internal * self.max_children) as f64
+    }
+}
+
+/// The result of `Node::shared_with`.
+///
+/// Only the arrays of children of internal nodes are counted, since those are the allocations that
+/// trees share (leaves are stored within them). Bytes are the capacity of those arrays, excluding
+/// any heap data owned by the leaves.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SharingStats {
+    /// The number of internal nodes reachable from the tree.
+    pub nodes: usize,
+    /// The number of those nodes also reachable from the other tree.
+    pub shared_nodes: usize,
+    /// The number of bytes of all nodes reachable from the tree.
+    pub bytes: usize,
+    /// The number of bytes of the shared nodes.
+    pub shared_bytes: usize,
+}
+
+impl SharingStats {
+    /// The number of bytes the tree takes in addition to the other tree.
+    pub fn unshared_bytes(&self) -> usize {
+        self.bytes - self.shared_bytes
+    }
+}
+
+impl<L: Leaf, NP: NodesPtr<L>> Node<L, NP> {
+    /// Count the nodes of this tree by height and by number of children. All leaves are counted
+    /// as size 1 in `leaf_sizes`; see `census_with` for a custom leaf size.
+    ///
+    /// Time: O(n)
+    pub fn census(&self) -> Census {
+        self.census_with(|_| 1)
+    }
+
+    /// Same as `census`, but with `leaf_size` used for computing `Census::leaf_sizes`.
+    pub fn census_with<F>(&self, leaf_size: F) -> Census where F: Fn(&L) -> usize {
+        fn visit<L, NP, F>(node: &Node<L, NP>, leaf_size: &F, census: &mut Census)
+            where L: Leaf, NP: NodesPtr<L>, F: Fn(&L) -> usize,
+        {
+            census.nodes[node.height()] += 1;
+            match node.leaf() {
+                Some(leaf) => {
+                    let size = leaf_size(leaf);
+                    let bucket = (64 - (size as u64).leading_zeros()) as usize;
+                    if census.leaf_sizes.len() <= bucket {
+                        census.leaf_sizes.resize(bucket + 1, 0);
+                    }
+                    census.leaf_sizes[bucket] += 1;
+                }
+                None => {
+                    census.children[node.children().len()] += 1;
+                    for child in node.children() {
+                        visit(child, leaf_size, census);
+                    }
+                }
+            }
+        }
+
+        let mut census = Census {
+            nodes: vec![0; self.height() + 1],
+            children: vec![0; NP::max_size() + 1],
+            leaf_sizes: Vec::new(),
+            max_children: NP::max_size(),
+        };
+        visit(self, &leaf_size, &mut census);
+        census
+    }
+
+    /// Count the nodes of this tree that are also reachable from `other`, by pointer identity.
+    /// Each node is counted once, even if it is reachable along several paths. This measures how
+    /// much memory a tree (such as an older version kept for undo) actually takes, given another.
+    ///
+    /// Time: O(n + m)
+    pub fn shared_with(&self, other: &Node<L, NP>) -> SharingStats {
+        fn collect<L, NP>(node: &Node<L, NP>, ids: &mut HashSet<*const Node<L, NP>>)
+            where L: Leaf, NP: NodesPtr<L>,
+        {
+            if !node.is_leaf() && ids.insert(node.children().as_ptr()) {
+                for child in node.children() {
+                    collect(child, ids);
+                }
+            }
+        }
+
+        let (mut ids, mut other_ids) = (HashSet::new(), HashSet::new());
+        collect(self, &mut ids);
+        collect(other, &mut other_ids);
+        let shared = ids.intersection(&other_ids).count();
+        let node_bytes = NP::max_size() * mem::size_of::<Node<L, NP>>();
+        SharingStats {
+            nodes: ids.len(),
+            shared_nodes: shared,
+            bytes: ids.len() * node_bytes,
+            shared_bytes: shared * node_bytes,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use test_help::*;
+
+    use std::iter::FromIterator;
+
+    #[test]
+    fn census() {
+        let node = CursorMutT::from_iter((0..256).map(ListLeaf)).into_root().unwrap();
+        let census = node.census_with(|leaf| leaf.0);
+        assert_eq!(census.height(), 2);
+        assert_eq!(census.nodes, vec![256, 16, 1]);
+        assert_eq!(census.children[16], 17);
+        assert_eq!(census.fill_factor(), 1.0);
+        assert_eq!(census.leaf_sizes, vec![1, 1, 2, 4, 8, 16, 32, 64, 128]);
+
+        let census = NodeRc::from_leaf(ListLeaf(0)).census();
+        assert_eq!(census.nodes, vec![1]);
+        assert_eq!(census.leaf_sizes, vec![0, 1]);
+    }
+
+    #[test]
+    fn shared_with() {
+        let node: NodeRc<_> = (0..1000).map(ListLeaf).collect();
+        let stats = node.shared_with(&node.clone());
+        assert_eq!(stats.nodes, node.census().nodes[1..].iter().sum::<usize>());
+        assert_eq!((stats.shared_nodes, stats.unshared_bytes()), (stats.nodes, 0));
+
+        let mut edited = node.clone();
+        edited.push_back(ListLeaf(1000));
+        let stats = edited.shared_with(&node);
+        assert!(stats.shared_nodes > 0 && stats.nodes - stats.shared_nodes <= edited.height() + 1);
+        assert_eq!(stats.unshared_bytes() * stats.shared_nodes,
+                   stats.shared_bytes * (stats.nodes - stats.shared_nodes));
+        let leaf = NodeRc::from_leaf(ListLeaf(0));
+        assert_eq!(leaf.shared_with(&node), Default::default());
+    }
+}
@@ -0,0 +1,104 @@
+//! Strategies for generating trees using proptest (requires the `proptest` feature).
+//!
+//! Useful for property-testing `Leaf` and `Info` implementations against trees of varying shapes,
+//! rather than only the well-filled trees produced by collecting leaves.
+
+use builder::TreeBuilder;
+use node::{Node, NodesPtr};
+use traits::Leaf;
+
+use arrayvec::ArrayVec;
+use proptest::collection::vec;
+use proptest::prelude::*;
+
+use std::fmt::Debug;
+use std::ops::Range;
+
+/// Generates non-empty trees with a number of leaves in `len`, each generated by `leaf`.
+///
+/// The trees are built by concatenating smaller trees of random sizes, which results in varying
+/// heights and fill factors (while still being balanced).
+pub fn node<L, NP, S>(leaf: S, len: Range<usize>) -> impl Strategy<Value=Node<L, NP>>
+    where L: Leaf + Debug, L::Info: Debug, NP: NodesPtr<L>, S: Strategy<Value=L>,
+{
+    assert!(len.start > 0, "trees cannot be empty");
+    let piece_len = 1..2 * NP::max_size() + 1;
+    (vec(leaf, len), vec(piece_len, 0..32)).prop_map(|(leaves, piece_lens)| {
+        let mut leaves = leaves.into_iter();
+        let mut root: Option<Node<L, NP>> = None;
+        for piece_len in piece_lens.into_iter().map(Some).chain(Some(None)) {
+            let builder: TreeBuilder<_, _> = match piece_len {
+                Some(piece_len) => leaves.by_ref().take(piece_len).collect(),
+                None => leaves.by_ref().collect(),
+            };
+            if let Some(piece) = builder.build() {
+                root = Some(match root {
+                    Some(root) => Node::concat(root, piece),
+                    None => piece,
+                });
+            }
+        }
+        root.unwrap()
+    })
+}
+
+/// Generates trees as `node` does, along with the index of a random leaf in it.
+pub fn node_and_index<L, NP, S>(leaf: S, len: Range<usize>)
+    -> impl Strategy<Value=(Node<L, NP>, usize)>
+    where L: Leaf + Debug, L::Info: Debug, NP: NodesPtr<L>, S: Strategy<Value=L>,
+{
+    node(leaf, len).prop_flat_map(|node| {
+        let count = node.leaves().count();
+        (Just(node), 0..count)
+    })
+}
+
+/// Generates trees of exactly `height`, with each leaf generated by `leaf`, and the number of
+/// children of each internal node chosen independently within the fill factors in `fill`.
+///
+/// A fill factor of 0.0 is the minimum number of children of a balanced node, and 1.0 the maximum.
+/// So trees of a specific shape can be targeted, such as ones with all nodes at minimum fill
+/// (`0.0..0.0`), which are the tallest trees for their number of leaves. Note that the number of
+/// leaves is exponential in `height`.
+pub fn shaped_node<L, NP, S>(leaf: S, height: usize, fill: Range<f64>) -> BoxedStrategy<Node<L, NP>>
+    where L: Leaf + Debug + 'static, L::Info: Debug, NP: NodesPtr<L> + 'static,
+          S: Strategy<Value=L> + Clone + 'static,
+{
+    assert!(0.0 <= fill.start && fill.start <= fill.end && fill.end <= 1.0, "invalid fill factors");
+    if height == 0 {
+        return leaf.prop_map(Node::from_leaf).boxed();
+    }
+    let (min, max) = (NP::max_size() / 2, NP::max_size());
+    let children = |fill: f64| min + (fill * (max - min) as f64).round() as usize;
+    let count = children(fill.start)..children(fill.end) + 1;
+    vec(shaped_node(leaf, height - 1, fill), count).prop_map(|nodes| {
+        Node::from_children(NP::new(nodes.into_iter().collect::<ArrayVec<_>>()))
+    }).boxed()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{node_and_index, shaped_node};
+    use node::Rc16;
+    use test_help::*;
+
+    use proptest::prelude::*;
+
+    proptest! {
+        #[test]
+        fn balanced((node, index) in node_and_index::<_, Rc16<_>, _>((0..100usize).prop_map(ListLeaf),
+                                                                    1..1000)) {
+            assert_balanced(&node);
+            assert_eq!(node.leaves().nth(index), node.get(index));
+        }
+
+        #[test]
+        fn shaped(min_fill in shaped_node::<_, Rc16<_>, _>(Just(ListLeaf(1)), 3, 0.0..0.0),
+                  any_fill in shaped_node::<_, Rc16<_>, _>(Just(ListLeaf(1)), 2, 0.0..1.0)) {
+            assert_balanced(&min_fill);
+            assert_eq!(min_fill.census().nodes, vec![512, 64, 8, 1]);
+            assert_balanced(&any_fill);
+            assert_eq!(any_fill.height(), 2);
+        }
+    }
+}
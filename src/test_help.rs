@@ -1,6 +1,6 @@
 use cursor::{Cursor, CursorMut};
-use node::{Node, Rc16};
-use traits::{Info, Leaf, PathInfo, SubOrd};
+use node::{Node, NodesPtr, Rc16};
+use traits::{Count, Info, KeyRange, Leaf, PathInfo, SubOrd};
 
 use std::cmp;
 
@@ -16,10 +16,39 @@ pub type CursorMutT<L> = CursorMut<L, ()>;
 /// A useful type alias for easy initialization of `Node`.
 pub type NodeRc<L> = Node<L, Rc16<L>>;
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+/// Asserts that all leaves are at the same depth, that every non-root internal node has at least
+/// half the maximum number of children, and that the info of every node is up-to-date.
+pub fn assert_balanced<L, NP>(root: &Node<L, NP>)
+    where L: Leaf, L::Info: PartialEq + ::std::fmt::Debug, NP: NodesPtr<L>,
+{
+    fn check<L, NP>(node: &Node<L, NP>, is_root: bool) -> L::Info
+        where L: Leaf, L::Info: PartialEq + ::std::fmt::Debug, NP: NodesPtr<L>,
+    {
+        match node.leaf() {
+            Some(leaf) => leaf.compute_info(),
+            None => {
+                let children = node.children();
+                assert!(is_root || children.len() >= NP::max_size() / 2, "underfull node");
+                let mut info = None;
+                for child in children {
+                    assert_eq!(child.height() + 1, node.height());
+                    let child_info = check(child, false);
+                    info = Some(info.map_or(child_info, |info: L::Info| info.gather(child_info)));
+                }
+                assert_eq!(info, Some(node.info()));
+                node.info()
+            }
+        }
+    }
+    check(root, true);
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "rkyv", derive(::rkyv::Archive, ::rkyv::Serialize))]
 pub struct ListLeaf(pub usize);
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "rkyv", derive(::rkyv::Archive, ::rkyv::Serialize, ::rkyv::Deserialize))]
 pub struct ListInfo {
     pub count: usize,
     pub sum: usize,
@@ -43,6 +72,36 @@ impl Leaf for ListLeaf {
     }
 }
 
+impl ::binary::LeafCodec for ListLeaf {
+    fn encode<W: ::std::io::Write>(&self, out: &mut W) -> ::std::io::Result<()> {
+        ::binary::write_varint(out, self.0 as u64)
+    }
+
+    fn decode<R: ::std::io::Read>(input: &mut R) -> ::std::io::Result<Self> {
+        ::binary::read_varint(input).map(|value| ListLeaf(value as usize))
+    }
+}
+
+#[cfg(feature = "serde")]
+impl ::serde::Serialize for ListLeaf {
+    fn serialize<S: ::serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.0.serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> ::serde::Deserialize<'de> for ListLeaf {
+    fn deserialize<D: ::serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        usize::deserialize(deserializer).map(ListLeaf)
+    }
+}
+
+impl Count for ListInfo {
+    fn count(&self) -> usize {
+        self.count
+    }
+}
+
 impl Info for ListInfo {
     fn gather(self, other: Self) -> Self {
         ListInfo {
@@ -50,6 +109,10 @@ impl Info for ListInfo {
             sum: self.sum + other.sum,
         }
     }
+
+    fn paranoid_eq(&self, other: &Self) -> Option<bool> {
+        Some(self == other)
+    }
 }
 
 impl PathInfo<ListInfo> for ListPath {
@@ -90,7 +153,7 @@ impl SubOrd<ListPath> for ListRun {
 #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
 pub struct SetLeaf(pub char, pub usize);
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct SetInfo {
     pub min: SetLeaf,
     pub max: SetLeaf,
@@ -118,6 +181,22 @@ impl Info for SetInfo {
             max: cmp::max(self.max, other.max),
         }
     }
+
+    fn paranoid_eq(&self, other: &Self) -> Option<bool> {
+        Some(self == other)
+    }
+}
+
+impl KeyRange for SetInfo {
+    type Key = SetLeaf;
+
+    fn min_key(&self) -> SetLeaf {
+        self.min
+    }
+
+    fn max_key(&self) -> SetLeaf {
+        self.max
+    }
 }
 
 impl SubOrd<SetInfo> for MinChar {
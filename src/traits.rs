@@ -9,11 +9,93 @@ pub trait Leaf: Clone {
     fn compute_info(&self) -> Self::Info;
 }
 
+/// The value of a leaf that is never mutated, and so need not be `Clone`. See `leaf::ReadOnly`.
+pub trait ReadOnlyLeaf {
+    type Info: Info;
+
+    fn compute_info(&self) -> Self::Info;
+}
+
+/// A leaf that can be split into two parts.
+pub trait LeafSplit: Leaf {
+    /// Split the leaf into two at `at`, leaving the first part in `self` and returning the second.
+    /// The unit of `at` is defined by the implementation.
+    fn split_off(&mut self, at: usize) -> Self;
+}
+
+/// A leaf that can absorb the leaf following it, used to merge tiny leaves together.
+pub trait LeafMerge: Leaf {
+    /// Append `other`, the leaf right after this one, to `self` if the merged leaf would not be
+    /// too large for a leaf. Returns whether `other` was appended.
+    fn try_merge(&mut self, other: &Self) -> bool;
+}
+
+/// A leaf with a size limit, which is split into leaves within the limit by the operations that
+/// respect it (such as `CursorMut::leaf_update_limited`). This keeps append-heavy workloads from
+/// growing a few huge leaves.
+pub trait LeafLimit: LeafSplit {
+    /// The maximum weight of a leaf.
+    fn max_weight() -> usize;
+
+    /// The weight of a leaf having `info`.
+    fn weight(info: &Self::Info) -> usize;
+
+    /// The offset to split this leaf at (using `split_off`) when it is over the limit, such that
+    /// the first part is within the limit. Defaults to `max_weight()`.
+    fn split_point(&self) -> usize {
+        Self::max_weight()
+    }
+
+    /// Split this leaf into leaves that are within the limit, in order.
+    fn split_to_limit(mut self) -> Vec<Self> {
+        let mut parts = Vec::new();
+        while Self::weight(&self.compute_info()) > Self::max_weight() {
+            let at = self.split_point();
+            assert!(at > 0, "split_point should be positive");
+            let rest = self.split_off(at);
+            parts.push(self);
+            self = rest;
+        }
+        parts.push(self);
+        parts
+    }
+}
+
+/// A leaf that is a sequence of elements (such as the chars of a string chunk), which can be
+/// traversed one element at a time using `ElemCursor`.
+///
+/// Elements are located by offsets within the leaf, in the same unit as `LeafSplit`. An element may
+/// span more than one unit (such as a char spanning multiple bytes).
+pub trait LeafElems: Leaf {
+    type Elem;
+
+    /// The offset at the end of the leaf (i.e., its length in offset units).
+    fn end_offset(&self) -> usize;
+
+    /// Returns the element starting at `offset` along with its length. `offset` is less than
+    /// `end_offset()`, and at an element boundary.
+    fn elem_at(&self, offset: usize) -> (Self::Elem, usize);
+
+    /// Returns the element ending at `offset` along with its length. `offset` is positive, and at
+    /// an element boundary.
+    fn elem_before(&self, offset: usize) -> (Self::Elem, usize);
+}
+
 /// Metadata that need to be gathered hierarchically over the tree.
 pub trait Info: Copy {
     /// Used when gathering info from children to parent nodes. Should probably be commutative and
     /// associative.
     fn gather(self, other: Self) -> Self;
+
+    /// Whether two infos are equal, used by the `paranoid` feature to verify the infos of nodes
+    /// after every mutation. Returns `None` (the default) to skip the verification.
+    fn paranoid_eq(&self, _other: &Self) -> Option<bool> { None }
+}
+
+/// An info that counts some unit in the leaves (such as the number of leaves or elements), used
+/// for positional indexing.
+pub trait Count: Info {
+    fn count(&self) -> usize;
 }
 
 pub trait PathInfo<RHS=Self>: Copy where RHS: Info {
@@ -32,6 +114,21 @@ pub trait PathInfo<RHS=Self>: Copy where RHS: Info {
     fn identity() -> Self;
 }
 
+/// An info holding the range of keys of the leaves under a node, for trees whose leaves are sorted
+/// by key (such as sets and maps). See the `sorted` module.
+///
+/// Each leaf should hold a single key, i.e., `min_key() == max_key()` for the info of a leaf. The
+/// `sorted` module does not split leaves, so it cannot interleave leaves covering a range of keys.
+pub trait KeyRange: Info {
+    type Key: Ord;
+
+    /// The least key among the leaves.
+    fn min_key(&self) -> Self::Key;
+
+    /// The greatest key among the leaves.
+    fn max_key(&self) -> Self::Key;
+}
+
 /// Substructure ordering.
 ///
 /// Useful for comparing a structure having multiple fields with another having a subset of those
@@ -46,14 +143,47 @@ pub trait SubOrd<T> {
 
 // == End of Trait Definitions ==
 
+/// Returns `a + b`, for use in numeric `Info` and `PathInfo` impls. With the `checked` feature,
+/// this panics on overflow (in release builds too), instead of silently wrapping around and
+/// corrupting the positions in the tree.
+#[inline]
+pub fn metric_add(a: usize, b: usize) -> usize {
+    if cfg!(feature = "checked") {
+        a.checked_add(b).unwrap_or_else(|| panic!("metric overflow: {} + {}", a, b))
+    } else {
+        a + b
+    }
+}
+
+/// Returns `a - b`. See `metric_add`. Underflow usually indicates a buggy `extend_inv`.
+#[inline]
+pub fn metric_sub(a: usize, b: usize) -> usize {
+    if cfg!(feature = "checked") {
+        a.checked_sub(b).unwrap_or_else(|| panic!("metric underflow: {} - {}", a, b))
+    } else {
+        a - b
+    }
+}
+
 impl Info for () {
     #[inline]
     fn gather(self, _: ()) { }
+
+    #[inline]
+    fn paranoid_eq(&self, _: &()) -> Option<bool> { Some(true) }
 }
 
 impl Info for usize {
     #[inline]
-    fn gather(self, other: usize) -> usize { self + other }
+    fn gather(self, other: usize) -> usize { metric_add(self, other) }
+
+    #[inline]
+    fn paranoid_eq(&self, other: &usize) -> Option<bool> { Some(self == other) }
+}
+
+impl Count for usize {
+    #[inline]
+    fn count(&self) -> usize { *self }
 }
 
 impl<T> PathInfo<T> for () where T: Info {
@@ -69,10 +199,10 @@ impl<T> PathInfo<T> for () where T: Info {
 
 impl PathInfo for usize {
     #[inline]
-    fn extend(self, other: usize) -> usize { self + other }
+    fn extend(self, other: usize) -> usize { metric_add(self, other) }
 
     #[inline]
-    fn extend_inv(self, other: usize) -> usize { self - other }
+    fn extend_inv(self, other: usize) -> usize { metric_sub(self, other) }
 
     #[inline]
     fn identity() -> usize { 0 }
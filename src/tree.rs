@@ -0,0 +1,151 @@
+//! Trees that can be empty.
+//!
+//! A `Node` always has at least one leaf, so operations that can remove all leaves (such as
+//! splitting, or removing through a `CursorMut`) return an `Option<Node>`. `OptionNode` wraps such
+//! an optional tree, and supports the same kinds of operations with the empty tree handled, so
+//! that code downstream need not match on the option at every step.
+
+use builder::TreeBuilder;
+use cursor::CursorMut;
+use cursor::conf::CMutConf;
+use node::{concat_opt, Arc16, Node, NodesPtr};
+use traits::{Leaf, PathInfo, SubOrd};
+
+use std::iter::FromIterator;
+use std::ops::Range;
+
+/// A tree that may be empty. See the module documentation.
+pub struct OptionNode<L: Leaf, NP: NodesPtr<L> = Arc16<L>> {
+    root: Option<Node<L, NP>>,
+}
+
+impl<L: Leaf, NP: NodesPtr<L>> Clone for OptionNode<L, NP> {
+    fn clone(&self) -> Self {
+        OptionNode { root: self.root.clone() }
+    }
+}
+
+impl<L: Leaf, NP: NodesPtr<L>> Default for OptionNode<L, NP> {
+    fn default() -> Self {
+        OptionNode::empty()
+    }
+}
+
+impl<L: Leaf, NP: NodesPtr<L>> OptionNode<L, NP> {
+    /// The empty tree.
+    pub fn empty() -> Self {
+        OptionNode { root: None }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.root.is_none()
+    }
+
+    /// Returns the root, or `None` if the tree is empty.
+    pub fn root(&self) -> Option<&Node<L, NP>> {
+        self.root.as_ref()
+    }
+
+    pub fn into_root(self) -> Option<Node<L, NP>> {
+        self.root
+    }
+
+    /// The info gathered from all leaves, or `None` if the tree is empty.
+    pub fn info(&self) -> Option<L::Info> {
+        self.root.as_ref().map(Node::info)
+    }
+
+    /// Append `leaf` to the tree. See `Node::push_back`.
+    pub fn push_back(&mut self, leaf: L) {
+        self.root = concat_opt(self.root.take(), Some(Node::from_leaf(leaf)));
+    }
+
+    /// Prepend `leaf` to the tree. See `Node::push_back`.
+    pub fn push_front(&mut self, leaf: L) {
+        self.root = concat_opt(Some(Node::from_leaf(leaf)), self.root.take());
+    }
+
+    /// Concatenates two trees, either of which may be empty. See `Node::concat`.
+    pub fn concat(self, other: Self) -> Self {
+        OptionNode { root: concat_opt(self.root, other.root) }
+    }
+
+    /// Split the tree into three around `range`, as `Node::split3` does.
+    pub fn split3<PI, K>(self, range: Range<K>) -> (Self, Self, Self)
+        where PI: PathInfo<L::Info>, K: SubOrd<PI>,
+    {
+        match self.root {
+            Some(root) => {
+                let (left, middle, right) = root.split3(range);
+                (left.into(), middle.into(), right.into())
+            }
+            None => (Self::empty(), Self::empty(), Self::empty()),
+        }
+    }
+
+    /// Returns a `CursorMut` at the root of this tree, which is empty if the tree is.
+    pub fn into_cursor_mut<PI, CONF>(self) -> CursorMut<L, PI, CONF>
+        where PI: PathInfo<L::Info>, CONF: CMutConf<L, PI, Ptr=NP>,
+    {
+        match self.root {
+            Some(root) => CursorMut::from_node(root),
+            None => CursorMut::new(),
+        }
+    }
+}
+
+impl<L: Leaf, NP: NodesPtr<L>> From<Node<L, NP>> for OptionNode<L, NP> {
+    fn from(root: Node<L, NP>) -> Self {
+        OptionNode { root: Some(root) }
+    }
+}
+
+impl<L: Leaf, NP: NodesPtr<L>> From<Option<Node<L, NP>>> for OptionNode<L, NP> {
+    fn from(root: Option<Node<L, NP>>) -> Self {
+        OptionNode { root }
+    }
+}
+
+impl<L, PI, CONF> From<CursorMut<L, PI, CONF>> for OptionNode<L, CONF::Ptr>
+    where L: Leaf, PI: PathInfo<L::Info>, CONF: CMutConf<L, PI>,
+{
+    fn from(cursor: CursorMut<L, PI, CONF>) -> Self {
+        OptionNode { root: cursor.into_root() }
+    }
+}
+
+impl<L: Leaf, NP: NodesPtr<L>> FromIterator<L> for OptionNode<L, NP> {
+    fn from_iter<I: IntoIterator<Item=L>>(iter: I) -> Self {
+        OptionNode { root: iter.into_iter().collect::<TreeBuilder<_, _>>().build() }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::OptionNode;
+    use cursor::CursorMut;
+    use node::Rc16;
+    use test_help::*;
+
+    type OptionNodeRc<L> = OptionNode<L, Rc16<L>>;
+
+    #[test]
+    fn empty() {
+        let mut tree = OptionNodeRc::empty();
+        assert_eq!((tree.is_empty(), tree.info()), (true, None));
+        assert_eq!(tree.leaves().count(), 0);
+        tree.push_back(ListLeaf(1));
+        tree.push_front(ListLeaf(0));
+        let tree = OptionNode::empty().concat(tree).concat(OptionNode::empty());
+        assert!(tree.leaves().map(|leaf| leaf.0).eq(0..2));
+
+        let (left, middle, right) = tree.split3(ListIndex(0)..ListIndex(5));
+        assert!(left.is_empty() && right.is_empty());
+        let mut cursor: CursorMut<_, ListPath> = middle.into_cursor_mut();
+        cursor.remove_leaf();
+        cursor.remove_leaf();
+        let tree = OptionNodeRc::from(cursor);
+        assert!(tree.is_empty());
+        assert!(tree.into_iter().next().is_none());
+    }
+}
@@ -0,0 +1,128 @@
+//! A tree paired with a version number, for finding out what changed since a given version.
+
+use diff::{count_leaves, diff, Change};
+use node::{Node, NodesPtr};
+use traits::Leaf;
+
+use std::collections::VecDeque;
+use std::ops::Range;
+
+/// A tree with a version number that is incremented every time a new root is published.
+///
+/// The last few versions are retained (which only costs the nodes modified since), so that the
+/// changes since any of them can be computed by `changed_since`. Since the subtrees shared between
+/// versions are skipped, this takes time proportional to the size of the changes rather than the
+/// size of the tree. This lets observers (such as a UI) check cheaply whether the part of the
+/// tree they depend on has changed since they last looked at it.
+pub struct Versioned<L: Leaf, NP> {
+    versions: VecDeque<Option<Node<L, NP>>>, // the last one is the current version
+    version: u64,
+    limit: usize,
+}
+
+impl<L: Leaf, NP: NodesPtr<L>> Clone for Versioned<L, NP> {
+    fn clone(&self) -> Self {
+        Versioned {
+            versions: self.versions.clone(),
+            version: self.version,
+            limit: self.limit,
+        }
+    }
+}
+
+impl<L: Leaf, NP: NodesPtr<L>> Versioned<L, NP> {
+    /// Create a tree at version 0 with `root`, retaining at most `limit` past versions.
+    pub fn new(root: Option<Node<L, NP>>, limit: usize) -> Self {
+        let mut versions = VecDeque::new();
+        versions.push_back(root);
+        Versioned { versions, version: 0, limit }
+    }
+
+    /// The current version number.
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    /// The root of the current version.
+    pub fn root(&self) -> Option<&Node<L, NP>> {
+        self.versions.back().unwrap().as_ref()
+    }
+
+    /// Publish `root` as the next version, and return its version number.
+    pub fn publish(&mut self, root: Option<Node<L, NP>>) -> u64 {
+        self.versions.push_back(root);
+        if self.versions.len() > self.limit + 1 {
+            self.versions.pop_front();
+        }
+        self.version += 1;
+        self.version
+    }
+
+    /// Returns the root of `version`, or `None` if it is not retained (or not published yet).
+    pub fn get(&self, version: u64) -> Option<Option<&Node<L, NP>>> {
+        let back = self.version.checked_sub(version)? as usize;
+        let index = self.versions.len().checked_sub(back + 1)?;
+        Some(self.versions[index].as_ref())
+    }
+
+    /// Returns the changes from `version` to the current version (see `diff::diff`), or `None` if
+    /// `version` is not retained.
+    pub fn changed_since(&self, version: u64) -> Option<Vec<Change>> where L: PartialEq {
+        let old = self.get(version)?;
+        Some(match (old, self.root()) {
+            (Some(old), Some(new)) => diff(old, new),
+            (None, None) => Vec::new(),
+            (old, new) => {
+                let (old, new) = (old.map_or(0, count_leaves), new.map_or(0, count_leaves));
+                vec![Change { old: 0..old, new: 0..new }]
+            }
+        })
+    }
+
+    /// Returns whether any of the leaves in `range` (of leaf indices in the current version) were
+    /// changed since `version`. Removing leaves counts as changing the leaf after them (if any).
+    /// Returns `true` if `version` is not retained.
+    pub fn range_changed_since(&self, version: u64, range: Range<usize>) -> bool
+        where L: PartialEq,
+    {
+        match self.changed_since(version) {
+            Some(changes) => changes.iter().any(|change| {
+                let end = if change.new.is_empty() { change.new.end + 1 } else { change.new.end };
+                change.new.start < range.end && range.start < end
+            }),
+            None => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Versioned;
+    use diff::Change;
+    use test_help::*;
+
+    #[test]
+    fn changed_since() {
+        let root: NodeRc<_> = (0..1000).map(ListLeaf).collect();
+        let mut versioned = Versioned::new(Some(root.clone()), 2);
+        let mut cursor_mut = ::cursor::CursorMut::<_, ListPath>::from_node(root);
+        cursor_mut.goto_min(ListIndex(500));
+        cursor_mut.leaf_update(|leaf| leaf.0 = 0);
+        assert_eq!(versioned.publish(cursor_mut.clone().into_root()), 1);
+        cursor_mut.goto_min(ListIndex(700));
+        cursor_mut.remove_leaf();
+        assert_eq!(versioned.publish(cursor_mut.clone().into_root()), 2);
+
+        assert_eq!(versioned.changed_since(2), Some(vec![]));
+        assert_eq!(versioned.changed_since(1), Some(vec![Change { old: 700..701, new: 700..700 }]));
+        assert_eq!(versioned.changed_since(0).unwrap().len(), 2);
+        assert!(versioned.range_changed_since(1, 600..701));
+        assert!(!versioned.range_changed_since(1, 0..700));
+        assert!(!versioned.range_changed_since(0, 501..700));
+
+        versioned.publish(None);
+        assert_eq!(versioned.changed_since(0), None);
+        assert_eq!(versioned.changed_since(2), Some(vec![Change { old: 0..999, new: 0..0 }]));
+        assert_eq!(versioned.changed_since(4), None);
+    }
+}